@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// A flat hash of `len` entries with string keys, in an order that isn't
+/// sorted, so that building the map can't lean on the keys arriving in order.
+fn flat_hash(len: usize) -> String {
+    let entries: Vec<String> = (0..len)
+        .map(|index| format!(r#""key{}" => {}"#, index.wrapping_mul(7919) % len, index))
+        .collect();
+    format!("{{{}}}", entries.join(", "))
+}
+
+/// Flat hashes of growing size, so that the time per entry shows whether
+/// building them stays close to linear.
+fn large_flat_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_flat_hash");
+    group.sample_size(20);
+    for len in [1_000, 10_000, 100_000] {
+        let input = flat_hash(len);
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &input, |b, input| {
+            b.iter(|| nompom::parse(input).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// A Rails-log style hash of symbol keys and plain values, parsed by the
+/// fast path and by the general parser.
+fn flat_symbol_hash(c: &mut Criterion) {
+    let entries: Vec<String> = (0..10_000)
+        .map(|index| match index % 4 {
+            0 => format!(":id{} => {}", index, index),
+            1 => format!(r#":name{} => "user {}""#, index, index),
+            2 => format!(":admin{} => false", index),
+            _ => format!(":score{} => {}.5", index, index),
+        })
+        .collect();
+    let input = format!("{{{}}}", entries.join(", "));
+    let mut group = c.benchmark_group("flat_symbol_hash");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("parse_flat_symbol_hash", |b| {
+        b.iter(|| nompom::parse_flat_symbol_hash(&input).unwrap())
+    });
+    group.bench_function("parse", |b| b.iter(|| nompom::parse(&input).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, large_flat_hash, flat_symbol_hash);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Clones a parsed tree of a thousand users, as a plain value and shared.
+fn clone(c: &mut Criterion) {
+    let users: Vec<String> = (0..1_000)
+        .map(|index| {
+            format!(
+                r#"{{:id => {}, :name => "user {}", :tags => [:a, :b, :c], :address => {{:city => "x", :zip => "{:05}"}}}}"#,
+                index, index, index
+            )
+        })
+        .collect();
+    let value = nompom::parse(&format!("[{}]", users.join(", "))).unwrap();
+    let shared = value.clone().into_shared();
+
+    let mut group = c.benchmark_group("clone");
+    group.bench_function("value", |b| b.iter(|| black_box(&value).clone()));
+    group.bench_function("shared", |b| b.iter(|| black_box(&shared).clone()));
+    group.finish();
+}
+
+criterion_group!(benches, clone);
+criterion_main!(benches);
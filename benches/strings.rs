@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// An array of short strings that are mostly escapes, each of which is
+/// decoded through the parser's reused scratch buffer.
+fn escaped_string_array(c: &mut Criterion) {
+    let element = r#""a\tb\\c\"dé\n\x41\101""#;
+    let input = format!("[{}]", vec![element; 10_000].join(", "));
+    let mut group = c.benchmark_group("escaped_string_array");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("parse", |b| b.iter(|| nompom::parse(&input).unwrap()));
+    group.finish();
+}
+
+/// One string of up to 1MB alternating escapes and plain runs, at growing
+/// lengths, so that the time per byte shows whether the scan stays linear.
+fn long_escaped_string(c: &mut Criterion) {
+    let mut group = c.benchmark_group("long_escaped_string");
+    for len in [10_000, 100_000, 1_000_000] {
+        let input = format!("\"{}\"", r#"abc\n\"d\\"#.repeat(len / 10));
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &input, |b, input| {
+            b.iter(|| nompom::parse(input).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, escaped_string_array, long_escaped_string);
+criterion_main!(benches);
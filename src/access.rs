@@ -0,0 +1,412 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt, ops};
+
+use crate::Value;
+
+/// A key or array index that can look up a value inside a [`Value`], for use
+/// with [`Value::get`] and the `[]` operator. Implemented for `usize` and for
+/// string keys.
+pub trait ValueIndex {
+    #[doc(hidden)]
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>;
+
+    #[doc(hidden)]
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value;
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Array(values) => values.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match value {
+            Value::Array(values) => values.get_mut(*self),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        if let Value::Null = value {
+            *value = Value::Array(Vec::new());
+        }
+        match value {
+            Value::Array(values) => {
+                let len = values.len();
+                if *self == len {
+                    values.push(Value::Null);
+                }
+                values.get_mut(*self).unwrap_or_else(|| {
+                    panic!(
+                        "index {} is out of bounds for an array of length {}",
+                        self, len
+                    )
+                })
+            }
+            other => panic!("cannot index into {} with an integer", other.type_name()),
+        }
+    }
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Object(map) | Value::Struct { fields: map, .. } => map.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match value {
+            Value::Object(map) | Value::Struct { fields: map, .. } => map.get_mut(self),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        if let Value::Null = value {
+            *value = Value::Object(Default::default());
+        }
+        match value {
+            Value::Object(map) | Value::Struct { fields: map, .. } => {
+                map.entry(self.to_string()).or_insert(Value::Null)
+            }
+            other => panic!("cannot index into {} with a key", other.type_name()),
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_str().index_into_mut(value)
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        self.as_str().index_or_insert(value)
+    }
+}
+
+impl<T: ValueIndex + ?Sized> ValueIndex for &T {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(value)
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        (**self).index_or_insert(value)
+    }
+}
+
+/// One step of a path for [`Value::get_path`]: a key of an object or struct,
+/// or an element of an array. Unlike a JSON pointer, keys are used as they
+/// are, so a key with a `/` or `~` in it needs no escaping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl ValueIndex for PathSegment<'_> {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match self {
+            PathSegment::Key(key) => key.index_into(value),
+            PathSegment::Index(index) => index.index_into(value),
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match self {
+            PathSegment::Key(key) => key.index_into_mut(value),
+            PathSegment::Index(index) => index.index_into_mut(value),
+        }
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        match self {
+            PathSegment::Key(key) => key.index_or_insert(value),
+            PathSegment::Index(index) => index.index_or_insert(value),
+        }
+    }
+}
+
+impl Value {
+    /// Looks up a key of an object or struct, or an element of an array.
+    /// Returns `None` if there is no such key or element, or if the value is
+    /// some other kind.
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// Like [`Value::get`], but returns a mutable reference.
+    pub fn get_mut<I: ValueIndex>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    /// Looks up a key of an object or struct like [`Value::get`], but ignoring
+    /// case, so `"name"` also finds `"Name"` or `"NAME"`. A key that matches
+    /// exactly wins, and otherwise the first match in key order, which puts
+    /// uppercase before lowercase: with both `"NAME"` and `"Name"`, `"name"`
+    /// finds `"NAME"`. Case is folded per character as by
+    /// [`char::to_lowercase`].
+    pub fn get_ci(&self, key: &str) -> Option<&Value> {
+        let map = match self {
+            Value::Object(map) | Value::Struct { fields: map, .. } => map,
+            _ => return None,
+        };
+        map.get(key).or_else(|| {
+            let key: Vec<char> = key.chars().flat_map(char::to_lowercase).collect();
+            map.iter()
+                .find(|(candidate, _)| {
+                    candidate
+                        .chars()
+                        .flat_map(char::to_lowercase)
+                        .eq(key.iter().copied())
+                })
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// Follows `segments` down from this value, as in
+    /// `value.get_path(&[Key("users"), Index(0), Key("name")])`. Returns
+    /// `None` if any step is missing. The empty path is this value.
+    pub fn get_path(&self, segments: &[PathSegment]) -> Option<&Value> {
+        segments
+            .iter()
+            .try_fold(self, |value, segment| segment.index_into(value))
+    }
+
+    /// Like [`Value::get_path`], but returns a mutable reference.
+    pub fn get_path_mut(&mut self, segments: &[PathSegment]) -> Option<&mut Value> {
+        segments
+            .iter()
+            .try_fold(self, |value, segment| segment.index_into_mut(value))
+    }
+
+    /// The first element of an array. Returns `None` if the array is empty,
+    /// or if the value is some other kind.
+    pub fn first(&self) -> Option<&Value> {
+        match self {
+            Value::Array(values) => values.first(),
+            _ => None,
+        }
+    }
+
+    /// Like [`Value::first`], but returns a mutable reference.
+    pub fn first_mut(&mut self) -> Option<&mut Value> {
+        match self {
+            Value::Array(values) => values.first_mut(),
+            _ => None,
+        }
+    }
+
+    /// The last element of an array. Returns `None` if the array is empty,
+    /// or if the value is some other kind.
+    pub fn last(&self) -> Option<&Value> {
+        match self {
+            Value::Array(values) => values.last(),
+            _ => None,
+        }
+    }
+
+    /// Like [`Value::last`], but returns a mutable reference.
+    pub fn last_mut(&mut self) -> Option<&mut Value> {
+        match self {
+            Value::Array(values) => values.last_mut(),
+            _ => None,
+        }
+    }
+
+    /// Replaces the value at the JSON pointer `path`, such as `/users/0/name`,
+    /// with `new`. The empty path replaces the whole value.
+    ///
+    /// The last segment may name a new key of an object, or the end of an
+    /// array, written as its length or `-`, to append. Everything before it
+    /// has to exist unless `create` is set, in which case missing keys and
+    /// `nil` values along the way become objects, and arrays are padded with
+    /// `nil` up to an index past their end, by at most 1024 elements, past
+    /// which the index is out of bounds.
+    pub fn set_pointer(
+        &mut self,
+        path: &str,
+        new: Value,
+        create: bool,
+    ) -> Result<(), PointerError> {
+        if path.is_empty() {
+            *self = new;
+            return Ok(());
+        }
+        if !path.starts_with('/') {
+            return Err(PointerError {
+                path: path.to_string(),
+                kind: PointerErrorKind::Syntax,
+            });
+        }
+
+        let mut current = self;
+        let mut segments = path[1..].split('/').peekable();
+        let mut end = 0;
+        while let Some(segment) = segments.next() {
+            end += 1 + segment.len();
+            let error = |kind| PointerError {
+                path: path[..end].to_string(),
+                kind,
+            };
+            let last = segments.peek().is_none();
+            let segment = unescape_segment(segment);
+
+            if create && matches!(current, Value::Null) {
+                *current = Value::Object(BTreeMap::new());
+            }
+            let slot = match current {
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    if last || create {
+                        map.entry(segment.into_owned()).or_insert(Value::Null)
+                    } else {
+                        map.get_mut(&*segment)
+                            .ok_or_else(|| error(PointerErrorKind::Missing))?
+                    }
+                }
+                Value::Array(values) => {
+                    let len = values.len();
+                    let index = match &*segment {
+                        "-" => len,
+                        segment => array_index(segment)
+                            .ok_or_else(|| error(PointerErrorKind::InvalidIndex))?,
+                    };
+                    let appending = index == len && last;
+                    if index >= len && !appending && !create {
+                        return Err(error(PointerErrorKind::OutOfBounds { len }));
+                    }
+                    if index >= len {
+                        let new_len = index
+                            .checked_add(1)
+                            .filter(|new_len| new_len - len <= MAX_POINTER_PADDING + 1)
+                            .ok_or_else(|| error(PointerErrorKind::OutOfBounds { len }))?;
+                        values.resize(new_len, Value::Null);
+                    }
+                    &mut values[index]
+                }
+                other => {
+                    return Err(error(PointerErrorKind::Scalar {
+                        found: other.type_name(),
+                    }))
+                }
+            };
+
+            if last {
+                *slot = new;
+                return Ok(());
+            }
+            current = slot;
+        }
+        unreachable!()
+    }
+}
+
+/// How many `nil` elements [`Value::set_pointer`] may pad an array with to
+/// reach an index past its end, so that a pointer such as `/100000000000`
+/// can't allocate without bound.
+const MAX_POINTER_PADDING: usize = 1024;
+
+/// Decodes `~1` and `~0`, which stand for `/` and `~` in pointer segments.
+pub(crate) fn unescape_segment(segment: &str) -> Cow<'_, str> {
+    if segment.contains('~') {
+        Cow::Owned(segment.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(segment)
+    }
+}
+
+/// Parses an array index, which JSON pointers write without leading zeros.
+fn array_index(segment: &str) -> Option<usize> {
+    let digits = segment.bytes().all(|b| b.is_ascii_digit());
+    if !digits || segment.is_empty() || (segment.len() > 1 && segment.starts_with('0')) {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+/// An error from [`Value::set_pointer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointerError {
+    /// The path up to and including the segment that couldn't be followed.
+    pub path: String,
+    pub kind: PointerErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerErrorKind {
+    /// The path is neither empty nor starts with `/`.
+    Syntax,
+    /// An array was indexed with something other than a number or `-`.
+    InvalidIndex,
+    /// A key along the path is missing.
+    Missing,
+    /// An index along the path is past the end of an array.
+    OutOfBounds { len: usize },
+    /// A value along the path has no keys or elements.
+    Scalar { found: &'static str },
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = &self.path;
+        match self.kind {
+            PointerErrorKind::Syntax => write!(f, "{}: pointer must start with '/'", path),
+            PointerErrorKind::InvalidIndex => write!(f, "{}: invalid array index", path),
+            PointerErrorKind::Missing => write!(f, "{}: missing key", path),
+            PointerErrorKind::OutOfBounds { len } => write!(
+                f,
+                "{}: index is out of bounds for an array of length {}",
+                path, len
+            ),
+            PointerErrorKind::Scalar { found } => {
+                write!(f, "{}: cannot index into {}", path, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+static NULL: Value = Value::Null;
+
+/// Looks up a key or an array element like [`Value::get`], but returns `nil`
+/// instead of `None`, so that `value["a"][0]["b"]` never panics.
+impl<I: ValueIndex> ops::Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+/// Looks up a key or an array element for assignment, as in
+/// `value["a"][0] = Value::from(1)`.
+///
+/// A missing key is inserted with a `nil` value, and `nil` itself is first
+/// replaced by an empty object or array, so nested assignments build up the
+/// structure along the way. An array index may be at most the array's length,
+/// which appends a `nil` element.
+///
+/// # Panics
+///
+/// Panics if the index is past the end of an array, or if the value is
+/// neither `nil` nor the kind of value the index is for.
+impl<I: ValueIndex> ops::IndexMut<I> for Value {
+    fn index_mut(&mut self, index: I) -> &mut Value {
+        index.index_or_insert(self)
+    }
+}
@@ -0,0 +1,115 @@
+//! Generating random values for property tests with quickcheck.
+
+use quickcheck::{Arbitrary, Gen};
+
+use crate::{ObjectKey, Value};
+
+/// How deeply generated objects, structs and arrays nest.
+const MAX_DEPTH: usize = 4;
+
+/// The most entries generated objects, structs and arrays have.
+const MAX_LEN: usize = 8;
+
+/// Generates values of every kind, nested at most a few levels deep and with
+/// at most a few entries per level. Every generated value renders with
+/// `Display` to Ruby source that parses back to an equal value, so floats are
+/// always finite, constants and struct names and fields are drawn from a
+/// fixed set of valid names, and there are no frozen strings, which only
+/// parse back with options set. Object keys are of every kind that
+/// [`ObjectKey`] tells apart.
+impl Arbitrary for Value {
+    fn arbitrary(g: &mut Gen) -> Self {
+        arbitrary_value(g, MAX_DEPTH)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Value::Bytes(string) => Box::new(string.shrink().map(Value::Bytes)),
+            Value::FrozenBytes(string) => Box::new(string.shrink().map(Value::FrozenBytes)),
+            Value::RawBytes(bytes) => Box::new(bytes.shrink().map(Value::RawBytes)),
+            Value::Symbol(name) => Box::new(name.shrink().map(Value::Symbol)),
+            Value::Integer(integer) => Box::new(integer.shrink().map(Value::Integer)),
+            Value::Float(float) => Box::new(
+                float
+                    .shrink()
+                    .filter(|float| float.is_finite())
+                    .map(Value::Float),
+            ),
+            Value::Boolean(boolean) => Box::new(boolean.shrink().map(Value::Boolean)),
+            // Shrinking the keys would make text that isn't a stored key.
+            Value::Object(map) => {
+                let keys: Vec<String> = map.keys().cloned().collect();
+                let values: Vec<Value> = map.values().cloned().collect();
+                Box::new(
+                    values.shrink().map(move |values| {
+                        Value::Object(keys.iter().cloned().zip(values).collect())
+                    }),
+                )
+            }
+            Value::Array(values) => Box::new(values.shrink().map(Value::Array)),
+            Value::Constant(_) | Value::Struct { .. } | Value::Null | Value::Recursive => {
+                quickcheck::empty_shrinker()
+            }
+            #[cfg(feature = "decimal")]
+            Value::Decimal(_) => quickcheck::empty_shrinker(),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(_) | Value::Date(_) => quickcheck::empty_shrinker(),
+        }
+    }
+}
+
+fn arbitrary_value(g: &mut Gen, depth: usize) -> Value {
+    let kinds = if depth == 0 { 7 } else { 10 };
+    match u8::arbitrary(g) % kinds {
+        0 => Value::Bytes(String::arbitrary(g)),
+        1 => Value::Symbol(String::arbitrary(g)),
+        2 => Value::Constant(choose(g, &["Foo", "Foo::Bar", "ActiveSupport::TimeZone"])),
+        3 => Value::Integer(i64::arbitrary(g)),
+        4 => Value::Float(finite_float(g)),
+        5 => Value::Boolean(bool::arbitrary(g)),
+        6 => Value::Null,
+        7 => Value::Object(
+            (0..len(g))
+                .map(|_| (arbitrary_key(g), arbitrary_value(g, depth - 1)))
+                .collect(),
+        ),
+        8 => Value::Struct {
+            name: choose(g, &["", "Point", "Foo::Bar"]),
+            fields: (0..len(g))
+                .map(|_| {
+                    let field = choose(g, &["a", "b", "name", "x_1"]);
+                    (field, arbitrary_value(g, depth - 1))
+                })
+                .collect(),
+        },
+        _ => Value::Array((0..len(g)).map(|_| arbitrary_value(g, depth - 1)).collect()),
+    }
+}
+
+fn arbitrary_key(g: &mut Gen) -> String {
+    let text = String::arbitrary(g);
+    let key = match u8::arbitrary(g) % 6 {
+        0 | 1 => ObjectKey::Symbol(&text),
+        2 => ObjectKey::Nil,
+        3 => ObjectKey::Boolean(bool::arbitrary(g)),
+        _ => ObjectKey::String(&text),
+    };
+    key.stored().into_owned()
+}
+
+fn len(g: &mut Gen) -> usize {
+    usize::arbitrary(g) % (g.size().min(MAX_LEN) + 1)
+}
+
+fn choose(g: &mut Gen, names: &[&str]) -> String {
+    g.choose(names).copied().unwrap_or_default().to_string()
+}
+
+fn finite_float(g: &mut Gen) -> f64 {
+    let float = f64::arbitrary(g);
+    if float.is_finite() {
+        float
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,140 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    parse_with_options,
+    split::{Event, Split, Splitter},
+    ParseError, ParseOptions, ReadError, Value,
+};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Values parsed incrementally from an [`AsyncRead`], returned by
+/// [`parse_from_async_read`] and [`parse_array_from_async_read`].
+///
+/// Only the value currently being read is buffered. A value that fails to
+/// parse is reported and reading carries on with the next one.
+#[derive(Debug)]
+pub struct AsyncValues<R> {
+    reader: R,
+    options: ParseOptions,
+    splitter: Splitter,
+    buffer: Vec<u8>,
+    /// How many bytes of the stream were dropped from the front of `buffer`.
+    consumed: usize,
+    eof: bool,
+    done: bool,
+}
+
+/// Reads whitespace separated top-level values from `reader`.
+pub fn parse_from_async_read<R: AsyncRead + Unpin>(reader: R) -> AsyncValues<R> {
+    AsyncValues::new(reader, Split::Values)
+}
+
+/// Reads the elements of a single top-level array from `reader`, so that a
+/// large array doesn't have to be held in memory all at once.
+pub fn parse_array_from_async_read<R: AsyncRead + Unpin>(reader: R) -> AsyncValues<R> {
+    AsyncValues::new(reader, Split::ArrayElements)
+}
+
+impl<R: AsyncRead + Unpin> AsyncValues<R> {
+    fn new(reader: R, split: Split) -> Self {
+        AsyncValues {
+            reader,
+            options: ParseOptions::default(),
+            splitter: Splitter::new(split),
+            buffer: Vec::new(),
+            consumed: 0,
+            eof: false,
+            done: false,
+        }
+    }
+
+    /// Parses each value with `options` rather than the defaults. Of the
+    /// [`line_comments`](ParseOptions::line_comments), only `#` is skipped
+    /// when finding where values end.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.splitter
+            .set_comments(options.line_comments.iter().any(|prefix| prefix == "#"));
+        self.options = options;
+        self
+    }
+
+    /// Reads until the next value is complete and parses it. Returns `None` at
+    /// the end of the stream.
+    pub async fn next_value(&mut self) -> Option<Result<Value, ReadError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            let event = match self.splitter.next(&mut self.buffer, self.eof) {
+                None if self.eof => {
+                    self.done = true;
+                    self.splitter.finish(self.buffer.len())
+                }
+                event => event,
+            };
+            if let Some(event) = event {
+                match self.handle(event) {
+                    Some(result) => return Some(result),
+                    None => continue,
+                }
+            }
+            if self.eof {
+                return None;
+            }
+
+            let len = self.buffer.len();
+            self.buffer.resize(len + CHUNK_SIZE, 0);
+            match self.reader.read(&mut self.buffer[len..]).await {
+                Ok(read) => {
+                    self.buffer.truncate(len + read);
+                    self.eof = read == 0;
+                }
+                Err(err) => {
+                    self.buffer.truncate(len);
+                    self.done = true;
+                    return Some(Err(ReadError::Io(err)));
+                }
+            }
+        }
+    }
+
+    /// Turns a splitter event into the next result, if it produces one.
+    fn handle(&mut self, event: Event) -> Option<Result<Value, ReadError>> {
+        match event {
+            Event::Value(range) => {
+                let base = self.consumed + range.start;
+                let result = match std::str::from_utf8(&self.buffer[range.clone()]) {
+                    Ok(text) => parse_with_options(text, &self.options)
+                        .map_err(|err| ReadError::Parse(err.offset_by(base))),
+                    Err(err) => Err(ReadError::Io(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        err,
+                    ))),
+                };
+
+                self.buffer.drain(..range.end);
+                self.splitter.consume(range.end);
+                self.consumed += range.end;
+                Some(result)
+            }
+            // Anything but whitespace after the array is reported by the
+            // splitter as unexpected.
+            Event::End => None,
+            Event::Unexpected(at) => {
+                self.done = true;
+                let offset = self.consumed + at;
+                let message = if at == self.buffer.len() {
+                    "unexpected end of input"
+                } else {
+                    "unexpected input"
+                };
+                Some(Err(ReadError::Parse(ParseError::Syntax {
+                    offset,
+                    message: message.to_string(),
+                })))
+            }
+        }
+    }
+}
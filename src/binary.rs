@@ -0,0 +1,292 @@
+//! A compact binary encoding of values with bincode, for caching parsed
+//! values on disk.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{
+    de::{
+        self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+    },
+    ser::{Serialize, SerializeTupleVariant, Serializer},
+};
+
+use crate::{DeserializeError, Value};
+
+/// The variant names, indexed by the tag each is written with. Tags are
+/// fixed, whichever features are enabled, so that bytes written by one build
+/// can be read by another.
+const VARIANTS: &[&str] = &[
+    "Bytes",
+    "FrozenBytes",
+    "Symbol",
+    "Constant",
+    "Integer",
+    "Float",
+    "Boolean",
+    "Object",
+    "Struct",
+    "Array",
+    "Null",
+    "Decimal",
+    "DateTime",
+    "Date",
+    "RawBytes",
+    "Recursive",
+];
+
+impl Value {
+    /// Encodes the value with bincode, for reading back with
+    /// [`Value::from_bytes`]. This is much faster to write and read than
+    /// Ruby or JSON text, and keeps every variant, such as symbols and
+    /// frozen strings, apart. Decimals, times and dates are written as text,
+    /// so that their encoding doesn't depend on those crates' versions.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(&Encode(self)).unwrap_or_else(|_| unreachable!())
+    }
+
+    /// Decodes a value written by [`Value::to_bytes`]. This is meant for
+    /// values this crate wrote, such as a cache: the bytes aren't checked for
+    /// deep nesting or huge lengths, so they shouldn't come from untrusted
+    /// sources.
+    ///
+    /// Decimals, times and dates fail to decode without the `decimal` or
+    /// `chrono` feature.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Value, DeserializeError> {
+        bincode::deserialize::<Decode>(bytes)
+            .map(|decoded| decoded.0)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A value written as an enum with the tags of [`VARIANTS`].
+struct Encode<'a>(&'a Value);
+
+impl Serialize for Encode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = "Value";
+        match self.0 {
+            Value::Bytes(string) => {
+                serializer.serialize_newtype_variant(name, 0, VARIANTS[0], string)
+            }
+            Value::FrozenBytes(string) => {
+                serializer.serialize_newtype_variant(name, 1, VARIANTS[1], string)
+            }
+            Value::Symbol(string) => {
+                serializer.serialize_newtype_variant(name, 2, VARIANTS[2], string)
+            }
+            Value::Constant(string) => {
+                serializer.serialize_newtype_variant(name, 3, VARIANTS[3], string)
+            }
+            Value::Integer(integer) => {
+                serializer.serialize_newtype_variant(name, 4, VARIANTS[4], integer)
+            }
+            Value::Float(float) => {
+                serializer.serialize_newtype_variant(name, 5, VARIANTS[5], float)
+            }
+            Value::Boolean(boolean) => {
+                serializer.serialize_newtype_variant(name, 6, VARIANTS[6], boolean)
+            }
+            Value::Object(map) => {
+                serializer.serialize_newtype_variant(name, 7, VARIANTS[7], &Entries(map))
+            }
+            Value::Struct {
+                name: class,
+                fields,
+            } => {
+                let mut state = serializer.serialize_tuple_variant(name, 8, VARIANTS[8], 2)?;
+                state.serialize_field(class)?;
+                state.serialize_field(&Entries(fields))?;
+                state.end()
+            }
+            Value::Array(values) => {
+                serializer.serialize_newtype_variant(name, 9, VARIANTS[9], &Elements(values))
+            }
+            Value::Null => serializer.serialize_unit_variant(name, 10, VARIANTS[10]),
+            Value::Recursive => serializer.serialize_unit_variant(name, 15, VARIANTS[15]),
+            Value::RawBytes(bytes) => {
+                serializer.serialize_newtype_variant(name, 14, VARIANTS[14], bytes)
+            }
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => {
+                serializer.serialize_newtype_variant(name, 11, VARIANTS[11], &decimal.to_string())
+            }
+            #[cfg(feature = "chrono")]
+            Value::DateTime(time) => {
+                serializer.serialize_newtype_variant(name, 12, VARIANTS[12], &time.to_rfc3339())
+            }
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => {
+                serializer.serialize_newtype_variant(name, 13, VARIANTS[13], &date.to_string())
+            }
+        }
+    }
+}
+
+struct Entries<'a>(&'a BTreeMap<String, Value>);
+
+impl Serialize for Entries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, Encode(value))))
+    }
+}
+
+struct Elements<'a>(&'a [Value]);
+
+impl Serialize for Elements<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(Encode))
+    }
+}
+
+/// A value read from the enum that [`Encode`] writes.
+struct Decode(Value);
+
+impl<'de> Deserialize<'de> for Decode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Value", VARIANTS, DecodeVisitor)
+    }
+}
+
+struct DecodeVisitor;
+
+impl<'de> Visitor<'de> for DecodeVisitor {
+    type Value = Decode;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an encoded value")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Decode, A::Error> {
+        let (tag, variant) = data.variant::<u32>()?;
+        let value = match tag {
+            0 => Value::Bytes(variant.newtype_variant()?),
+            1 => Value::FrozenBytes(variant.newtype_variant()?),
+            2 => Value::Symbol(variant.newtype_variant()?),
+            3 => Value::Constant(variant.newtype_variant()?),
+            4 => Value::Integer(variant.newtype_variant()?),
+            5 => Value::Float(variant.newtype_variant()?),
+            6 => Value::Boolean(variant.newtype_variant()?),
+            7 => Value::Object(variant.newtype_variant::<DecodeEntries>()?.0),
+            8 => variant.tuple_variant(2, StructVisitor)?,
+            9 => Value::Array(variant.newtype_variant::<DecodeElements>()?.0),
+            10 => {
+                variant.unit_variant()?;
+                Value::Null
+            }
+            14 => Value::RawBytes(variant.newtype_variant()?),
+            15 => {
+                variant.unit_variant()?;
+                Value::Recursive
+            }
+            #[cfg(feature = "decimal")]
+            11 => {
+                let text: String = variant.newtype_variant()?;
+                Value::Decimal(text.parse().map_err(de::Error::custom)?)
+            }
+            #[cfg(feature = "chrono")]
+            12 => {
+                let text: String = variant.newtype_variant()?;
+                let time =
+                    chrono::DateTime::parse_from_rfc3339(&text).map_err(de::Error::custom)?;
+                Value::DateTime(time)
+            }
+            #[cfg(feature = "chrono")]
+            13 => {
+                let text: String = variant.newtype_variant()?;
+                Value::Date(text.parse().map_err(de::Error::custom)?)
+            }
+            #[cfg(not(feature = "decimal"))]
+            11 => return Err(de::Error::custom("decimals need the `decimal` feature")),
+            #[cfg(not(feature = "chrono"))]
+            12 | 13 => {
+                return Err(de::Error::custom(
+                    "times and dates need the `chrono` feature",
+                ))
+            }
+            _ => {
+                return Err(de::Error::invalid_value(
+                    de::Unexpected::Unsigned(tag.into()),
+                    &"a value tag",
+                ))
+            }
+        };
+        Ok(Decode(value))
+    }
+}
+
+struct StructVisitor;
+
+impl<'de> Visitor<'de> for StructVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a struct's name and fields")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let name = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let fields = seq
+            .next_element::<DecodeEntries>()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Value::Struct {
+            name,
+            fields: fields.0,
+        })
+    }
+}
+
+struct DecodeEntries(BTreeMap<String, Value>);
+
+impl<'de> Deserialize<'de> for DecodeEntries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = DecodeEntries;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DecodeEntries, A::Error> {
+                let mut entries = BTreeMap::new();
+                while let Some((key, value)) = map.next_entry::<String, Decode>()? {
+                    entries.insert(key, value.0);
+                }
+                Ok(DecodeEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+struct DecodeElements(Vec<Value>);
+
+impl<'de> Deserialize<'de> for DecodeElements {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ElementsVisitor;
+
+        impl<'de> Visitor<'de> for ElementsVisitor {
+            type Value = DecodeElements;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a sequence of values")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DecodeElements, A::Error> {
+                // The length comes from the input, so it only sizes small
+                // arrays up front.
+                let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(value) = seq.next_element::<Decode>()? {
+                    values.push(value.0);
+                }
+                Ok(DecodeElements(values))
+            }
+        }
+
+        deserializer.deserialize_seq(ElementsVisitor)
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+
+use crate::Value;
+
+/// Fluent builder for a `Value::Object`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectBuilder {
+    entries: BTreeMap<String, Value>,
+}
+
+impl ObjectBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `key` to `value`, replacing any earlier value for the same key.
+    pub fn key(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.entries.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Object(self.entries)
+    }
+}
+
+/// Fluent builder for a `Value::Array`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArrayBuilder {
+    elements: Vec<Value>,
+}
+
+impl ArrayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, value: impl Into<Value>) -> Self {
+        self.elements.push(value.into());
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Array(self.elements)
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use crate::Value;
+
+/// The kinds of [`Value`], as named by [`Value::type_name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Bytes,
+    Symbol,
+    Constant,
+    Integer,
+    Float,
+    #[cfg(feature = "decimal")]
+    Decimal,
+    #[cfg(feature = "chrono")]
+    DateTime,
+    #[cfg(feature = "chrono")]
+    Date,
+    Boolean,
+    Object,
+    Struct,
+    Array,
+    Null,
+    Recursive,
+}
+
+impl ValueType {
+    /// The same name as [`Value::type_name`] gives for values of this type.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::Bytes => "string",
+            ValueType::Symbol => "symbol",
+            ValueType::Constant => "constant",
+            ValueType::Integer => "integer",
+            ValueType::Float => "float",
+            #[cfg(feature = "decimal")]
+            ValueType::Decimal => "decimal",
+            #[cfg(feature = "chrono")]
+            ValueType::DateTime => "datetime",
+            #[cfg(feature = "chrono")]
+            ValueType::Date => "date",
+            ValueType::Boolean => "boolean",
+            ValueType::Object => "object",
+            ValueType::Struct => "struct",
+            ValueType::Array => "array",
+            ValueType::Null => "null",
+            ValueType::Recursive => "recursive",
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An error from [`Value::coerce_to`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoerceError {
+    pub from: ValueType,
+    pub to: ValueType,
+}
+
+impl fmt::Display for CoerceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot coerce {} to {}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for CoerceError {}
+
+impl Value {
+    /// The kind of value this is.
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Bytes(_) | Value::FrozenBytes(_) | Value::RawBytes(_) => ValueType::Bytes,
+            Value::Symbol(_) => ValueType::Symbol,
+            Value::Constant(_) => ValueType::Constant,
+            Value::Integer(_) => ValueType::Integer,
+            Value::Float(_) => ValueType::Float,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(_) => ValueType::Decimal,
+            #[cfg(feature = "chrono")]
+            Value::DateTime(_) => ValueType::DateTime,
+            #[cfg(feature = "chrono")]
+            Value::Date(_) => ValueType::Date,
+            Value::Boolean(_) => ValueType::Boolean,
+            Value::Object(_) => ValueType::Object,
+            Value::Struct { .. } => ValueType::Struct,
+            Value::Array(_) => ValueType::Array,
+            Value::Null => ValueType::Null,
+            Value::Recursive => ValueType::Recursive,
+        }
+    }
+
+    /// The type every element of an array shares, or `None` if they differ
+    /// or this isn't an array. An empty array gives `ValueType::Null`, since
+    /// there is nothing its elements have to be. Integers and floats are
+    /// different types, so `[1, 2.5]` gives `None`; use [`Value::as_number`]
+    /// on each element to accept both. Frozen and unfrozen strings are both
+    /// `ValueType::Bytes`.
+    pub fn is_homogeneous_array(&self) -> Option<ValueType> {
+        let values = match self {
+            Value::Array(values) => values,
+            _ => return None,
+        };
+        let mut types = values.iter().map(Value::value_type);
+        let first = types.next().unwrap_or(ValueType::Null);
+        types.all(|ty| ty == first).then_some(first)
+    }
+
+    /// Converts the value to `ty`. Every value converts to its own type
+    /// unchanged, and otherwise:
+    ///
+    /// | from                       | to                | when                                    |
+    /// |----------------------------|-------------------|-----------------------------------------|
+    /// | string, symbol             | integer, float    | the text is a number, like `"42"`       |
+    /// | string, symbol             | boolean           | the text is `true` or `false`           |
+    /// | string, symbol, constant   | string, symbol    | always                                  |
+    /// | integer                    | float             | the integer is exactly representable    |
+    /// | float                      | integer           | the float is whole and in range         |
+    /// | integer, float, boolean    | string            | always, as Ruby would print it          |
+    /// | integer                    | boolean           | the integer is `0` or `1`               |
+    /// | boolean                    | integer, float    | always, as `1` or `0`                   |
+    /// | struct                     | object            | always, keeping the fields              |
+    /// | string, symbol             | decimal           | the text is a number that fits exactly  |
+    /// | integer                    | decimal           | always                                  |
+    /// | decimal                    | integer           | the decimal is whole and in range       |
+    /// | decimal                    | string            | always, with every digit                |
+    /// | datetime, date             | string            | always, as Ruby would print it          |
+    ///
+    /// Any other conversion fails.
+    pub fn coerce_to(&self, ty: ValueType) -> Result<Value, CoerceError> {
+        if self.value_type() == ty {
+            return Ok(self.clone());
+        }
+        let error = || CoerceError {
+            from: self.value_type(),
+            to: ty,
+        };
+
+        let coerced = match (self, ty) {
+            (
+                Value::Bytes(text) | Value::FrozenBytes(text) | Value::Symbol(text),
+                ValueType::Integer,
+            ) => text.parse().ok().map(Value::Integer),
+            (
+                Value::Bytes(text) | Value::FrozenBytes(text) | Value::Symbol(text),
+                ValueType::Float,
+            ) => parse_float(text).map(Value::Float),
+            (
+                Value::Bytes(text) | Value::FrozenBytes(text) | Value::Symbol(text),
+                ValueType::Boolean,
+            ) => match text.as_str() {
+                "true" => Some(Value::Boolean(true)),
+                "false" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            (
+                Value::Bytes(text) | Value::Symbol(text) | Value::Constant(text),
+                ValueType::Bytes,
+            ) => Some(Value::Bytes(text.clone())),
+            (
+                Value::Bytes(text)
+                | Value::FrozenBytes(text)
+                | Value::Symbol(text)
+                | Value::Constant(text),
+                ValueType::Symbol,
+            ) => Some(Value::Symbol(text.clone())),
+            (Value::Integer(integer), ValueType::Float) => {
+                let float = *integer as f64;
+                (float as i128 == *integer as i128).then_some(Value::Float(float))
+            }
+            (Value::Float(float), ValueType::Integer) => {
+                let in_range = *float >= i64::MIN as f64 && *float < i64::MAX as f64;
+                (float.fract() == 0.0 && in_range).then_some(Value::Integer(*float as i64))
+            }
+            (Value::Integer(_) | Value::Float(_) | Value::Boolean(_), ValueType::Bytes) => {
+                Some(Value::Bytes(self.to_inspect()))
+            }
+            (Value::Integer(integer @ (0 | 1)), ValueType::Boolean) => {
+                Some(Value::Boolean(*integer == 1))
+            }
+            (Value::Boolean(boolean), ValueType::Integer) => {
+                Some(Value::Integer(i64::from(*boolean)))
+            }
+            (Value::Boolean(boolean), ValueType::Float) => {
+                Some(Value::Float(if *boolean { 1.0 } else { 0.0 }))
+            }
+            #[cfg(feature = "decimal")]
+            (
+                Value::Bytes(text) | Value::FrozenBytes(text) | Value::Symbol(text),
+                ValueType::Decimal,
+            ) => rust_decimal::Decimal::from_str_exact(text)
+                .ok()
+                .map(Value::Decimal),
+            #[cfg(feature = "decimal")]
+            (Value::Integer(integer), ValueType::Decimal) => {
+                Some(Value::Decimal((*integer).into()))
+            }
+            #[cfg(feature = "decimal")]
+            (Value::Decimal(decimal), ValueType::Integer) => {
+                use rust_decimal::prelude::ToPrimitive;
+                decimal
+                    .is_integer()
+                    .then(|| decimal.to_i64())
+                    .flatten()
+                    .map(Value::Integer)
+            }
+            #[cfg(feature = "decimal")]
+            (Value::Decimal(decimal), ValueType::Bytes) => Some(Value::Bytes(decimal.to_string())),
+            #[cfg(feature = "chrono")]
+            (Value::DateTime(_) | Value::Date(_), ValueType::Bytes) => {
+                Some(Value::Bytes(self.to_ruby()))
+            }
+            (Value::Struct { fields, .. }, ValueType::Object) => {
+                Some(Value::Object(fields.clone()))
+            }
+            _ => None,
+        };
+        coerced.ok_or_else(error)
+    }
+}
+
+/// Parses a decimal float such as `1.5` or `-2e3`. Rust's own parser also
+/// takes `inf` and `NaN`, which aren't numbers as far as Ruby is concerned.
+fn parse_float(text: &str) -> Option<f64> {
+    let digits = text.trim_start_matches(['+', '-']);
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    text.parse().ok()
+}
@@ -0,0 +1,89 @@
+//! Comments kept from the input by
+//! [`parse_with_comments`](crate::parse_with_comments), for writing back with
+//! [`Value::to_ruby_with_comments`](crate::Value::to_ruby_with_comments).
+
+use std::{collections::BTreeMap, ops::Range};
+
+/// The comments attached to each value of a parse, by the value's JSON
+/// pointer, as [`Value::set_pointer`](crate::Value::set_pointer) takes. The
+/// empty pointer is the whole value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    nodes: BTreeMap<String, NodeComments>,
+}
+
+/// The comments attached to one value. Each is kept as written, with its
+/// `#` or other prefix, and without the line break after it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeComments {
+    /// The comments before the value, or before its key in a hash, in order.
+    pub leading: Vec<String>,
+    /// The comments after the value on its line. For the whole value, also
+    /// those after it at the end of the input.
+    pub trailing: Vec<String>,
+}
+
+impl Comments {
+    /// The comments attached to the value at `pointer`, if it has any.
+    pub fn get(&self, pointer: &str) -> Option<&NodeComments> {
+        self.nodes.get(pointer)
+    }
+
+    /// The comments attached to the value at `pointer`, for adding or
+    /// removing some. This makes an empty entry if there wasn't one.
+    pub fn node_mut(&mut self, pointer: &str) -> &mut NodeComments {
+        self.nodes.entry(pointer.to_string()).or_default()
+    }
+
+    /// Each value with comments and its pointer, in pointer order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NodeComments)> {
+        self.nodes
+            .iter()
+            .map(|(pointer, node)| (pointer.as_str(), node))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes
+            .values()
+            .all(|node| node.leading.is_empty() && node.trailing.is_empty())
+    }
+
+    /// Attaches each of `comments` in `input` to the nearest of `spans`,
+    /// the values inside arrays and hashes in order of their starts, or to
+    /// the whole value, which starts at `root`.
+    pub(crate) fn attach(
+        input: &str,
+        root: usize,
+        spans: Vec<(Range<usize>, String)>,
+        comments: Vec<Range<usize>>,
+    ) -> Self {
+        // The innermost value ending at each position.
+        let mut ends = BTreeMap::new();
+        for (span, path) in &spans {
+            let innermost = ends.entry(span.end).or_insert((span.start, path));
+            if span.start > innermost.0 {
+                *innermost = (span.start, path);
+            }
+        }
+
+        let mut attached = Comments::default();
+        for comment in comments {
+            let text = input[comment.clone()].to_string();
+            if comment.end <= root {
+                attached.node_mut("").leading.push(text);
+                continue;
+            }
+            // A value that ends before the comment with only spaces and
+            // commas between them.
+            let before = input[..comment.start].trim_end_matches([' ', '\t', ',']);
+            let trailing = ends.range(before.len()..=comment.start).next_back();
+            let next = spans.partition_point(|(span, _)| span.start < comment.end);
+            match (trailing, spans.get(next)) {
+                (Some((_, (_, path))), _) => attached.node_mut(path).trailing.push(text),
+                (None, Some((_, path))) => attached.node_mut(path).leading.push(text),
+                (None, None) => attached.node_mut("").trailing.push(text),
+            }
+        }
+        attached
+    }
+}
@@ -0,0 +1,225 @@
+//! Deserializing Rust types from parsed values with serde.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::de::{
+    self,
+    value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+    DeserializeOwned, IntoDeserializer, Visitor,
+};
+
+use crate::{ObjectKey, Value};
+
+/// An error from deserializing a [`Value`] into a Rust type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeserializeError {
+    message: String,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeserializeError {
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Deserializes a parsed value into `T`, as in
+/// `let config: Config = from_value(parse(input)?)?`.
+///
+/// Strings, symbols and constants all deserialize as strings. Object keys
+/// written as symbols, like `:name => 1`, match the field `name`, as do
+/// `name: 1` and `"name" => 1`. Keys such as `1 =>` can deserialize as
+/// integers, as for a `HashMap<u32, _>`. Structs deserialize from their fields.
+/// Decimals deserialize as strings of their digits, so that they stay exact,
+/// and times and dates as RFC 3339 strings, which chrono's types read.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, DeserializeError> {
+    T::deserialize(value)
+}
+
+/// Reads a key as its [`ObjectKey::text`], without the colon that the parser
+/// keeps on `:name =>` keys.
+fn field_name(key: String) -> Key {
+    Key(ObjectKey::text(ObjectKey::of(&key)).to_string())
+}
+
+/// An object key, which deserializes as a string or, since keys like `1 =>`
+/// are stored as text, as an integer when one is asked for.
+struct Key(String);
+
+macro_rules! deserialize_integer_key {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.0.parse::<$ty>() {
+                    Ok(integer) => visitor.$visit(integer),
+                    Err(_) => visitor.visit_string(self.0),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Key {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_integer_key! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for Key {
+    type Deserializer = Key;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+fn visit_object<'de, V: Visitor<'de>>(
+    map: BTreeMap<String, Value>,
+    visitor: V,
+) -> Result<V::Value, DeserializeError> {
+    let mut entries =
+        MapDeserializer::new(map.into_iter().map(|(key, value)| (field_name(key), value)));
+    let value = visitor.visit_map(&mut entries)?;
+    entries.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Value::Bytes(string)
+            | Value::FrozenBytes(string)
+            | Value::Symbol(string)
+            | Value::Constant(string) => visitor.visit_string(string),
+            Value::RawBytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::Integer(integer) => visitor.visit_i64(integer),
+            Value::Float(float) => visitor.visit_f64(float),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => visitor.visit_string(decimal.to_string()),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(time) => visitor.visit_string(time.to_rfc3339()),
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => visitor.visit_string(date.to_string()),
+            Value::Boolean(boolean) => visitor.visit_bool(boolean),
+            Value::Object(map) | Value::Struct { fields: map, .. } => visit_object(map, visitor),
+            Value::Array(values) => {
+                let mut elements = SeqDeserializer::new(values.into_iter());
+                let value = visitor.visit_seq(&mut elements)?;
+                elements.end()?;
+                Ok(value)
+            }
+            Value::Null | Value::Recursive => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are written as a string or symbol, like `:red`, and
+    /// other variants as an object with a single key naming the variant.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            Value::Bytes(variant)
+            | Value::FrozenBytes(variant)
+            | Value::Symbol(variant)
+            | Value::Constant(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Object(map) if map.len() == 1 => visitor.visit_enum(MapAccessDeserializer::new(
+                MapDeserializer::new(map.into_iter().map(|(key, value)| (field_name(key), value))),
+            )),
+            other => Err(de::Error::invalid_type(unexpected(&other), &"an enum")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+fn unexpected(value: &Value) -> de::Unexpected<'_> {
+    match value {
+        Value::Bytes(string)
+        | Value::FrozenBytes(string)
+        | Value::Symbol(string)
+        | Value::Constant(string) => de::Unexpected::Str(string),
+        Value::RawBytes(bytes) => de::Unexpected::Bytes(bytes),
+        Value::Integer(integer) => de::Unexpected::Signed(*integer),
+        Value::Float(float) => de::Unexpected::Float(*float),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(_) => de::Unexpected::Other("decimal"),
+        #[cfg(feature = "chrono")]
+        Value::DateTime(_) => de::Unexpected::Other("datetime"),
+        #[cfg(feature = "chrono")]
+        Value::Date(_) => de::Unexpected::Other("date"),
+        Value::Boolean(boolean) => de::Unexpected::Bool(*boolean),
+        Value::Object(_) | Value::Struct { .. } => de::Unexpected::Map,
+        Value::Array(_) => de::Unexpected::Seq,
+        Value::Null => de::Unexpected::Unit,
+        Value::Recursive => de::Unexpected::Other("recursion marker"),
+    }
+}
@@ -0,0 +1,173 @@
+//! Structured reports of problems in the input, for editor tooling.
+
+use std::{fmt, ops::Range};
+
+use crate::ParseError;
+
+/// A problem found in the input by
+/// [`parse_diagnostics`](crate::parse_diagnostics), with the bytes it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The bytes of the input the problem covers. This is the whole of a
+    /// skipped entry or ignored trailing input, from where the error was
+    /// found, and otherwise the character the error was found at, or an
+    /// empty range at the end of input.
+    pub range: Range<usize>,
+    pub severity: Severity,
+    /// The same message a [`ParseError`] would have.
+    pub message: String,
+    pub code: DiagnosticCode,
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// The input isn't valid, and [`parse`](crate::parse) rejects it.
+    Error,
+    /// The input is valid but probably not what was meant, such as a key
+    /// written twice, whose first value is lost.
+    Warning,
+}
+
+/// The kind of problem a [`Diagnostic`] reports, for handling them without
+/// matching on messages. Named in kebab case by [`DiagnosticCode::as_str`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    /// The input is empty, or only whitespace and comments.
+    EmptyInput,
+    /// A string, heredoc or percent array has no closing delimiter.
+    UnterminatedString,
+    /// A `/* */` comment is never closed.
+    UnterminatedComment,
+    /// The input ended where more was expected.
+    UnexpectedEndOfInput,
+    /// Something other than what the grammar allows here, such as a
+    /// malformed separator or a stray operator.
+    UnexpectedToken,
+    /// There is more input after the value.
+    TrailingInput,
+    /// A string has an escape that isn't valid.
+    InvalidEscape,
+    /// A number is malformed or out of range.
+    InvalidNumber,
+    /// A time or date isn't a real one.
+    InvalidTimestamp,
+    /// A key can't be stored as the options ask.
+    InvalidKey,
+    /// A key is written more than once in the same hash.
+    DuplicateKey,
+    /// A value of a type the options don't allow.
+    DisallowedType,
+    /// The input is nested deeper, or has a longer string, than allowed.
+    LimitExceeded,
+}
+
+impl DiagnosticCode {
+    /// The code's name, such as `unterminated-string`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::EmptyInput => "empty-input",
+            DiagnosticCode::UnterminatedString => "unterminated-string",
+            DiagnosticCode::UnterminatedComment => "unterminated-comment",
+            DiagnosticCode::UnexpectedEndOfInput => "unexpected-end-of-input",
+            DiagnosticCode::UnexpectedToken => "unexpected-token",
+            DiagnosticCode::TrailingInput => "trailing-input",
+            DiagnosticCode::InvalidEscape => "invalid-escape",
+            DiagnosticCode::InvalidNumber => "invalid-number",
+            DiagnosticCode::InvalidTimestamp => "invalid-timestamp",
+            DiagnosticCode::InvalidKey => "invalid-key",
+            DiagnosticCode::DuplicateKey => "duplicate-key",
+            DiagnosticCode::DisallowedType => "disallowed-type",
+            DiagnosticCode::LimitExceeded => "limit-exceeded",
+        }
+    }
+
+    /// Classifies one of the parser's messages.
+    pub(crate) fn classify(message: &str) -> Self {
+        let number_errors = [
+            "integer out of range",
+            "invalid float",
+            "invalid octal digit",
+            "invalid digit for",
+            "underscore in a number",
+            "expected digits after",
+            "decimal out of range",
+            "exponent out of range",
+            "invalid value for",
+        ];
+        if message.starts_with("unterminated comment") {
+            DiagnosticCode::UnterminatedComment
+        } else if message.starts_with("unterminated") {
+            DiagnosticCode::UnterminatedString
+        } else if message.starts_with("unexpected trailing input")
+            || message.starts_with("expected whitespace between values")
+        {
+            DiagnosticCode::TrailingInput
+        } else if message.starts_with("invalid escape") || message.contains("unicode") {
+            DiagnosticCode::InvalidEscape
+        } else if number_errors
+            .iter()
+            .any(|prefix| message.starts_with(prefix))
+        {
+            DiagnosticCode::InvalidNumber
+        } else if message.starts_with("invalid date")
+            || message.starts_with("invalid time")
+            || message.starts_with("expected a time zone")
+        {
+            DiagnosticCode::InvalidTimestamp
+        } else if message.starts_with("key is not") {
+            DiagnosticCode::InvalidKey
+        } else if message.starts_with("duplicate key") {
+            DiagnosticCode::DuplicateKey
+        } else if message.contains("are not allowed") {
+            DiagnosticCode::DisallowedType
+        } else if message.starts_with("maximum nesting depth")
+            || ["max_string_len", "max_array_len", "max_object_keys"]
+                .iter()
+                .any(|option| message.contains(option))
+        {
+            DiagnosticCode::LimitExceeded
+        } else if message.contains("end of input") {
+            DiagnosticCode::UnexpectedEndOfInput
+        } else {
+            DiagnosticCode::UnexpectedToken
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Diagnostic {
+    /// The diagnostic for an error that stopped the parse, covering the
+    /// character it was found at.
+    pub(crate) fn from_error(input: &str, error: ParseError) -> Self {
+        match error {
+            ParseError::Empty => Diagnostic {
+                range: 0..input.len(),
+                severity: Severity::Error,
+                message: error.to_string(),
+                code: DiagnosticCode::EmptyInput,
+            },
+            ParseError::Syntax { offset, message } => {
+                let mut start = offset.min(input.len());
+                while !input.is_char_boundary(start) {
+                    start -= 1;
+                }
+                let end = input[start..]
+                    .chars()
+                    .next()
+                    .map_or(start, |c| start + c.len_utf8());
+                Diagnostic {
+                    range: start..end,
+                    severity: Severity::Error,
+                    code: DiagnosticCode::classify(&message),
+                    message,
+                }
+            }
+        }
+    }
+}
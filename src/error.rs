@@ -0,0 +1,203 @@
+use nom::{
+    error::{ErrorKind, VerboseError, VerboseErrorKind},
+    Offset,
+};
+use std::fmt;
+
+/// An error returned when the input could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The input was empty or contained only whitespace.
+    Empty,
+    /// The input is not a valid Ruby value.
+    Syntax {
+        /// Byte offset into the input where parsing failed.
+        offset: usize,
+        /// Description of what went wrong.
+        message: String,
+    },
+}
+
+impl ParseError {
+    /// Builds an error from the innermost failure recorded by nom, naming the
+    /// closest enclosing context if there is one.
+    pub(crate) fn from_verbose(input: &str, err: VerboseError<&str>) -> Self {
+        let (remaining, kind) = match err.errors.first() {
+            Some(first) => first,
+            None => {
+                return ParseError::Syntax {
+                    offset: 0,
+                    message: "invalid input".to_string(),
+                }
+            }
+        };
+
+        // Errors raised by this crate carry their whole message as a
+        // context on top of `ErrorKind::Fail`.
+        let (mut message, outer) = match (kind, err.errors.get(1)) {
+            (
+                VerboseErrorKind::Nom(ErrorKind::Fail),
+                Some((at, VerboseErrorKind::Context(msg))),
+            ) if at == remaining => (msg.to_string(), &err.errors[2..]),
+            (VerboseErrorKind::Char(c), _) if remaining.is_empty() => (
+                format!("expected '{}', found end of input", c),
+                &err.errors[1..],
+            ),
+            (VerboseErrorKind::Char(c), _) => (format!("expected '{}'", c), &err.errors[1..]),
+            (VerboseErrorKind::Context(context), _) => {
+                (format!("invalid {}", context), &err.errors[1..])
+            }
+            (VerboseErrorKind::Nom(_), _) if remaining.is_empty() => {
+                ("unexpected end of input".to_string(), &err.errors[1..])
+            }
+            (VerboseErrorKind::Nom(_), _) => ("unexpected input".to_string(), &err.errors[1..]),
+        };
+
+        let context = outer.iter().find_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(context) => Some(context),
+            _ => None,
+        });
+        if let Some(context) = context {
+            message.push_str(" in ");
+            message.push_str(context);
+        }
+
+        ParseError::Syntax {
+            offset: input.offset(remaining),
+            message,
+        }
+    }
+}
+
+impl ParseError {
+    /// The 1-based line and column of the error in `input`, counting columns
+    /// in characters. Returns `None` for [`ParseError::Empty`].
+    pub fn line_column(&self, input: &str) -> Option<(usize, usize)> {
+        let (start, line) = self.source_line(input)?;
+        let column = input[start..self.offset_in(input)?].chars().count() + 1;
+        Some((line, column))
+    }
+
+    /// Renders the error for display to a user, showing the offending line of
+    /// `input` with a caret under the failure column:
+    ///
+    /// ```text
+    /// error: expected '>' in object
+    ///  --> 1:8
+    ///   |
+    /// 1 | #<User id 1>
+    ///   |        ^
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let (message, offset) = match self {
+            ParseError::Empty => return format!("error: {}\n", self),
+            ParseError::Syntax { message, .. } => (message, self.offset_in(input).unwrap_or(0)),
+        };
+        let (start, line) = self.source_line(input).unwrap_or((0, 1));
+        let end = input[start..]
+            .find('\n')
+            .map_or(input.len(), |end| start + end);
+        let source = input[start..end].trim_end_matches('\r');
+        let column = input[start..offset].chars().count() + 1;
+
+        // Tabs are copied into the padding so that the caret lines up however
+        // wide the terminal draws them.
+        let padding: String = source
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(line.to_string().len());
+        format!(
+            "error: {message}\n{gutter}--> {line}:{column}\n{gutter} |\n{line} | {source}\n{gutter} | {padding}^\n",
+            message = message,
+            gutter = gutter,
+            line = line,
+            column = column,
+            source = source,
+            padding = padding,
+        )
+    }
+
+    /// The error's offset, clamped to `input` and moved back to a character
+    /// boundary in case the error came from different input.
+    fn offset_in(&self, input: &str) -> Option<usize> {
+        match self {
+            ParseError::Empty => None,
+            ParseError::Syntax { offset, .. } => {
+                let mut offset = (*offset).min(input.len());
+                while !input.is_char_boundary(offset) {
+                    offset -= 1;
+                }
+                Some(offset)
+            }
+        }
+    }
+
+    /// The byte offset where the error's line starts, and its 1-based number.
+    fn source_line(&self, input: &str) -> Option<(usize, usize)> {
+        let before = &input[..self.offset_in(input)?];
+        let start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Some((start, before.matches('\n').count() + 1))
+    }
+}
+
+impl ParseError {
+    /// Moves the reported position `base` bytes further into the input, for
+    /// errors found in a slice that started at `base`.
+    pub(crate) fn offset_by(self, base: usize) -> Self {
+        match self {
+            ParseError::Syntax { offset, message } => ParseError::Syntax {
+                offset: offset + base,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "expected a value, found empty input"),
+            ParseError::Syntax { offset, message } => write!(f, "{} at offset {}", message, offset),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// An error from reading input and parsing it.
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading the input failed.
+    Io(std::io::Error),
+    /// The input could not be parsed.
+    Parse(ParseError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "failed to read input: {}", err),
+            ReadError::Parse(err) => write!(f, "failed to parse input: {}", err),
+        }
+    }
+}
+
+/// The message already includes the underlying error's, so it isn't given as
+/// the source as well, which error reporters would print a second time.
+impl std::error::Error for ReadError {}
+
+impl From<std::io::Error> for ReadError {
+    fn from(err: std::io::Error) -> Self {
+        ReadError::Io(err)
+    }
+}
+
+impl From<ParseError> for ReadError {
+    fn from(err: ParseError) -> Self {
+        ReadError::Parse(err)
+    }
+}
@@ -0,0 +1,250 @@
+//! Decoding of backslash escapes inside quoted strings.
+
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use crate::{InvalidUnicode, Value};
+
+/// Decodes the escapes in the content of a double-quoted string.
+///
+/// Octal (`\101`) and hex (`\x41`) escapes produce a single byte, as in Ruby.
+/// An octal escape above `\377` wraps to the low byte (`\400` is `\0`), which
+/// is what MRI does. Because the decoded content has to be valid UTF-8, byte
+/// escapes above `\x7F` are only accepted when they combine into a valid UTF-8
+/// sequence, e.g. `"\303\251"` is `"é"` but a lone `"\377"` is rejected.
+///
+/// A `\u` escape for a surrogate or a code point above `U+10FFFF` is handled
+/// as `invalid_unicode` says.
+///
+/// The content is decoded in `bytes`, which is cleared first, and then copied
+/// into the returned string. Returns `None` if an escape is malformed or the
+/// result is not valid UTF-8.
+pub(crate) fn unescape_double(
+    content: &str,
+    bytes: &mut Vec<u8>,
+    invalid_unicode: InvalidUnicode,
+) -> Option<String> {
+    if !content.contains('\\') {
+        return Some(content.to_string());
+    }
+
+    decode_double(content, bytes, invalid_unicode).ok()?;
+    std::str::from_utf8(bytes).ok().map(str::to_owned)
+}
+
+/// Decodes the one escape that `input` starts with, just after its
+/// backslash, as in a double-quoted string, returning what it stands for and
+/// how many bytes of `input` it takes. Returns `None` if the escape is
+/// malformed, stands for nothing, or isn't valid UTF-8 by itself.
+pub(crate) fn unescape_one(
+    input: &str,
+    invalid_unicode: InvalidUnicode,
+) -> Option<(String, usize)> {
+    let mut chars = input.char_indices().peekable();
+    let mut bytes = Vec::new();
+    decode_escape(&mut chars, &mut bytes, invalid_unicode)?;
+    let len = chars.peek().map_or(input.len(), |&(index, _)| index);
+    match String::from_utf8(bytes) {
+        Ok(decoded) if !decoded.is_empty() => Some((decoded, len)),
+        _ => None,
+    }
+}
+
+type Chars<'a> = Peekable<CharIndices<'a>>;
+
+/// Decodes `content` into `bytes` as [`unescape_double`] does, failing with
+/// the offset of the backslash that starts the first invalid escape.
+fn decode_double(
+    content: &str,
+    bytes: &mut Vec<u8>,
+    invalid_unicode: InvalidUnicode,
+) -> Result<(), usize> {
+    bytes.clear();
+    // Where each byte escape above `\x7F` was decoded to and written, for
+    // finding the escape that breaks UTF-8.
+    let mut high_bytes = Vec::new();
+    let mut chars = content.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            push_char(bytes, c);
+            continue;
+        }
+
+        let len = bytes.len();
+        decode_escape(&mut chars, bytes, invalid_unicode).ok_or(start)?;
+        if bytes.len() == len + 1 && bytes[len] > 0x7f {
+            high_bytes.push((len, start));
+        }
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(high_bytes
+            .iter()
+            .find(|(position, _)| *position >= err.valid_up_to())
+            .map_or(content.len(), |(_, start)| *start)),
+    }
+}
+
+/// Decodes the escape after a backslash.
+fn decode_escape(
+    chars: &mut Chars<'_>,
+    bytes: &mut Vec<u8>,
+    invalid_unicode: InvalidUnicode,
+) -> Option<()> {
+    let peek = |chars: &mut Chars<'_>| chars.peek().map(|&(_, c)| c);
+    match chars.next()?.1 {
+        'n' => bytes.push(b'\n'),
+        't' => bytes.push(b'\t'),
+        'r' => bytes.push(b'\r'),
+        's' => bytes.push(b' '),
+        'a' => bytes.push(0x07),
+        'b' => bytes.push(0x08),
+        'e' => bytes.push(0x1b),
+        'f' => bytes.push(0x0c),
+        'v' => bytes.push(0x0b),
+        // A backslash before a line break joins the lines.
+        '\n' => {}
+        digit @ '0'..='7' => {
+            let mut code = digit.to_digit(8)?;
+            for _ in 0..2 {
+                match peek(chars).and_then(|c| c.to_digit(8)) {
+                    Some(digit) => {
+                        code = code * 8 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            bytes.push((code & 0xff) as u8);
+        }
+        'x' => {
+            let mut code = chars.next()?.1.to_digit(16)?;
+            if let Some(digit) = peek(chars).and_then(|c| c.to_digit(16)) {
+                code = code * 16 + digit;
+                chars.next();
+            }
+            bytes.push(code as u8);
+        }
+        'u' => {
+            if peek(chars) == Some('{') {
+                chars.next();
+                let mut any = false;
+                loop {
+                    while peek(chars) == Some(' ') {
+                        chars.next();
+                    }
+                    if peek(chars) == Some('}') {
+                        chars.next();
+                        break;
+                    }
+                    let mut code = 0;
+                    let mut digits = String::new();
+                    while let Some(digit) = peek(chars).filter(char::is_ascii_hexdigit) {
+                        if digits.len() == 6 {
+                            return None;
+                        }
+                        code = code * 16 + digit.to_digit(16)?;
+                        digits.push(digit);
+                        chars.next();
+                    }
+                    if digits.is_empty() {
+                        return None;
+                    }
+                    push_code_point(bytes, code, invalid_unicode, || {
+                        format!("\\u{{{}}}", digits)
+                    })?;
+                    any = true;
+                }
+                if !any {
+                    return None;
+                }
+            } else {
+                let mut code = 0;
+                let mut digits = String::new();
+                for _ in 0..4 {
+                    let digit = chars.next()?.1;
+                    code = code * 16 + digit.to_digit(16)?;
+                    digits.push(digit);
+                }
+                push_code_point(bytes, code, invalid_unicode, || format!("\\u{}", digits))?;
+            }
+        }
+        // Any other escaped character stands for itself.
+        other => push_char(bytes, other),
+    }
+    Some(())
+}
+
+/// An invalid escape found by [`Value::validate_escapes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeError {
+    /// The byte offset of the backslash that starts the escape.
+    pub offset: usize,
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid escape sequence at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for EscapeError {}
+
+impl Value {
+    /// Checks that `content`, the text between the quotes of a
+    /// double-quoted string, has only escapes that [`parse`](crate::parse)
+    /// accepts, for validating text before embedding it in Ruby source.
+    /// Byte escapes such as `\303\251` must combine into valid UTF-8, and
+    /// a `\u` escape must be a character, as in `Strict`
+    /// [`on_invalid_unicode`](crate::ParseOptions::on_invalid_unicode). An
+    /// unknown escape such as `\q` stands for its character, as in Ruby, so
+    /// it is valid. Quotes inside `content` aren't checked, nor is `#{`.
+    pub fn validate_escapes(content: &str) -> Result<(), EscapeError> {
+        decode_double(content, &mut Vec::new(), InvalidUnicode::Strict)
+            .map_err(|offset| EscapeError { offset })
+    }
+}
+
+/// Decodes the content of a single-quoted string, where only `\\` and an
+/// escaped delimiter are escapes and any other backslash is literal.
+pub(crate) fn unescape_single(content: &str, delimiter: char) -> String {
+    if !content.contains('\\') {
+        return content.to_string();
+    }
+
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if next == '\\' || next == delimiter => {
+                result.push(next);
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Pushes the character for a `\u` escape. When `code` isn't a valid
+/// character, `escape` gives the original escape text for
+/// [`InvalidUnicode::Preserve`].
+fn push_code_point(
+    bytes: &mut Vec<u8>,
+    code: u32,
+    invalid_unicode: InvalidUnicode,
+    escape: impl FnOnce() -> String,
+) -> Option<()> {
+    match (char::from_u32(code), invalid_unicode) {
+        (Some(c), _) => push_char(bytes, c),
+        (None, InvalidUnicode::Strict) => return None,
+        (None, InvalidUnicode::Replace) => push_char(bytes, char::REPLACEMENT_CHARACTER),
+        (None, InvalidUnicode::Preserve) => bytes.extend_from_slice(escape().as_bytes()),
+    }
+    Some(())
+}
+
+fn push_char(bytes: &mut Vec<u8>, c: char) {
+    let mut buf = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+}
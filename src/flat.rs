@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use crate::{parse, ParseError, Value};
+
+/// Parses `input` like [`parse`], but first tries a much faster scan that
+/// only understands single-level hashes with symbol keys, such as the
+/// `{:id=>1, :name=>"x", :admin=>false}` that Rails logs are full of.
+///
+/// The fast scan handles `:name =>` keys, and values that are strings
+/// without escapes or interpolation, integers, plain floats such as `1.5`,
+/// bare symbols, `true`, `false` and `nil`. Anything else, such as a nested
+/// hash, a comment or a `name:` key, falls back to the general parser, so
+/// the result, and any error, is always what [`parse`] gives.
+pub fn parse_flat_symbol_hash(input: &str) -> Result<Value, ParseError> {
+    match scan_hash(input.as_bytes()) {
+        Some(map) => Ok(Value::Object(map)),
+        None => parse(input),
+    }
+}
+
+/// Scans a whole flat hash, or gives up with `None` at the first thing the
+/// fast path doesn't handle.
+fn scan_hash(input: &[u8]) -> Option<BTreeMap<String, Value>> {
+    let mut map = BTreeMap::new();
+    let mut pos = skip_space(input, 0);
+    if input.get(pos) != Some(&b'{') {
+        return None;
+    }
+    pos = skip_space(input, pos + 1);
+    if input.get(pos) == Some(&b'}') {
+        return finish(input, pos + 1, map);
+    }
+
+    loop {
+        if input.get(pos) != Some(&b':') {
+            return None;
+        }
+        let end = ident_end(input, pos + 1)?;
+        let key = text(input, pos, end)?;
+        pos = skip_space(input, end);
+        if !input[pos..].starts_with(b"=>") {
+            return None;
+        }
+        pos = skip_space(input, pos + 2);
+
+        let (value, end) = scan_value(input, pos)?;
+        map.insert(key, value);
+        pos = skip_space(input, end);
+        match input.get(pos) {
+            Some(b',') => {
+                pos = skip_space(input, pos + 1);
+                // A comma after the last entry.
+                if input.get(pos) == Some(&b'}') {
+                    return finish(input, pos + 1, map);
+                }
+            }
+            Some(b'}') => return finish(input, pos + 1, map),
+            _ => return None,
+        }
+    }
+}
+
+/// Accepts the hash if nothing but whitespace follows it.
+fn finish(
+    input: &[u8],
+    pos: usize,
+    map: BTreeMap<String, Value>,
+) -> Option<BTreeMap<String, Value>> {
+    match skip_space(input, pos) == input.len() {
+        true => Some(map),
+        false => None,
+    }
+}
+
+/// Scans one value at `pos`, returning it with where it ends.
+fn scan_value(input: &[u8], pos: usize) -> Option<(Value, usize)> {
+    let value = match *input.get(pos)? {
+        quote @ (b'"' | b'\'') => {
+            let len = input[pos + 1..]
+                .iter()
+                .position(|&byte| byte == quote || byte == b'\\' || byte == b'#')?;
+            if input[pos + 1 + len] != quote {
+                return None;
+            }
+            let string = text(input, pos + 1, pos + 1 + len)?;
+            return Some((Value::Bytes(string), pos + len + 2));
+        }
+        b':' => {
+            let end = ident_end(input, pos + 1)?;
+            return Some((Value::Symbol(text(input, pos + 1, end)?), end));
+        }
+        b'-' | b'0'..=b'9' => return scan_number(input, pos),
+        _ => {
+            let end = ident_end(input, pos)?;
+            match &input[pos..end] {
+                b"nil" => (Value::Null, end),
+                b"true" => (Value::Boolean(true), end),
+                b"false" => (Value::Boolean(false), end),
+                _ => return None,
+            }
+        }
+    };
+    Some(value)
+}
+
+/// Scans an integer, or a float with digits on both sides of its point.
+/// Leading zeros, underscores and exponents are left to the general parser.
+fn scan_number(input: &[u8], pos: usize) -> Option<(Value, usize)> {
+    let digits = |from: usize| {
+        input[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+
+    let start = pos + usize::from(input[pos] == b'-');
+    let mut end = match digits(start) {
+        0 => return None,
+        len if len > 1 && input[start] == b'0' => return None,
+        len => start + len,
+    };
+    let float = input.get(end) == Some(&b'.');
+    if float {
+        end = match digits(end + 1) {
+            0 => return None,
+            len => end + 1 + len,
+        };
+    }
+    if input
+        .get(end)
+        .is_some_and(|byte| byte.is_ascii_alphanumeric() || b"_.".contains(byte))
+    {
+        return None;
+    }
+
+    let text = std::str::from_utf8(&input[pos..end]).ok()?;
+    let value = match float {
+        true => Value::Float(text.parse().ok()?),
+        false => Value::Integer(text.parse().ok()?),
+    };
+    Some((value, end))
+}
+
+/// The end of the identifier at `pos`, which has to start with a letter or
+/// underscore and be followed by a delimiter.
+fn ident_end(input: &[u8], pos: usize) -> Option<usize> {
+    match input.get(pos) {
+        Some(byte) if byte.is_ascii_alphabetic() || *byte == b'_' => {}
+        _ => return None,
+    }
+    let end = pos
+        + input[pos..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_')
+            .count();
+    match input.get(end) {
+        None | Some(b' ' | b'\t' | b'\r' | b'\n' | b',' | b'}' | b'=') => Some(end),
+        _ => None,
+    }
+}
+
+fn text(input: &[u8], start: usize, end: usize) -> Option<String> {
+    std::str::from_utf8(&input[start..end])
+        .ok()
+        .map(String::from)
+}
+
+fn skip_space(input: &[u8], pos: usize) -> usize {
+    pos + input[pos..]
+        .iter()
+        .take_while(|byte| matches!(byte, b' ' | b'\t' | b'\r' | b'\n'))
+        .count()
+}
@@ -0,0 +1,319 @@
+use std::{collections::BTreeMap, fmt, fmt::Write};
+
+use crate::{ObjectKey, Value, ValueType};
+
+/// An error from [`Value::to_query_string`], which only encodes objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryStringError {
+    pub found: ValueType,
+}
+
+impl fmt::Display for QueryStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot encode {} as a query string", self.found)
+    }
+}
+
+impl std::error::Error for QueryStringError {}
+
+impl Value {
+    /// Flattens nested objects and arrays into a single map from key paths to
+    /// values, joining keys and array indices with `separator`, so that
+    /// `{a: {b: 1}, c: [2, 3]}` becomes `{"a.b" => 1, "c.0" => 2, "c.1" => 3}`.
+    ///
+    /// Empty objects and arrays are kept as values so that they survive
+    /// [`Value::unflatten`]. A value that isn't an object or array flattens to
+    /// a single entry with an empty key.
+    pub fn flatten(&self, separator: &str) -> BTreeMap<String, Value> {
+        let mut flat = BTreeMap::new();
+        flatten_into(self, &mut String::new(), separator, &mut flat);
+        flat
+    }
+
+    /// Encodes an object as a URL query string, such as `a=1&b=x%20y`, with
+    /// nested values named the way Rack reads them back: `{a: {b: 1}}` is
+    /// `a[b]=1` and `{a: [1, 2]}` is `a[]=1&a[]=2`. Structs are encoded like
+    /// objects, and the colon of a `:name` key is dropped.
+    ///
+    /// Everything but letters, digits and `-._~` is percent-encoded in keys
+    /// and values, apart from the brackets that nest keys. `nil` and empty
+    /// objects are encoded as an empty value, as in `a=`, and empty arrays
+    /// as `a[]=`. Strings that aren't UTF-8 are encoded byte for byte.
+    pub fn to_query_string(&self) -> Result<String, QueryStringError> {
+        match self {
+            Value::Object(map) | Value::Struct { fields: map, .. } if map.is_empty() => {
+                return Ok(String::new())
+            }
+            Value::Object(_) | Value::Struct { .. } => {}
+            _ => {
+                return Err(QueryStringError {
+                    found: self.value_type(),
+                })
+            }
+        }
+
+        let mut query = String::new();
+        // Each value still to encode, with the key it's encoded under.
+        let mut stack = vec![(String::new(), self)];
+        while let Some((key, value)) = stack.pop() {
+            let len = stack.len();
+            let text = match value {
+                Value::Object(map) | Value::Struct { fields: map, .. } if !map.is_empty() => {
+                    for (name, value) in map {
+                        let name = ObjectKey::of(name).text();
+                        let mut nested = key.clone();
+                        if key.is_empty() {
+                            encode_into(&mut nested, name.as_bytes());
+                        } else {
+                            nested.push('[');
+                            encode_into(&mut nested, name.as_bytes());
+                            nested.push(']');
+                        }
+                        stack.push((nested, value));
+                    }
+                    stack[len..].reverse();
+                    continue;
+                }
+                Value::Array(values) if !values.is_empty() => {
+                    for value in values {
+                        stack.push((format!("{}[]", key), value));
+                    }
+                    stack[len..].reverse();
+                    continue;
+                }
+                Value::Array(_) => {
+                    push_pair(&mut query, &format!("{}[]", key), b"");
+                    continue;
+                }
+                Value::RawBytes(bytes) => {
+                    push_pair(&mut query, &key, bytes);
+                    continue;
+                }
+                value => scalar_text(value),
+            };
+            push_pair(&mut query, &key, text.as_bytes());
+        }
+        Ok(query)
+    }
+
+    /// Flattens a config object into environment variables named
+    /// `PREFIX_A_B` for the value at `a.b`, as twelve-factor apps read them.
+    /// Keys are uppercased, with the colon of a `:name` key dropped and any
+    /// character other than a letter, digit or `_` replaced by `_`. Array
+    /// elements are named by index, as in `PREFIX_A_0`. An empty prefix
+    /// leaves names like `A_B`, and a value that isn't an object or array is
+    /// a single variable named by the prefix.
+    ///
+    /// Strings, symbols and constants are their text, and strings that
+    /// aren't UTF-8 have invalid bytes replaced with U+FFFD. Integers and
+    /// booleans are written as Ruby prints them, floats always with a point
+    /// or an exponent, such as `1.0`, decimals with their digits, times in
+    /// RFC 3339 and dates as `2023-01-01`. `nil` and empty objects and
+    /// arrays are empty strings. Structs are flattened like objects. When two
+    /// paths give the same name, such as `a_b` and `a.b`, the later one in
+    /// key order wins.
+    pub fn to_env_vars(&self, prefix: &str) -> BTreeMap<String, String> {
+        let mut vars = BTreeMap::new();
+        let mut stack = vec![(prefix.to_string(), self)];
+        while let Some((name, value)) = stack.pop() {
+            let len = stack.len();
+            match value {
+                Value::Object(map) | Value::Struct { fields: map, .. } if !map.is_empty() => {
+                    for (key, value) in map {
+                        let key = ObjectKey::of(key).text();
+                        let mut nested = name.clone();
+                        if !nested.is_empty() {
+                            nested.push('_');
+                        }
+                        nested.extend(key.chars().flat_map(char::to_uppercase).map(|c| {
+                            match c.is_ascii_alphanumeric() {
+                                true => c,
+                                false => '_',
+                            }
+                        }));
+                        stack.push((nested, value));
+                    }
+                    stack[len..].reverse();
+                }
+                Value::Array(values) if !values.is_empty() => {
+                    for (index, value) in values.iter().enumerate() {
+                        let nested = match name.is_empty() {
+                            true => index.to_string(),
+                            false => format!("{}_{}", name, index),
+                        };
+                        stack.push((nested, value));
+                    }
+                    stack[len..].reverse();
+                }
+                value => {
+                    vars.insert(name, scalar_text(value));
+                }
+            }
+        }
+        vars
+    }
+
+    /// Rebuilds nested values from a map produced by [`Value::flatten`].
+    ///
+    /// Each key is split on `separator`. A level whose keys are exactly
+    /// `0..n` becomes an array and any other level becomes an object, so an
+    /// object that only had the keys `"0"`, `"1"`, ... comes back as an array,
+    /// and keys that contained the separator come back nested. When a key is
+    /// both a value and the prefix of other keys, as in `a` and `a.b`, the
+    /// nested keys win. A map with only the empty key unflattens to that value.
+    pub fn unflatten(flat: BTreeMap<String, Value>, separator: &str) -> Value {
+        let mut root = BTreeMap::new();
+        for (key, value) in flat {
+            if key.is_empty() {
+                if root.is_empty() {
+                    return value;
+                }
+                continue;
+            }
+
+            let mut segments = key.split(separator).peekable();
+            let mut map = &mut root;
+            while let Some(segment) = segments.next() {
+                if segments.peek().is_none() {
+                    map.entry(segment.to_string()).or_insert(value);
+                    break;
+                }
+
+                let entry = map
+                    .entry(segment.to_string())
+                    .or_insert_with(|| Value::Object(BTreeMap::new()));
+                if !matches!(entry, Value::Object(_)) {
+                    *entry = Value::Object(BTreeMap::new());
+                }
+                map = match entry {
+                    Value::Object(map) => map,
+                    _ => unreachable!(),
+                };
+            }
+        }
+
+        arrays_from_indices(Value::Object(root))
+    }
+}
+
+fn flatten_into(
+    value: &Value,
+    path: &mut String,
+    separator: &str,
+    flat: &mut BTreeMap<String, Value>,
+) {
+    let mut visit = |segment: &str, value: &Value, path: &mut String| {
+        let len = path.len();
+        if !path.is_empty() {
+            path.push_str(separator);
+        }
+        path.push_str(segment);
+        flatten_into(value, path, separator, flat);
+        path.truncate(len);
+    };
+
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                visit(key, value, path);
+            }
+        }
+        Value::Array(values) if !values.is_empty() => {
+            for (index, value) in values.iter().enumerate() {
+                visit(&index.to_string(), value, path);
+            }
+        }
+        _ => {
+            flat.insert(path.clone(), value.clone());
+        }
+    }
+}
+
+/// Turns the objects built by `unflatten` whose keys are `0..n` into arrays.
+fn arrays_from_indices(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let is_array = !map.is_empty()
+                && map.len() == map.keys().filter(|key| is_index(key, map.len())).count();
+            if is_array {
+                let mut entries: Vec<_> = map
+                    .into_iter()
+                    .map(|(key, value)| (key.parse::<usize>().unwrap_or(0), value))
+                    .collect();
+                entries.sort_by_key(|(index, _)| *index);
+                Value::Array(
+                    entries
+                        .into_iter()
+                        .map(|(_, value)| arrays_from_indices(value))
+                        .collect(),
+                )
+            } else {
+                Value::Object(
+                    map.into_iter()
+                        .map(|(key, value)| (key, arrays_from_indices(value)))
+                        .collect(),
+                )
+            }
+        }
+        other => other,
+    }
+}
+
+/// Whether `key` is the canonical spelling of an index below `len`.
+fn is_index(key: &str, len: usize) -> bool {
+    match key.parse::<usize>() {
+        Ok(index) => index < len && index.to_string() == key,
+        Err(_) => false,
+    }
+}
+
+/// The text of a value that isn't nested, as `to_query_string` and
+/// `to_env_vars` write it. Objects and arrays, which those write entry by
+/// entry, are empty here.
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::Bytes(string)
+        | Value::FrozenBytes(string)
+        | Value::Symbol(string)
+        | Value::Constant(string) => string.clone(),
+        Value::RawBytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        Value::Integer(integer) => integer.to_string(),
+        Value::Float(float) => format!("{:?}", float),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(decimal) => decimal.to_string(),
+        #[cfg(feature = "chrono")]
+        Value::DateTime(time) => time.to_rfc3339(),
+        #[cfg(feature = "chrono")]
+        Value::Date(date) => date.to_string(),
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::Object(_)
+        | Value::Struct { .. }
+        | Value::Array(_)
+        | Value::Null
+        | Value::Recursive => String::new(),
+    }
+}
+
+/// Appends `key=value` to a query string, encoding the value.
+fn push_pair(query: &mut String, key: &str, value: &[u8]) {
+    if !query.is_empty() {
+        query.push('&');
+    }
+    query.push_str(key);
+    query.push('=');
+    encode_into(query, value);
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn encode_into(out: &mut String, bytes: &[u8]) {
+    for &byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => {
+                let _ = write!(out, "%{:02X}", byte);
+            }
+        }
+    }
+}
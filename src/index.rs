@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{parse_with_options, parser::parse_entry_key, ParseError, ParseOptions, Value};
+
+/// The entries of a top-level object, indexed without parsing their values.
+/// Returned by [`parse_object_index`].
+#[derive(Debug, Clone)]
+pub struct ObjectIndex<'a> {
+    input: &'a str,
+    options: ParseOptions,
+    /// Where each key's value is in `input`.
+    entries: BTreeMap<String, Range<usize>>,
+}
+
+/// Scans the object in `input`, recording where each key's value is so that
+/// values can be parsed one at a time with [`ObjectIndex::get`]. This is much
+/// cheaper than [`parse`](crate::parse) when only a few keys of a large object
+/// are needed.
+///
+/// Keys are parsed and stored as they are in [`Value::Object`]. Values are only
+/// skipped over, following brackets, strings and `%w`/`%i` arrays, so a value
+/// that is malformed inside is reported by `get` rather than here.
+pub fn parse_object_index(input: &str) -> Result<ObjectIndex<'_>, ParseError> {
+    ObjectIndex::new(input, ParseOptions::default())
+}
+
+impl<'a> ObjectIndex<'a> {
+    fn new(input: &'a str, options: ParseOptions) -> Result<Self, ParseError> {
+        let error = |at: usize, message: &str| ParseError::Syntax {
+            offset: at,
+            message: message.to_string(),
+        };
+
+        let start = input.len() - input.trim_start().len();
+        if start == input.len() {
+            return Err(ParseError::Empty);
+        }
+        if !input[start..].starts_with('{') {
+            return Err(error(start, "expected '{'"));
+        }
+
+        let mut entries = BTreeMap::new();
+        let mut pos = start + 1;
+        loop {
+            pos = skip_space(input, pos);
+            // Either an empty object or a comma after the last entry.
+            if input[pos..].starts_with('}') {
+                break;
+            }
+
+            let (key, rest) = parse_entry_key(input, &input[pos..], &options)?;
+            let value_start = skip_space(input, input.len() - rest.len());
+            let value_end = value_start + value_len(&input[value_start..], '}');
+            if value_end == value_start {
+                return Err(error(value_start, "expected a value"));
+            }
+            entries.insert(key, value_start..value_end);
+
+            pos = skip_space(input, value_end);
+            match input[pos..].chars().next() {
+                Some(',') => pos += 1,
+                Some('}') => break,
+                Some(_) => return Err(error(pos, "expected '}' in map")),
+                None => return Err(error(pos, "expected '}', found end of input in map")),
+            }
+        }
+
+        let end = skip_space(input, pos + 1);
+        if end < input.len() {
+            return Err(error(end, "unexpected trailing input"));
+        }
+
+        Ok(ObjectIndex {
+            input,
+            options,
+            entries,
+        })
+    }
+
+    /// Like [`parse_object_index`], but values are parsed with `options`.
+    pub fn with_options(self, options: ParseOptions) -> Self {
+        ObjectIndex { options, ..self }
+    }
+
+    /// Parses the value for `key`, or returns `None` if the object has no
+    /// such key. Error offsets are into the whole input.
+    pub fn get(&self, key: &str) -> Option<Result<Value, ParseError>> {
+        let range = self.entries.get(key)?;
+        Some(
+            parse_with_options(&self.input[range.clone()], &self.options)
+                .map_err(|err| err.offset_by(range.start)),
+        )
+    }
+
+    /// The source text of the value for `key`.
+    pub fn raw(&self, key: &str) -> Option<&'a str> {
+        self.entries
+            .get(key)
+            .map(|range| &self.input[range.clone()])
+    }
+
+    /// The object's keys, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Skips whitespace and comments, like the parser does.
+fn skip_space(input: &str, pos: usize) -> usize {
+    let mut rest = &input[pos..];
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', '\r', '\n']);
+        match rest.strip_prefix('#') {
+            Some(comment) if !comment.starts_with('<') => {
+                rest = &comment[comment.find('\n').unwrap_or(comment.len())..];
+            }
+            _ => return input.len() - rest.len(),
+        }
+    }
+}
+
+/// The length of the value at the start of `text`, which ends at the first
+/// `,` or `close` outside any brackets, strings, percent arrays or comments.
+/// Trailing whitespace and comments aren't included.
+pub(crate) fn value_len(text: &str, close: char) -> usize {
+    let mut closers = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let mut end = 0;
+    while let Some((_, c)) = chars.next() {
+        match c {
+            ',' if closers.is_empty() => break,
+            _ if c == close && closers.is_empty() => break,
+            '"' | '\'' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        break;
+                    }
+                }
+            }
+            '%' if matches!(chars.peek(), Some((_, 'w')) | Some((_, 'i'))) => {
+                chars.next();
+                if let Some((_, open)) = chars.next() {
+                    let close = match open {
+                        '(' => ')',
+                        '[' => ']',
+                        '{' => '}',
+                        '<' => '>',
+                        other => other,
+                    };
+                    let mut nesting = 0;
+                    while let Some((_, next)) = chars.next() {
+                        match next {
+                            '\\' => {
+                                chars.next();
+                            }
+                            _ if next == close && nesting == 0 => break,
+                            _ if next == close => nesting -= 1,
+                            _ if next == open => nesting += 1,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            '#' if matches!(chars.peek(), Some((_, '<'))) => {
+                chars.next();
+                closers.push('>');
+            }
+            '#' => {
+                while chars.next_if(|(_, next)| *next != '\n').is_some() {}
+                continue;
+            }
+            '(' => closers.push(')'),
+            '[' => closers.push(']'),
+            '{' => closers.push('}'),
+            c if closers.last() == Some(&c) => {
+                closers.pop();
+            }
+            _ => {}
+        }
+        // Strings and percent arrays have been scanned to their end by now.
+        if !c.is_whitespace() {
+            end = chars.peek().map_or(text.len(), |(next, _)| *next);
+        }
+    }
+    end
+}
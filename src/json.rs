@@ -0,0 +1,268 @@
+//! Conversions to and from `serde_json::Value`.
+//!
+//! Both directions work from an explicit stack rather than by recursion, so
+//! that deeply nested values can't overflow the call stack.
+
+use std::{
+    collections::{btree_map, BTreeMap},
+    io, vec,
+};
+
+use serde_json::Map;
+
+use crate::{ObjectKey, Value};
+
+impl Value {
+    /// Writes the value as JSON to `w` as it goes, converting it the same way
+    /// as `serde_json::Value::from`, but without building a copy of it first.
+    /// `w` gets many small writes, so a file or socket should be wrapped in
+    /// an [`io::BufWriter`].
+    pub fn write_json(&self, w: &mut impl io::Write) -> io::Result<()> {
+        // What is left to write, with the innermost value last.
+        enum Pending<'v> {
+            Value(&'v Value),
+            Text(&'static str),
+            Key(&'v str),
+        }
+
+        let mut stack = vec![Pending::Value(self)];
+        while let Some(pending) = stack.pop() {
+            let value = match pending {
+                Pending::Value(value) => value,
+                Pending::Text(text) => {
+                    w.write_all(text.as_bytes())?;
+                    continue;
+                }
+                Pending::Key(key) => {
+                    serde_json::to_writer(&mut *w, json_key(key))?;
+                    w.write_all(b":")?;
+                    continue;
+                }
+            };
+
+            match value {
+                Value::Bytes(string)
+                | Value::FrozenBytes(string)
+                | Value::Symbol(string)
+                | Value::Constant(string) => serde_json::to_writer(&mut *w, string)?,
+                Value::RawBytes(bytes) => {
+                    serde_json::to_writer(&mut *w, &String::from_utf8_lossy(bytes))?
+                }
+                Value::Integer(integer) => write!(w, "{}", integer)?,
+                // serde_json writes NaN and the infinities as `null`.
+                Value::Float(float) => serde_json::to_writer(&mut *w, float)?,
+                #[cfg(feature = "decimal")]
+                Value::Decimal(decimal) => serde_json::to_writer(&mut *w, &decimal.to_string())?,
+                #[cfg(feature = "chrono")]
+                Value::DateTime(time) => serde_json::to_writer(&mut *w, &time.to_rfc3339())?,
+                #[cfg(feature = "chrono")]
+                Value::Date(date) => serde_json::to_writer(&mut *w, &date.to_string())?,
+                Value::Boolean(boolean) => write!(w, "{}", boolean)?,
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    w.write_all(b"{")?;
+                    stack.push(Pending::Text("}"));
+                    for (index, (key, value)) in map.iter().enumerate().rev() {
+                        stack.push(Pending::Value(value));
+                        stack.push(Pending::Key(key));
+                        if index > 0 {
+                            stack.push(Pending::Text(","));
+                        }
+                    }
+                }
+                Value::Array(values) => {
+                    w.write_all(b"[")?;
+                    stack.push(Pending::Text("]"));
+                    for (index, value) in values.iter().enumerate().rev() {
+                        stack.push(Pending::Value(value));
+                        if index > 0 {
+                            stack.push(Pending::Text(","));
+                        }
+                    }
+                }
+                Value::Null | Value::Recursive => w.write_all(b"null")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The JSON key for an object key. Symbol keys keep their colon, as they are
+/// stored, and other keys are their [`ObjectKey::text`].
+fn json_key(key: &str) -> &str {
+    match ObjectKey::of(key) {
+        ObjectKey::Symbol(_) => key,
+        other => other.text(),
+    }
+}
+
+/// Symbols and constants become JSON strings, structs become objects of their
+/// fields, and floats that JSON can't represent, such as NaN, become `null`.
+/// Decimals become strings of their digits, such as `"1.50"`, since a JSON
+/// number would be read back as a float. Times and dates become RFC 3339
+/// strings.
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        // A partly converted array or object, with the children still to
+        // convert and, for an object, the key of the one being converted.
+        enum Frame {
+            Array(Vec<serde_json::Value>, vec::IntoIter<Value>),
+            Object(
+                Map<String, serde_json::Value>,
+                btree_map::IntoIter<String, Value>,
+                String,
+            ),
+        }
+
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut value = value;
+        loop {
+            let mut done = match value {
+                Value::Bytes(string)
+                | Value::FrozenBytes(string)
+                | Value::Symbol(string)
+                | Value::Constant(string) => serde_json::Value::String(string),
+                Value::RawBytes(bytes) => {
+                    serde_json::Value::String(String::from_utf8_lossy(&bytes).into_owned())
+                }
+                Value::Integer(integer) => serde_json::Value::Number(integer.into()),
+                Value::Float(float) => serde_json::Number::from_f64(float)
+                    .map_or(serde_json::Value::Null, serde_json::Value::Number),
+                #[cfg(feature = "decimal")]
+                Value::Decimal(decimal) => serde_json::Value::String(decimal.to_string()),
+                #[cfg(feature = "chrono")]
+                Value::DateTime(time) => serde_json::Value::String(time.to_rfc3339()),
+                #[cfg(feature = "chrono")]
+                Value::Date(date) => serde_json::Value::String(date.to_string()),
+                Value::Boolean(boolean) => serde_json::Value::Bool(boolean),
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    let mut entries = map.into_iter();
+                    match entries.next() {
+                        Some((key, first)) => {
+                            stack.push(Frame::Object(Map::new(), entries, key));
+                            value = first;
+                            continue;
+                        }
+                        None => serde_json::Value::Object(Map::new()),
+                    }
+                }
+                Value::Array(values) => {
+                    let mut elements = values.into_iter();
+                    match elements.next() {
+                        Some(first) => {
+                            stack.push(Frame::Array(
+                                Vec::with_capacity(elements.len() + 1),
+                                elements,
+                            ));
+                            value = first;
+                            continue;
+                        }
+                        None => serde_json::Value::Array(Vec::new()),
+                    }
+                }
+                Value::Null | Value::Recursive => serde_json::Value::Null,
+            };
+
+            // Hand the converted value to its parent, finishing every parent
+            // whose last child it was.
+            value = loop {
+                match stack.last_mut() {
+                    None => return done,
+                    Some(Frame::Array(converted, rest)) => {
+                        converted.push(done);
+                        if let Some(next) = rest.next() {
+                            break next;
+                        }
+                    }
+                    Some(Frame::Object(converted, rest, key)) => {
+                        converted.insert(json_key(key).to_string(), done);
+                        if let Some((next_key, next)) = rest.next() {
+                            *key = next_key;
+                            break next;
+                        }
+                    }
+                }
+                done = match stack.pop() {
+                    Some(Frame::Array(converted, _)) => serde_json::Value::Array(converted),
+                    Some(Frame::Object(converted, ..)) => serde_json::Value::Object(converted),
+                    None => unreachable!(),
+                };
+            };
+        }
+    }
+}
+
+/// Integral JSON numbers that fit in an `i64` become `Value::Integer`, and all
+/// other numbers become `Value::Float`. Keys are all string keys, even those
+/// that start with `:`.
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        enum Frame {
+            Array(Vec<Value>, vec::IntoIter<serde_json::Value>),
+            Object(BTreeMap<String, Value>, serde_json::map::IntoIter, String),
+        }
+
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut value = value;
+        loop {
+            let mut done = match value {
+                serde_json::Value::Null => Value::Null,
+                serde_json::Value::Bool(boolean) => Value::Boolean(boolean),
+                serde_json::Value::Number(number) => match number.as_i64() {
+                    Some(integer) => Value::Integer(integer),
+                    None => number.as_f64().map_or(Value::Null, Value::Float),
+                },
+                serde_json::Value::String(string) => Value::Bytes(string),
+                serde_json::Value::Array(values) => {
+                    let mut elements = values.into_iter();
+                    match elements.next() {
+                        Some(first) => {
+                            stack.push(Frame::Array(
+                                Vec::with_capacity(elements.len() + 1),
+                                elements,
+                            ));
+                            value = first;
+                            continue;
+                        }
+                        None => Value::Array(Vec::new()),
+                    }
+                }
+                serde_json::Value::Object(map) => {
+                    let mut entries = map.into_iter();
+                    match entries.next() {
+                        Some((key, first)) => {
+                            stack.push(Frame::Object(Default::default(), entries, key));
+                            value = first;
+                            continue;
+                        }
+                        None => Value::Object(Default::default()),
+                    }
+                }
+            };
+
+            // Hand the converted value to its parent, as above.
+            value = loop {
+                match stack.last_mut() {
+                    None => return done,
+                    Some(Frame::Array(converted, rest)) => {
+                        converted.push(done);
+                        if let Some(next) = rest.next() {
+                            break next;
+                        }
+                    }
+                    Some(Frame::Object(converted, rest, key)) => {
+                        converted.insert(ObjectKey::String(key).stored().into_owned(), done);
+                        if let Some((next_key, next)) = rest.next() {
+                            *key = next_key;
+                            break next;
+                        }
+                    }
+                }
+                done = match stack.pop() {
+                    Some(Frame::Array(converted, _)) => Value::Array(converted),
+                    Some(Frame::Object(converted, ..)) => Value::Object(converted),
+                    None => unreachable!(),
+                };
+            };
+        }
+    }
+}
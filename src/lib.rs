@@ -0,0 +1,73 @@
+//! A parser for Ruby hash literals, such as those produced by `Hash#inspect`.
+
+mod access;
+#[cfg(feature = "quickcheck")]
+mod arbitrary;
+#[cfg(feature = "tokio")]
+mod async_read;
+#[cfg(all(feature = "serde", feature = "bincode"))]
+mod binary;
+mod builder;
+mod coerce;
+mod comments;
+#[cfg(feature = "serde")]
+mod de;
+mod diagnostic;
+mod error;
+mod escape;
+mod flat;
+mod flatten;
+mod index;
+#[cfg(feature = "serde_json")]
+mod json;
+mod merge;
+mod number;
+mod options;
+mod parser;
+mod query;
+mod read;
+mod ruby;
+#[cfg(feature = "serde")]
+mod ser;
+mod shape;
+mod shared;
+#[cfg(feature = "tokio")]
+mod split;
+mod token;
+mod value;
+mod walk;
+
+pub use access::{PathSegment, PointerError, PointerErrorKind, ValueIndex};
+#[cfg(feature = "tokio")]
+pub use async_read::{parse_array_from_async_read, parse_from_async_read, AsyncValues};
+pub use builder::{ArrayBuilder, ObjectBuilder};
+pub use coerce::{CoerceError, ValueType};
+pub use comments::{Comments, NodeComments};
+#[cfg(feature = "serde")]
+pub use de::{from_value, DeserializeError};
+pub use diagnostic::{Diagnostic, DiagnosticCode, Severity};
+pub use error::{ParseError, ReadError};
+pub use escape::EscapeError;
+pub use flat::parse_flat_symbol_hash;
+pub use flatten::QueryStringError;
+pub use index::{parse_object_index, ObjectIndex};
+pub use merge::{MergeRules, MergeStrategy};
+pub use number::Number;
+pub use options::{
+    DuplicateKeys, InvalidUnicode, InvalidUtf8, KeyStyle, ParseOptions, UnknownTokens,
+};
+pub use parser::{
+    parse, parse_call_args, parse_concatenated, parse_concatenated_with_options, parse_diagnostics,
+    parse_lenient, parse_lenient_with_diagnostics, parse_quoted, parse_value_at,
+    parse_with_comments, parse_with_data, parse_with_frozen, parse_with_key_spans,
+    parse_with_options, parse_with_scratch, scan_first_hash,
+};
+pub use query::{Query, QuerySegment};
+pub use read::parse_slice;
+#[cfg(feature = "serde")]
+pub use ser::{to_value, SerializeError};
+pub use shape::{Shape, ShapeError, ShapeErrorKind};
+pub use shared::SharedValue;
+pub use token::{tokenize, Token, TokenKind, Tokens};
+pub use value::{ObjectKey, Value};
+pub use walk::RenameKeysError;
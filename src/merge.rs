@@ -0,0 +1,132 @@
+//! Merging one value into another with different rules at different paths.
+
+use std::collections::{btree_map, BTreeMap};
+
+use crate::{access::unescape_segment, Value};
+
+/// How [`Value::merge_with_rules`] combines the value at a path with the one
+/// merged into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Take the new value.
+    Replace,
+    /// Append the new array's elements to the old array's. Anything other
+    /// than two arrays is replaced.
+    Concat,
+    /// Append the elements of the new array that the old array doesn't
+    /// already have, once each. Anything other than two arrays is replaced.
+    Union,
+    /// Merge two objects, or two structs with the same name, key by key,
+    /// each key with the rule for its own path. Keys only in the new value
+    /// are added. Anything else is replaced.
+    #[default]
+    DeepMerge,
+}
+
+/// Which [`MergeStrategy`] [`Value::merge_with_rules`] uses at each path,
+/// built up as in `MergeRules::new().rule("/tags", MergeStrategy::Concat)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeRules {
+    rules: Vec<(Vec<String>, MergeStrategy)>,
+    default: MergeStrategy,
+}
+
+impl MergeRules {
+    /// Rules that deep merge everywhere, until others are added.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `strategy` for the values at `pattern`, a JSON pointer such as
+    /// `/servers/*/tags`, in which a `*` segment matches any key.
+    /// Keys are matched as stored, so that a `:tags =>` key is `/:tags`. When
+    /// more than one rule matches a path, the first added wins.
+    pub fn rule(mut self, pattern: &str, strategy: MergeStrategy) -> Self {
+        let segments = match pattern.strip_prefix('/') {
+            Some(pattern) => pattern
+                .split('/')
+                .map(|segment| unescape_segment(segment).into_owned())
+                .collect(),
+            None => Vec::new(),
+        };
+        self.rules.push((segments, strategy));
+        self
+    }
+
+    /// Uses `strategy` where no rule matches, instead of
+    /// [`MergeStrategy::DeepMerge`].
+    pub fn default_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.default = strategy;
+        self
+    }
+
+    /// The strategy for the value at `path`.
+    fn strategy(&self, path: &[String]) -> MergeStrategy {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| {
+                pattern.len() == path.len()
+                    && pattern
+                        .iter()
+                        .zip(path)
+                        .all(|(pattern, segment)| pattern == "*" || pattern == segment)
+            })
+            .map_or(self.default, |(_, strategy)| *strategy)
+    }
+}
+
+impl Value {
+    /// Merges `other` into this value, combining the values at each path as
+    /// `rules` say, such as concatenating arrays under `/tags` but replacing
+    /// those under `/ids`. The whole value is at the empty path. Only a deep
+    /// merge goes on to the paths below it, so a rule below a path that is
+    /// replaced or concatenated never applies.
+    pub fn merge_with_rules(&mut self, other: Value, rules: &MergeRules) {
+        merge_at(self, other, rules, &mut Vec::new());
+    }
+}
+
+fn merge_at(existing: &mut Value, new: Value, rules: &MergeRules, path: &mut Vec<String>) {
+    match (rules.strategy(path), existing, new) {
+        (MergeStrategy::Concat, Value::Array(existing), Value::Array(new)) => existing.extend(new),
+        (MergeStrategy::Union, Value::Array(existing), Value::Array(new)) => {
+            for value in new {
+                if !existing.contains(&value) {
+                    existing.push(value);
+                }
+            }
+        }
+        (MergeStrategy::DeepMerge, Value::Object(existing), Value::Object(new)) => {
+            merge_maps(existing, new, rules, path)
+        }
+        (
+            MergeStrategy::DeepMerge,
+            Value::Struct { name, fields },
+            Value::Struct {
+                name: new_name,
+                fields: new_fields,
+            },
+        ) if *name == new_name => merge_maps(fields, new_fields, rules, path),
+        (_, existing, new) => *existing = new,
+    }
+}
+
+fn merge_maps(
+    existing: &mut BTreeMap<String, Value>,
+    new: BTreeMap<String, Value>,
+    rules: &MergeRules,
+    path: &mut Vec<String>,
+) {
+    for (key, value) in new {
+        match existing.entry(key) {
+            btree_map::Entry::Occupied(entry) => {
+                path.push(entry.key().clone());
+                merge_at(entry.into_mut(), value, rules, path);
+                path.pop();
+            }
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+}
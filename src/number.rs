@@ -0,0 +1,72 @@
+use crate::Value;
+
+/// A numeric value, for code that does math over parsed data without caring
+/// whether each number was written as an integer or a float. Returned by
+/// [`Value::as_number`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+}
+
+impl Number {
+    /// Whether the number was an integer, rather than a float that happens
+    /// to be whole.
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Number::Int(_))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Number::Float(_))
+    }
+
+    /// The number as an `i64`, if it is an integer. Floats aren't truncated;
+    /// see [`Value::coerce_to`] for converting whole floats.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Number::Int(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// The number as an `f64`. This is lossy for integers beyond 2^53 in
+    /// magnitude, which round to the nearest float, and for decimals with
+    /// more digits than a float holds.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(integer) => *integer as f64,
+            Number::Float(float) => *float,
+            #[cfg(feature = "decimal")]
+            Number::Decimal(decimal) => {
+                rust_decimal::prelude::ToPrimitive::to_f64(decimal).unwrap_or(f64::NAN)
+            }
+        }
+    }
+}
+
+impl From<Number> for Value {
+    fn from(number: Number) -> Self {
+        match number {
+            Number::Int(integer) => Value::Integer(integer),
+            Number::Float(float) => Value::Float(float),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(decimal) => Value::Decimal(decimal),
+        }
+    }
+}
+
+impl Value {
+    /// The number, if this is an integer or a float, or with the `decimal`
+    /// feature, a decimal. Numeric strings aren't converted.
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Integer(integer) => Some(Number::Int(*integer)),
+            Value::Float(float) => Some(Number::Float(*float)),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => Some(Number::Decimal(*decimal)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{Value, ValueType};
+
+/// Options controlling which syntax the parser accepts.
+///
+/// The defaults parse plain Ruby literals; everything else is opt-in.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Accept Elixir map literals such as `%{name: "x", "k" => 1}`.
+    ///
+    /// Elixir maps use the same `key: value` and `key => value` entries as
+    /// Ruby hashes, and their `:atom` keys and values parse like Ruby symbols.
+    /// The only new syntax is the leading `%` before the brace, which is off by
+    /// default because `%{...}` is a string literal in Ruby.
+    pub elixir_maps: bool,
+
+    /// Turn string values that hold a whole number, such as `"42"` or
+    /// `"-1.5e3"`, into `Value::Integer` or `Value::Float`.
+    ///
+    /// Strings with a leading zero (`"007"`) or surrounding whitespace
+    /// (`" 42 "`) are left alone unless `coerce_padded_numeric_strings` is
+    /// also set, and so are integers that don't fit in an `i64`. Only string
+    /// literals in value position are coerced, not keys or `%w` elements.
+    pub coerce_numeric_strings: bool,
+
+    /// With `coerce_numeric_strings`, also coerce strings with leading zeros
+    /// or surrounding whitespace.
+    pub coerce_padded_numeric_strings: bool,
+
+    /// Extra tokens accepted as `true`, such as `yes` or `t`. Like `true`
+    /// itself they have to stand as a whole word, so `t` doesn't match the
+    /// start of `tomorrow`.
+    pub truthy_tokens: Vec<String>,
+
+    /// Extra tokens accepted as `false`, such as `no` or `f`.
+    pub falsy_tokens: Vec<String>,
+
+    /// Builds the value for each integer literal, letting callers reinterpret
+    /// integers while parsing, for example to tag large values as epoch
+    /// timestamps. Defaults to `Value::Integer`.
+    pub on_integer: fn(i64) -> Value,
+
+    /// Parse parenthesized lists such as `(1, 2, 3)`, as emitted by some
+    /// serializers for tuples, into `Value::Array`. A single parenthesized
+    /// value like `(1)` is also an array, not a grouping.
+    pub parenthesized_arrays: bool,
+
+    /// What to do with a bareword in value position that isn't any known
+    /// literal, such as `undefined`.
+    pub on_unknown: UnknownTokens,
+
+    /// What to do with a `\u` escape that isn't a valid character, such as
+    /// the lone surrogate `"\uD800"`.
+    pub on_invalid_unicode: InvalidUnicode,
+
+    /// What [`parse_slice`](crate::parse_slice) does with input that isn't
+    /// valid UTF-8, such as binary data in a string of a Ruby dump.
+    pub invalid_utf8: InvalidUtf8,
+
+    /// The longest string, in bytes after decoding escapes, that may appear
+    /// in the input. This applies to keys as well as values, and to the words
+    /// of `%w` and `%i` arrays. A longer string fails the parse with a message
+    /// naming where it is by its JSON pointer, when it is inside a collection.
+    pub max_string_len: Option<usize>,
+
+    /// The most elements any one array may have, such as a list argument an
+    /// API caps, counting a trailing hash of entries as one. An array with
+    /// more fails the parse at its first element past the limit, with a
+    /// message naming the array by its JSON pointer. Arrays made of the
+    /// lines of an [`indent_mode`](Self::indent_mode) document aren't limited.
+    pub max_array_len: Option<usize>,
+
+    /// The most entries any one hash may have, counting each entry as
+    /// written, even one whose key repeats another. As with
+    /// [`max_array_len`](Self::max_array_len), a hash with more fails the
+    /// parse at its first entry past the limit, naming the hash. Fields of
+    /// inspected objects aren't limited.
+    pub max_object_keys: Option<usize>,
+
+    /// Ignore `.freeze`, `.dup` and `.to_s` calls after a string, array or
+    /// hash literal, such as `"x".freeze`, in any number and order. No other
+    /// method calls are accepted. [`parse_with_frozen`](crate::parse_with_frozen)
+    /// reports which literals were left frozen.
+    pub ignore_trailing_calls: bool,
+
+    /// With `ignore_trailing_calls`, parse string literals left frozen by
+    /// their calls, such as `"x".freeze`, as `Value::FrozenBytes` rather than
+    /// `Value::Bytes`, so that tools can keep them frozen. A later `.dup`
+    /// makes an unfrozen copy again.
+    pub record_frozen: bool,
+
+    /// Read integers with a leading zero, such as `0755`, as octal like Ruby
+    /// does. By default they are decimal, so `0755` is 755. Either way `0`
+    /// and `00` are zero.
+    pub leading_zero_octal: bool,
+
+    /// Parse every integer and float literal as an exact `Value::Decimal`,
+    /// for data such as prices where binary float rounding isn't acceptable.
+    /// A literal with more digits than a decimal can hold fails the parse
+    /// rather than being rounded. `on_integer` isn't called for these.
+    #[cfg(feature = "decimal")]
+    pub decimal_numbers: bool,
+
+    /// Parse times as `Time#inspect` writes them, such as
+    /// `2023-01-01 12:00:00 UTC` or `2023-01-01 12:00:00.5 +0100`, into
+    /// `Value::DateTime`, and bare dates such as `2023-01-01` into
+    /// `Value::Date`. Text shaped like either that isn't a real time or date,
+    /// such as `2023-02-30`, fails the parse.
+    #[cfg(feature = "chrono")]
+    pub strict_timestamps: bool,
+
+    /// Whether object keys are stored as written or all as one kind.
+    pub key_style: KeyStyle,
+
+    /// Extra characters allowed in bare keys written as `key: value`, such
+    /// as `-` and `.` for `content-type: "text"`. Keys still have to start
+    /// with a letter, digit or underscore. Symbols like `:a-b` are unaffected.
+    pub extra_key_chars: Vec<char>,
+
+    /// Accept bare words as keys before `=>`, such as `{ name => "x" }` in
+    /// some non-strict dumps, storing them as string keys. In Ruby these
+    /// would be method calls, so they need quoting or a colon.
+    pub bareword_arrow_keys: bool,
+
+    /// Split symbol keys with dots, such as `:"a.b" => 1` or `"a.b": 1`,
+    /// into nested objects, as in `{:a => {:b => 1}}`, the way i18n and
+    /// config dumps mean them. Entries that share a prefix end up in the same
+    /// object. String keys like `"a.b" => 1` are kept whole unless `key_style`
+    /// makes every key a symbol.
+    pub split_dotted_keys: bool,
+
+    /// What to do when two keys of a hash are the same once stored, such as
+    /// `:a =>` and `a:` with `key_style: AllStrings`, or `:"a.b"` and a
+    /// later `a:` with `split_dotted_keys`. Defaults to keeping the last.
+    pub on_duplicate_key: DuplicateKeys,
+
+    /// Accept Ruby's `Integer(...)` and `Float(...)` conversions around a
+    /// string or number, such as `Integer("42")` or `Float("1.5")`, as the
+    /// number they produce. An argument that Ruby would reject, such as
+    /// `Integer("x")`, fails the parse. With the `decimal` feature,
+    /// `BigDecimal("1.23")` is accepted too, as an exact `Value::Decimal`.
+    pub conversion_calls: bool,
+
+    /// In [`parse_concatenated_with_options`](crate::parse_concatenated_with_options),
+    /// also accept YAML-style `---` lines between values, as in some exports.
+    /// Each value still stands on its own, so a separator may come before
+    /// the first value or between two values, but not inside one.
+    pub document_separators: bool,
+
+    /// In [`parse_concatenated_with_options`](crate::parse_concatenated_with_options),
+    /// also accept `;` between values, as in `1; 2; nil` from a REPL
+    /// capture. A `;` inside a string or hash is part of it as usual, and
+    /// values separated by `;` need no whitespace between them.
+    pub semicolon_separators: bool,
+
+    /// The prefixes that start a comment running to the end of the line,
+    /// such as `//` or `--` for near-Ruby formats from other tools. Defaults
+    /// to just `#`. Comments are only recognized between tokens, so a prefix
+    /// inside a string is part of the string. Like `#`, no comment starts at
+    /// `#<`, which opens an inspected object.
+    pub line_comments: Vec<String>,
+
+    /// Also skip `/* ... */` comments, which may span lines but don't nest.
+    pub block_comments: bool,
+
+    /// Read the whole input in [`parse_with_options`](crate::parse_with_options)
+    /// as an indented document instead, YAML-style: lines of `key: value`
+    /// make a hash and lines of `- value` a list, and a `key:` or `-` with
+    /// nothing after it holds the more indented lines below it, or a list at
+    /// its own indentation. A `- key: value` item starts a hash that the
+    /// lines indented to match continue. Values are Ruby literals as
+    /// elsewhere, so strings need quotes and `[1, 2]` or `{a: 1}` work
+    /// inline. Indentation is all spaces or all tabs, whichever comes first,
+    /// and mixing them fails the parse, so [`tab_width`](Self::tab_width)
+    /// doesn't apply.
+    pub indent_mode: bool,
+
+    /// How many columns a tab advances to the next multiple of, when working
+    /// out the indentation that a `<<~` heredoc removes from its lines. A
+    /// body indented with a mix of tabs and spaces loses the same columns
+    /// from each line, and a tab that reaches past them is kept. Defaults to
+    /// 8, as in Ruby. A width of 0 counts as 1.
+    pub tab_width: usize,
+
+    /// Fail the parse unless the value is a hash, as a config file has to
+    /// be, rather than accepting a bare `42` or `[1, 2]`. The error is at
+    /// the start of the value and names the type found.
+    pub require_object_root: bool,
+
+    /// Accept the `{...}` and `[...]` that Ruby's `inspect` writes in place
+    /// of a hash or array that contains itself, as in `{:a=>{...}}`, as
+    /// `Value::Recursive`, so that the rest of the structure can still be
+    /// read. The marker doesn't say what it refers to.
+    pub recursion_markers: bool,
+
+    /// The types of value that may appear anywhere in the input, such as
+    /// only objects, arrays, strings, integers and booleans for a strict
+    /// endpoint. Any other value fails the parse, at the value. Object keys
+    /// aren't values, so `:name => 1` is allowed without symbols. Defaults to
+    /// `None`, which allows every type.
+    pub allowed_types: Option<Vec<ValueType>>,
+}
+
+/// How [`ParseOptions::on_unknown`] treats unrecognized barewords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownTokens {
+    /// Fail the parse.
+    #[default]
+    Strict,
+    /// Keep the bareword's text as a `Value::Bytes`. The bareword runs up to
+    /// the next whitespace, comma, bracket or quote. This includes
+    /// pseudo-constants like `__FILE__`, but not `__END__`.
+    CaptureAsString,
+}
+
+/// How [`ParseOptions::on_invalid_unicode`] treats `\u` escapes for surrogates
+/// and code points above `U+10FFFF`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidUnicode {
+    /// Fail the parse, as Ruby does.
+    #[default]
+    Strict,
+    /// Decode the escape as U+FFFD, the replacement character.
+    Replace,
+    /// Keep the escape as written, backslash included.
+    Preserve,
+}
+
+/// How [`ParseOptions::invalid_utf8`] treats bytes of the input that aren't
+/// valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidUtf8 {
+    /// Fail the parse at the first such byte, so that corruption is noticed.
+    #[default]
+    Error,
+    /// Decode each run of such bytes as U+FFFD, the replacement character.
+    Lossy,
+    /// Keep the bytes, so that a string with them in it is a
+    /// `Value::RawBytes`. They are decoded as U+FFFD anywhere else, such as
+    /// in a key or symbol.
+    Raw,
+}
+
+/// How [`ParseOptions::key_style`] stores object keys. Keys written as
+/// `:name => value` are normally stored as `":name"`, and all other keys
+/// without a colon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyStyle {
+    /// Store keys as written.
+    #[default]
+    Preserve,
+    /// Store every key without a colon, so `:name =>`, `name:` and
+    /// `"name" =>` are all `"name"`.
+    AllStrings,
+    /// Store every key with a colon, so all three of those are `":name"`.
+    /// Keys that aren't valid bare symbols, such as `"a b"` or `1`, fail the
+    /// parse.
+    AllSymbols,
+}
+
+/// How [`ParseOptions::on_duplicate_key`] resolves keys that collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Keep the value of the last entry, as Ruby does.
+    #[default]
+    Last,
+    /// Keep the value of the first entry.
+    First,
+    /// Fail the parse at the second key, naming both as they were written.
+    Error,
+    /// Merge the entries' values if both are objects, recursively, and
+    /// otherwise keep the last.
+    Merge,
+    /// Concatenate the entries' values if both are arrays, so that
+    /// `{a: [1], a: [2]}` is `{a: [1, 2]}`, and otherwise keep the last.
+    MergeArrays,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            elixir_maps: false,
+            coerce_numeric_strings: false,
+            coerce_padded_numeric_strings: false,
+            truthy_tokens: Vec::new(),
+            falsy_tokens: Vec::new(),
+            on_integer: Value::Integer,
+            parenthesized_arrays: false,
+            on_unknown: UnknownTokens::Strict,
+            on_invalid_unicode: InvalidUnicode::Strict,
+            invalid_utf8: InvalidUtf8::Error,
+            max_string_len: None,
+            max_array_len: None,
+            max_object_keys: None,
+            ignore_trailing_calls: false,
+            record_frozen: false,
+            leading_zero_octal: false,
+            #[cfg(feature = "decimal")]
+            decimal_numbers: false,
+            #[cfg(feature = "chrono")]
+            strict_timestamps: false,
+            key_style: KeyStyle::Preserve,
+            extra_key_chars: Vec::new(),
+            bareword_arrow_keys: false,
+            split_dotted_keys: false,
+            on_duplicate_key: DuplicateKeys::Last,
+            conversion_calls: false,
+            document_separators: false,
+            semicolon_separators: false,
+            line_comments: vec!["#".to_string()],
+            block_comments: false,
+            indent_mode: false,
+            tab_width: 8,
+            require_object_root: false,
+            recursion_markers: false,
+            allowed_types: None,
+        }
+    }
+}
@@ -0,0 +1,3323 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{char, digit1, satisfy},
+    combinator::{consumed, cut, map, not, opt, recognize, value},
+    error::{context, ContextError, ErrorKind, FromExternalError, ParseError, VerboseError},
+    multi::{many0, many0_count, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    AsChar, IResult, InputTakeAtPosition, Offset, Parser,
+};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{btree_map, BTreeMap, BTreeSet},
+    fmt,
+    num::ParseIntError,
+    ops::Range,
+};
+
+#[cfg(feature = "decimal")]
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::{
+    comments::Comments,
+    diagnostic::{Diagnostic, DiagnosticCode, Severity},
+    escape::{unescape_double, unescape_one, unescape_single},
+    index::value_len,
+    ruby::is_bare_symbol,
+    shape::push_segment,
+    token::{Token, TokenKind},
+    DuplicateKeys, KeyStyle, ObjectKey, ParseOptions, UnknownTokens, Value, ValueType,
+};
+
+trait HashParseError<T>: ParseError<T> + ContextError<T> + FromExternalError<T, ParseIntError> {}
+impl<T, E: ParseError<T> + ContextError<T> + FromExternalError<T, ParseIntError>> HashParseError<T>
+    for E
+{
+}
+
+/// Skips whitespace and `#` comments, which run to the end of the line. A
+/// `#<` starts an inspected object rather than a comment.
+fn sp<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    let chars = " \t\r\n";
+
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches(|c| chars.contains(c));
+        match rest.strip_prefix('#') {
+            Some(comment) if !comment.starts_with('<') => {
+                rest = &comment[comment.find('\n').unwrap_or(comment.len())..];
+            }
+            _ => return Ok((rest, &input[..input.len() - rest.len()])),
+        }
+    }
+}
+
+/// Skips whitespace and the comments that
+/// [`line_comments`](ParseOptions::line_comments) and
+/// [`block_comments`](ParseOptions::block_comments) ask for.
+fn sp_with<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    options: &ParseOptions,
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    let chars = " \t\r\n";
+
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches(|c| chars.contains(c));
+        if rest.starts_with("#<") {
+            break;
+        }
+        if options.block_comments {
+            if let Some(comment) = rest.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(end) => {
+                        rest = &comment[end + 2..];
+                        continue;
+                    }
+                    None => return Err(failure(rest, "unterminated comment")),
+                }
+            }
+        }
+        let prefix = options
+            .line_comments
+            .iter()
+            .find(|prefix| !prefix.is_empty() && rest.starts_with(prefix.as_str()));
+        match prefix {
+            Some(prefix) => {
+                let comment = &rest[prefix.len()..];
+                rest = &comment[comment.find('\n').unwrap_or(comment.len())..];
+            }
+            None => break,
+        }
+    }
+    Ok((rest, &input[..input.len() - rest.len()]))
+}
+
+/// How deeply arrays and hashes may nest before parsing fails, so that
+/// adversarial input can't overflow the stack. Unoptimized builds take the
+/// most stack per level, and this leaves them room on the 2 MiB stack that
+/// spawned threads get by default.
+const MAX_DEPTH: usize = 64;
+
+/// State shared by the recursive parsers for the duration of one parse.
+#[derive(Debug)]
+struct State<'o> {
+    options: &'o ParseOptions,
+    depth: Cell<usize>,
+    /// Buffer reused for decoding escapes, so that each string only allocates
+    /// its final value.
+    scratch: RefCell<Vec<u8>>,
+    /// The heredocs seen so far, whose bodies whitespace skips over.
+    heredocs: RefCell<Vec<Heredoc>>,
+    /// A fuller message for the failure being raised, for messages that have
+    /// more in them than the static one nom carries, along with the static
+    /// one it replaces. See [`State::error`].
+    detail: RefCell<Option<(&'static str, String)>>,
+    /// The JSON pointer of the value being parsed, kept only while something
+    /// needs it: tracking where things are, or naming a collection that is
+    /// too long.
+    path: RefCell<String>,
+    /// Whether to skip entries and elements that fail to parse, as
+    /// [`parse_lenient_with_diagnostics`] does, rather than failing, and to
+    /// report keys that repeat.
+    recover: Cell<bool>,
+    /// The problems found while recovering, in the order they were found.
+    diagnostics: RefCell<Vec<Problem>>,
+    /// Where things are, for [`parse_with_frozen`], [`parse_with_comments`]
+    /// and [`parse_with_key_spans`].
+    tracked: Option<RefCell<Tracked>>,
+}
+
+/// What [`parse_with_frozen`], [`parse_with_comments`] and
+/// [`parse_with_key_spans`] find while
+/// parsing. Everything is keyed by the address of its first byte, as for a
+/// [`Problem`], so that something parsed again after backtracking is only
+/// found once.
+#[derive(Debug, Default)]
+struct Tracked {
+    /// The pointer of each frozen literal.
+    frozen: BTreeMap<usize, String>,
+    /// The end and pointer of each value inside an array or hash.
+    spans: BTreeMap<usize, (usize, String)>,
+    /// The end of each comment.
+    comments: BTreeMap<usize, usize>,
+    /// The end and pointer of each key of a hash, pointing at its value.
+    keys: BTreeMap<usize, (usize, String)>,
+}
+
+/// A problem found while recovering. Positions are addresses of bytes of
+/// the input, which, like nom's [`Offset`], become offsets once the whole
+/// input is known again.
+#[derive(Debug)]
+struct Problem {
+    start: usize,
+    end: usize,
+    severity: Severity,
+    message: String,
+}
+
+/// The static message of a duplicate key failure, which the full message
+/// naming the keys replaces.
+const DUPLICATE_KEY: &str = "duplicate key";
+
+/// The static messages of failures for strings and collections that are too
+/// long, which fuller messages naming the value and the limit replace.
+const STRING_TOO_LONG: &str = "string exceeds max_string_len";
+const ARRAY_TOO_LONG: &str = "array exceeds max_array_len";
+const HASH_TOO_LONG: &str = "hash exceeds max_object_keys";
+
+/// Where a heredoc is in the input. Positions are the length of the input
+/// remaining from there, which is the same whichever suffix of the input a
+/// parser is looking at.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Heredoc {
+    /// Just after the `<<ID` opener.
+    opener: usize,
+    /// The newline that ends the opener's line, after which the body starts.
+    line_end: usize,
+    /// Just after the terminator line.
+    body_end: usize,
+}
+
+impl<'o> State<'o> {
+    fn new(options: &'o ParseOptions) -> Self {
+        State {
+            options,
+            depth: Cell::new(0),
+            scratch: RefCell::new(Vec::new()),
+            heredocs: RefCell::new(Vec::new()),
+            detail: RefCell::new(None),
+            path: RefCell::new(String::new()),
+            recover: Cell::new(false),
+            diagnostics: RefCell::new(Vec::new()),
+            tracked: None,
+        }
+    }
+
+    /// Records entering a nested array or hash. The returned guard leaves it
+    /// again when dropped.
+    fn descend<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        input: &'a str,
+    ) -> Result<DepthGuard<'_>, nom::Err<E>> {
+        if self.depth.get() >= MAX_DEPTH {
+            return Err(failure(input, "maximum nesting depth exceeded"));
+        }
+        self.depth.set(self.depth.get() + 1);
+        Ok(DepthGuard(&self.depth))
+    }
+}
+
+impl State<'_> {
+    /// Runs `parser` for the value at `segments` below the one being parsed,
+    /// so that [`parse_with_frozen`] knows where the literals in it are.
+    fn nested<'a, O, E, S: fmt::Display>(
+        &self,
+        segments: &[S],
+        mut parser: impl Parser<&'a str, O, E>,
+        input: &'a str,
+    ) -> IResult<&'a str, O, E> {
+        let limited = self.options.max_string_len.is_some()
+            || self.options.max_array_len.is_some()
+            || self.options.max_object_keys.is_some();
+        if self.tracked.is_none() && !limited {
+            return parser.parse(input);
+        }
+        let len = self.path.borrow().len();
+        for segment in segments {
+            push_segment(&mut self.path.borrow_mut(), &segment.to_string());
+        }
+        let result = parser.parse(input);
+        self.path.borrow_mut().truncate(len);
+        result
+    }
+
+    /// Fails at `input`, a string or an element of the collection being
+    /// parsed or of its element `index`, if its length `len` is more than
+    /// `limit` allows. `message` is the static message, which is made fuller
+    /// with the pointer of the string or collection and the limit.
+    fn check_len<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        input: &'a str,
+        len: usize,
+        limit: Option<usize>,
+        index: Option<usize>,
+        message: &'static str,
+    ) -> Result<(), nom::Err<E>> {
+        let limit = match limit {
+            Some(limit) if len > limit => limit,
+            _ => return Ok(()),
+        };
+        let mut path = self.path.borrow().clone();
+        if let Some(index) = index {
+            push_segment(&mut path, &index.to_string());
+        }
+        let detail = match message.split_once(' ') {
+            Some((what, rest)) if !path.is_empty() => {
+                format!("{} at {} {} of {}", what, path, rest, limit)
+            }
+            _ => format!("{} of {}", message, limit),
+        };
+        *self.detail.borrow_mut() = Some((message, detail));
+        Err(failure(input, message))
+    }
+
+    /// Records that the value at `segments` below the one being parsed runs
+    /// from the start of `input`, after any whitespace, to `rest`, for
+    /// [`parse_with_comments`].
+    fn record_span<S: fmt::Display>(&self, segments: &[S], input: &str, rest: &str) {
+        let tracked = match &self.tracked {
+            Some(tracked) => tracked,
+            None => return,
+        };
+        let (start, _) = sp_with::<()>(self.options, input).unwrap_or((input, ""));
+        let mut path = self.path.borrow().clone();
+        for segment in segments {
+            push_segment(&mut path, &segment.to_string());
+        }
+        let end = rest.as_ptr() as usize;
+        let mut tracked = tracked.borrow_mut();
+        tracked.spans.insert(start.as_ptr() as usize, (end, path));
+    }
+
+    /// Records that the key of the entry at `segments` below the one being
+    /// parsed is written as `spelling`, for [`parse_with_key_spans`].
+    fn record_key<S: fmt::Display>(&self, segments: &[S], spelling: &str) {
+        let tracked = match &self.tracked {
+            Some(tracked) => tracked,
+            None => return,
+        };
+        let mut path = self.path.borrow().clone();
+        for segment in segments {
+            push_segment(&mut path, &segment.to_string());
+        }
+        let start = spelling.as_ptr() as usize;
+        tracked
+            .borrow_mut()
+            .keys
+            .insert(start, (start + spelling.len(), path));
+    }
+
+    /// Records the comments in `skipped`, which [`sp_with`] skipped over.
+    fn record_comments(&self, skipped: &str) {
+        let tracked = match &self.tracked {
+            Some(tracked) => tracked,
+            None => return,
+        };
+        let mut rest = skipped.trim_start();
+        while !rest.is_empty() {
+            let len = match rest.strip_prefix("/*") {
+                Some(comment) if self.options.block_comments => {
+                    comment.find("*/").map_or(rest.len(), |end| end + 4)
+                }
+                _ => rest.find('\n').unwrap_or(rest.len()),
+            };
+            let comment = rest[..len].trim_end();
+            let start = comment.as_ptr() as usize;
+            tracked
+                .borrow_mut()
+                .comments
+                .insert(start, start + comment.len());
+            rest = rest[len..].trim_start();
+        }
+    }
+
+    /// Skips whitespace like [`sp_with`], and at the end of a line that
+    /// opened heredocs, skips their bodies too.
+    fn sp<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str, E> + '_ {
+        move |input| {
+            let mut rest = input;
+            loop {
+                let (after, _) = sp_with(self.options, rest)?;
+                let heredocs = self.heredocs.borrow();
+                let skipped = rest[..rest.len() - after.len()]
+                    .match_indices('\n')
+                    .find_map(|(index, _)| {
+                        let line_end = rest.len() - index;
+                        heredocs
+                            .iter()
+                            .filter(|heredoc| heredoc.line_end == line_end)
+                            .map(|heredoc| heredoc.body_end)
+                            .min()
+                    });
+                match skipped {
+                    Some(body_end) => rest = &input[input.len() - body_end..],
+                    None => {
+                        self.record_comments(&rest[..rest.len() - after.len()]);
+                        return Ok((after, &input[..input.len() - after.len()]));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Builds an object key in the style the options ask for. `marked` is
+    /// whether the key was written as a `:name =>` symbol, which keeps its
+    /// colon when keys are preserved.
+    fn styled_key<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        input: &'a str,
+        name: String,
+        marked: bool,
+    ) -> Result<String, nom::Err<E>> {
+        match self.options.key_style {
+            KeyStyle::Preserve if marked => Ok(format!(":{}", name)),
+            KeyStyle::Preserve | KeyStyle::AllStrings => {
+                Ok(ObjectKey::String(&name).stored().into_owned())
+            }
+            KeyStyle::AllSymbols if is_bare_symbol(&name) => Ok(format!(":{}", name)),
+            KeyStyle::AllSymbols
+                if self.options.split_dotted_keys && name.split('.').all(is_bare_symbol) =>
+            {
+                Ok(format!(":{}", name))
+            }
+            KeyStyle::AllSymbols => Err(failure(input, "key is not a valid symbol")),
+        }
+    }
+
+    /// Builds an object from entries parsed by [`parse_tracked_entry`],
+    /// merging the objects that share a prefix of their paths. A key that is
+    /// already there, or an earlier value that isn't an object where a path
+    /// has to nest, is resolved as `on_duplicate_key` asks.
+    fn build_object<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        entries: Vec<(&'a str, Vec<String>, Value)>,
+    ) -> Result<BTreeMap<String, Value>, nom::Err<E>> {
+        let policy = self.options.on_duplicate_key;
+        let track = policy == DuplicateKeys::Error || self.recover.get();
+        let mut root = BTreeMap::new();
+        // How each path was first written, for the error.
+        let mut spellings: BTreeMap<Vec<String>, &str> = BTreeMap::new();
+        'entries: for (spelling, path, value) in entries {
+            let mut map = &mut root;
+            for (depth, segment) in path.iter().enumerate() {
+                let last = depth + 1 == path.len();
+                let first = match track {
+                    true => *spellings.entry(path[..=depth].to_vec()).or_insert(spelling),
+                    false => spelling,
+                };
+                let existing = match map.entry(segment.clone()) {
+                    btree_map::Entry::Vacant(entry) if last => {
+                        entry.insert(value);
+                        continue 'entries;
+                    }
+                    btree_map::Entry::Vacant(entry) => entry.insert(Value::Object(BTreeMap::new())),
+                    btree_map::Entry::Occupied(entry) => {
+                        let existing = entry.into_mut();
+                        if last || !matches!(existing, Value::Object(_)) {
+                            if self.recover.get() && policy != DuplicateKeys::Error {
+                                self.diagnostics.borrow_mut().push(Problem {
+                                    start: spelling.as_ptr() as usize,
+                                    end: spelling.as_ptr() as usize + spelling.len(),
+                                    severity: Severity::Warning,
+                                    message: duplicate_key_message(spelling, first),
+                                });
+                            }
+                            match policy {
+                                DuplicateKeys::First => continue 'entries,
+                                DuplicateKeys::Error => {
+                                    return Err(self.duplicate_key(spelling, first))
+                                }
+                                DuplicateKeys::Merge if last => {
+                                    merge_values(existing, value);
+                                    continue 'entries;
+                                }
+                                DuplicateKeys::MergeArrays if last => {
+                                    match (&mut *existing, value) {
+                                        (Value::Array(values), Value::Array(more)) => {
+                                            values.extend(more)
+                                        }
+                                        (_, value) => *existing = value,
+                                    }
+                                    continue 'entries;
+                                }
+                                DuplicateKeys::Last | DuplicateKeys::Merge if last => {
+                                    *existing = value;
+                                    continue 'entries;
+                                }
+                                DuplicateKeys::Last
+                                | DuplicateKeys::Merge
+                                | DuplicateKeys::MergeArrays => {
+                                    *existing = Value::Object(BTreeMap::new())
+                                }
+                            }
+                        }
+                        existing
+                    }
+                };
+                map = match existing {
+                    Value::Object(map) => map,
+                    _ => unreachable!(),
+                };
+            }
+        }
+        Ok(root)
+    }
+
+    /// Fails at the key `spelling` for colliding with the one written as
+    /// `first`, keeping the fuller message to replace the static one with.
+    fn duplicate_key<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        spelling: &'a str,
+        first: &str,
+    ) -> nom::Err<E> {
+        *self.detail.borrow_mut() = Some((DUPLICATE_KEY, duplicate_key_message(spelling, first)));
+        failure(spelling, DUPLICATE_KEY)
+    }
+
+    /// Records an error at its offset into `input`, covering the input up to
+    /// where `end` starts.
+    fn record(&self, input: &str, end: &str, error: crate::ParseError) {
+        let (offset, message) = match error {
+            crate::ParseError::Syntax { offset, message } => (offset, message),
+            crate::ParseError::Empty => (0, error.to_string()),
+        };
+        let start = input.as_ptr() as usize + offset.min(input.len());
+        self.diagnostics.borrow_mut().push(Problem {
+            start,
+            end: (end.as_ptr() as usize).max(start),
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    /// The problems found so far, with their positions in `input`, which has
+    /// to be the input they were found in.
+    fn take_diagnostics(&self, input: &str) -> Vec<Diagnostic> {
+        let base = input.as_ptr() as usize;
+        let diagnostics = self.diagnostics.take().into_iter();
+        diagnostics
+            .map(|problem| Diagnostic {
+                range: problem.start - base..problem.end - base,
+                severity: problem.severity,
+                code: DiagnosticCode::classify(&problem.message),
+                message: problem.message,
+            })
+            .collect()
+    }
+
+    /// Converts a failure into a [`ParseError`](crate::ParseError), with the
+    /// fuller message left by the parser that raised it, if any.
+    fn error(&self, input: &str, err: VerboseError<&str>) -> crate::ParseError {
+        let mut error = crate::ParseError::from_verbose(input, err);
+        if let (Some((short, detail)), crate::ParseError::Syntax { message, .. }) =
+            (self.detail.take(), &mut error)
+        {
+            // A value that is too long is already named by the detail, so
+            // the context nom adds after it is left off.
+            *message = if [STRING_TOO_LONG, ARRAY_TOO_LONG, HASH_TOO_LONG].contains(&short) {
+                detail
+            } else {
+                message.replacen(short, &detail, 1)
+            };
+        }
+        error
+    }
+
+    /// Describes why the hash entry, if `close` is `}`, or else the list
+    /// element at `input` failed to parse, by parsing it again without
+    /// recovering inside it.
+    fn describe(&self, input: &str, close: char) -> crate::ParseError {
+        let pending = self.detail.take();
+        self.recover.set(false);
+        let result = if close == '}' {
+            parse_key_value::<VerboseError<&str>>(self)(input).map(|_| ())
+        } else {
+            parse_list_element::<VerboseError<&str>>(self)(input).map(|_| ())
+        };
+        self.recover.set(true);
+        match result {
+            Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => self.error(input, err),
+            // The element parses by itself, but is one more than its
+            // collection may have.
+            _ => crate::ParseError::Syntax {
+                offset: 0,
+                message: pending
+                    .map_or_else(|| "unexpected input".to_string(), |(_, detail)| detail),
+            },
+        }
+    }
+
+    /// Fails at `input` if `string` is longer than the options allow.
+    fn check_string_len<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        input: &'a str,
+        string: &str,
+    ) -> Result<(), nom::Err<E>> {
+        let limit = self.options.max_string_len;
+        self.check_len(input, string.len(), limit, None, STRING_TOO_LONG)
+    }
+
+    /// Fails at `input` if the options don't allow values of this type.
+    fn check_type<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+        &self,
+        input: &'a str,
+        value: &Value,
+    ) -> Result<(), nom::Err<E>> {
+        let ty = value.value_type();
+        match &self.options.allowed_types {
+            Some(allowed) if !allowed.contains(&ty) => Err(failure(
+                input,
+                match ty {
+                    ValueType::Bytes => "strings are not allowed",
+                    ValueType::Symbol => "symbols are not allowed",
+                    ValueType::Constant => "constants are not allowed",
+                    ValueType::Integer => "integers are not allowed",
+                    ValueType::Float => "floats are not allowed",
+                    #[cfg(feature = "decimal")]
+                    ValueType::Decimal => "decimals are not allowed",
+                    #[cfg(feature = "chrono")]
+                    ValueType::DateTime => "times are not allowed",
+                    #[cfg(feature = "chrono")]
+                    ValueType::Date => "dates are not allowed",
+                    ValueType::Boolean => "booleans are not allowed",
+                    ValueType::Object => "objects are not allowed",
+                    ValueType::Struct => "structs are not allowed",
+                    ValueType::Array => "arrays are not allowed",
+                    ValueType::Null => "nil is not allowed",
+                    ValueType::Recursive => "recursion markers are not allowed",
+                },
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the value for a string literal, coercing it to a number if the
+    /// options ask for that.
+    fn string_value(&self, bytes: String) -> Value {
+        if self.options.coerce_numeric_strings {
+            if let Some(number) = coerce_numeric(&bytes, self.options.coerce_padded_numeric_strings)
+            {
+                return number;
+            }
+        }
+        Value::Bytes(bytes)
+    }
+}
+
+/// Parses `text` as an integer or float if all of it is a number. As with
+/// literals, a decimal point or an exponent makes it a float, so `"3e2"` is
+/// `300.0` and `"3"` is `3`.
+fn coerce_numeric(text: &str, padded: bool) -> Option<Value> {
+    let text = if padded { text.trim() } else { text };
+    let unsigned = text.strip_prefix('-').unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(index) => (&unsigned[..index], Some(&unsigned[index + 1..])),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.find('.') {
+        Some(index) => (&mantissa[..index], Some(&mantissa[index + 1..])),
+        None => (mantissa, None),
+    };
+
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !digits(whole) || !fraction.is_none_or(digits) {
+        return None;
+    }
+    if let Some(exponent) = exponent {
+        if !digits(
+            exponent
+                .strip_prefix(|c| c == '-' || c == '+')
+                .unwrap_or(exponent),
+        ) {
+            return None;
+        }
+    }
+    if !padded && whole.len() > 1 && whole.starts_with('0') {
+        return None;
+    }
+
+    if fraction.is_none() && exponent.is_none() {
+        text.parse().ok().map(Value::Integer)
+    } else {
+        text.parse().ok().map(Value::Float)
+    }
+}
+
+struct DepthGuard<'s>(&'s Cell<usize>);
+
+impl Drop for DepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+/// The message for the key `spelling` colliding with the one written as
+/// `first`.
+fn duplicate_key_message(spelling: &str, first: &str) -> String {
+    if spelling == first {
+        format!("{} {}", DUPLICATE_KEY, spelling)
+    } else {
+        format!("{} {}, first written as {}", DUPLICATE_KEY, spelling, first)
+    }
+}
+
+/// Builds an unrecoverable error whose message is `message` itself.
+fn failure<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+    message: &'static str,
+) -> nom::Err<E> {
+    nom::Err::Failure(E::add_context(
+        input,
+        message,
+        E::from_error_kind(input, ErrorKind::Fail),
+    ))
+}
+
+/// Recognizes the content between two delimiters, leaving the closing
+/// delimiter unconsumed. Empty content (`""`) matches without consuming
+/// anything, so the closing delimiter is always the next character. The
+/// content runs to the first delimiter that isn't escaped with a backslash,
+/// or to the end of input, in a single pass, so that strings with many
+/// escapes take time in proportion to their length.
+fn parse_inner_str<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    delimiter: char,
+) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str, E> {
+    move |input| {
+        let mut chars = input.char_indices();
+        while let Some((index, c)) = chars.next() {
+            if c == delimiter {
+                return Ok((&input[index..], &input[..index]));
+            }
+            if c == '\\' {
+                // Whatever is escaped, including the delimiter, is content.
+                chars.next();
+            }
+        }
+        Ok(("", input))
+    }
+}
+
+/// Parses text with a given delimiter. A missing closing delimiter is reported
+/// as an unterminated string at the opening one.
+fn parse_str<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    delimiter: char,
+) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str, E> {
+    move |input| {
+        let (rest, content) = preceded(char(delimiter), parse_inner_str(delimiter))(input)?;
+        match char::<_, E>(delimiter)(rest) {
+            Ok((rest, _)) => Ok((rest, content)),
+            Err(_) => Err(failure(input, "unterminated string")),
+        }
+    }
+}
+
+/// Matches `word` only as a whole word, so that `t` doesn't match the start of
+/// `tomorrow`.
+fn keyword<'a, 'w, E: ParseError<&'a str>>(
+    word: &'w str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str, E> + 'w {
+    move |input| {
+        terminated(
+            tag(word),
+            not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+        )(input)
+    }
+}
+
+/// Parses `true` and `false`, along with any extra tokens configured in the
+/// options.
+fn parse_boolean<'a, 's, E: ParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, bool, E> + 's {
+    move |input| {
+        let options = state.options;
+        let truthy =
+            std::iter::once("true").chain(options.truthy_tokens.iter().map(String::as_str));
+        let falsy = std::iter::once("false").chain(options.falsy_tokens.iter().map(String::as_str));
+        let words = truthy
+            .map(|word| (word, true))
+            .chain(falsy.map(|word| (word, false)));
+
+        for (word, boolean) in words {
+            if let Ok((rest, _)) = keyword::<E>(word)(input) {
+                return Ok((rest, boolean));
+            }
+        }
+        Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag)))
+    }
+}
+
+fn parse_nil<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Value, E> {
+    value(Value::Null, keyword("nil"))(input)
+}
+
+/// Converts CRLF line endings to LF so that string content read from files
+/// with Windows line endings doesn't accumulate stray `\r` characters.
+fn normalize_newlines(content: &str) -> Cow<'_, str> {
+    if content.contains("\r\n") {
+        Cow::Owned(content.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Parses a double-quoted string and decodes its escapes.
+fn parse_double_quoted<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        let (rest, content) = parse_str('"')(input)?;
+        let mut scratch = state.scratch.borrow_mut();
+        let decoded = unescape_double(
+            &normalize_newlines(content),
+            &mut scratch,
+            state.options.on_invalid_unicode,
+        );
+        match decoded {
+            Some(decoded) => Ok((rest, decoded)),
+            None => Err(failure(input, "invalid escape sequence")),
+        }
+    }
+}
+
+/// Parses a character literal such as `?a`, which Ruby reads as the string
+/// `"a"`. An escape after the `?`, as in `?\n` or `?\u{263A}`, decodes as it
+/// would in a double-quoted string, and may stand for more than one
+/// character, as `?\u{61 62}` does. A word character has to end the word, so
+/// that `?ab` isn't `"a"` followed by `b`.
+fn parse_char_literal<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        let after = match input.strip_prefix('?') {
+            Some(after) => after,
+            None => return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Char))),
+        };
+        let (rest, text) = match after.strip_prefix('\\') {
+            Some(escape) => match unescape_one(escape, state.options.on_invalid_unicode) {
+                Some((text, len)) => (&escape[len..], text),
+                None => return Err(failure(after, "invalid escape sequence")),
+            },
+            None => {
+                let c = match after.chars().next() {
+                    Some(c) if !c.is_whitespace() => c,
+                    _ => return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Char))),
+                };
+                let rest = &after[c.len_utf8()..];
+                let word = |c: char| c.is_alphanumeric() || c == '_';
+                if word(c) && rest.starts_with(word) {
+                    return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Char)));
+                }
+                (rest, c.to_string())
+            }
+        };
+        Ok((rest, state.string_value(text)))
+    }
+}
+
+/// Parses a single-quoted string and decodes its escapes.
+fn parse_single_quoted<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, String, E> {
+    map(parse_str('\''), |content| {
+        unescape_single(&normalize_newlines(content), '\'')
+    })(input)
+}
+
+fn parse_string<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        let (rest, string) = alt((parse_double_quoted(state), parse_single_quoted))(input)?;
+        state.check_string_len(input, &string)?;
+        Ok((rest, string))
+    }
+}
+
+fn parse_bytes<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| context("bytes", parse_string(state))(input)
+}
+
+/// Parses a heredoc such as `<<~EOS`, whose body starts on the next line and
+/// runs up to a line holding just `EOS`. The rest of the opener's line is
+/// parsed as usual, and can open more heredocs, whose bodies follow in turn.
+///
+/// With `<<`, the body is kept exactly as written, trailing newline included,
+/// and the terminator has to start its line. `<<-` lets the terminator be
+/// indented, and `<<~` also removes the indentation common to the body's
+/// lines, ignoring lines of only whitespace and counting tabs as reaching
+/// the next multiple of [`tab_width`](ParseOptions::tab_width) columns, 8 by
+/// default. A quoted `<<'EOS'` body has no
+/// escapes; otherwise escapes are decoded as in double-quoted strings.
+fn parse_heredoc<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        let identifier = || {
+            recognize(pair(
+                satisfy(|c: char| c.is_ascii_alphabetic() || c == '_'),
+                take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+            ))
+        };
+        let (rest, (flavor, (quote, id))) = preceded(
+            tag("<<"),
+            pair(
+                opt(alt((char('~'), char('-')))),
+                alt((
+                    map(delimited(char('\''), identifier(), char('\'')), |id| {
+                        (Some('\''), id)
+                    }),
+                    map(delimited(char('"'), identifier(), char('"')), |id| {
+                        (Some('"'), id)
+                    }),
+                    map(identifier(), |id| (None, id)),
+                )),
+            ),
+        )(input)?;
+
+        let line_end = match rest.find('\n') {
+            Some(index) => rest.len() - index,
+            None => return Err(failure(input, "unterminated heredoc")),
+        };
+        let opener = rest.len();
+        let mut heredocs = state.heredocs.borrow_mut();
+        // The body follows the bodies of earlier heredocs on the same line.
+        let body_start = heredocs
+            .iter()
+            .filter(|heredoc| heredoc.line_end == line_end && heredoc.opener > opener)
+            .map(|heredoc| heredoc.body_end)
+            .min()
+            .unwrap_or(line_end - 1);
+        let text = &rest[rest.len() - body_start..];
+
+        let mut pos = 0;
+        let (body_len, body_end) = loop {
+            if pos >= text.len() {
+                return Err(failure(input, "unterminated heredoc"));
+            }
+            let end = text[pos..]
+                .find('\n')
+                .map_or(text.len(), |index| pos + index);
+            let line = text[pos..end].trim_end_matches('\r');
+            let line = match flavor {
+                Some(_) => line.trim_start_matches([' ', '\t']),
+                None => line,
+            };
+            if line == id {
+                break (pos, text.len() - (end + 1).min(text.len()));
+            }
+            pos = end + 1;
+        };
+        if !heredocs.iter().any(|heredoc| heredoc.opener == opener) {
+            heredocs.push(Heredoc {
+                opener,
+                line_end,
+                body_end,
+            });
+        }
+
+        let body = normalize_newlines(&text[..body_len]);
+        let body = match flavor {
+            Some('~') => Cow::Owned(dedent(&body, state.options.tab_width)),
+            _ => body,
+        };
+        let string = match quote {
+            Some('\'') => body.into_owned(),
+            _ => {
+                let mut scratch = state.scratch.borrow_mut();
+                match unescape_double(&body, &mut scratch, state.options.on_invalid_unicode) {
+                    Some(decoded) => decoded,
+                    None => return Err(failure(input, "invalid escape sequence")),
+                }
+            }
+        };
+        state.check_string_len(input, &string)?;
+        Ok((rest, string))
+    }
+}
+
+/// Parses a heredoc joined to more heredocs or strings with `+`, as in
+/// `<<~A + <<~B`, whose bodies follow the line in order.
+fn parse_heredoc_sum<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        let (rest, (mut string, parts)) = pair(
+            parse_heredoc(state),
+            many0(preceded(
+                tuple((state.sp(), char('+'), state.sp())),
+                alt((parse_heredoc(state), parse_string(state))),
+            )),
+        )(input)?;
+        if !parts.is_empty() {
+            string.extend(parts);
+            state.check_string_len(input, &string)?;
+        }
+        Ok((rest, string))
+    }
+}
+
+/// Removes the indentation common to all lines of a `<<~` heredoc body that
+/// aren't just whitespace, with tabs advancing to the next multiple of
+/// `tab_width` columns.
+fn dedent(body: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let indent = body
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut width = 0;
+            for c in line.chars() {
+                match c {
+                    ' ' => width += 1,
+                    '\t' => width = (width / tab_width + 1) * tab_width,
+                    _ => break,
+                }
+            }
+            width
+        })
+        .min()
+        .unwrap_or(0);
+
+    let mut result = String::with_capacity(body.len());
+    for line in body.split_inclusive('\n') {
+        let mut width = 0;
+        let mut cut = 0;
+        for (index, c) in line.char_indices() {
+            width = match c {
+                ' ' => width + 1,
+                '\t' => (width / tab_width + 1) * tab_width,
+                _ => break,
+            };
+            if width > indent {
+                break;
+            }
+            cut = index + 1;
+        }
+        result.push_str(&line[cut..]);
+    }
+    result
+}
+
+/// Parses a `%w` word array or `%i` symbol array. The delimiter is one of the
+/// bracket pairs, which may nest inside the array, or any other punctuation
+/// character, which also closes the array. A backslash escapes the delimiter,
+/// a backslash, or whitespace, which is then part of the element, so
+/// `%w[a\ b c]` is `["a b", "c"]`.
+fn parse_percent_array<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| percent_array(state, input)
+}
+
+fn percent_array<'a, E: HashParseError<&'a str>>(
+    state: &State,
+    input: &'a str,
+) -> IResult<&'a str, Value, E> {
+    let (rest, kind) = preceded(char('%'), alt((char('w'), char('i'))))(input)?;
+    let (rest, open) = satisfy(|c| !c.is_alphanumeric() && !c.is_whitespace())(rest)?;
+    let close = match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        other => other,
+    };
+
+    let push = |elements: &mut Vec<Value>, word: String| {
+        state.check_string_len(input, &word)?;
+        let element = match kind {
+            'w' => Value::Bytes(word),
+            _ => Value::Symbol(word),
+        };
+        state.check_type(input, &element)?;
+        elements.push(element);
+        let limit = state.options.max_array_len;
+        state.check_len(input, elements.len(), limit, None, ARRAY_TOO_LONG)
+    };
+
+    let mut elements = Vec::new();
+    let mut word = String::new();
+    let mut depth = 0;
+    let mut chars = rest.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, next))
+                    if next == open
+                        || next == close
+                        || matches!(next, '\\' | ' ' | '\t' | '\r' | '\n' | '\x0b' | '\x0c') =>
+                {
+                    word.push(next)
+                }
+                Some((_, next)) => {
+                    word.push('\\');
+                    word.push(next);
+                }
+                None => break,
+            },
+            c if c == close && depth == 0 => {
+                if !word.is_empty() {
+                    push(&mut elements, word)?;
+                }
+                return Ok((&rest[index + c.len_utf8()..], Value::Array(elements)));
+            }
+            c if c == close => {
+                depth -= 1;
+                word.push(c);
+            }
+            c if c == open => {
+                depth += 1;
+                word.push(c);
+            }
+            ' ' | '\t' | '\r' | '\n' | '\x0b' | '\x0c' => {
+                if !word.is_empty() {
+                    push(&mut elements, std::mem::take(&mut word))?;
+                }
+            }
+            c => word.push(c),
+        }
+    }
+
+    Err(failure(input, "unterminated percent array"))
+}
+
+/// Parses a time as `Time#inspect` writes it, such as `2023-01-01 12:00:00
+/// UTC`, or a bare date such as `2023-01-01`, when `strict_timestamps` is
+/// set. Once the date matches, the rest has to make a real time or date.
+#[cfg(feature = "chrono")]
+fn parse_timestamp<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if !state.options.strict_timestamps {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag)));
+        }
+        use nom::{bytes::complete::take_while_m_n, character::complete::one_of};
+
+        let digits = |n| take_while_m_n(n, n, |c: char| c.is_ascii_digit());
+        let word_end = || not(satisfy(|c: char| c.is_alphanumeric() || c == '_'));
+
+        let (rest, date) = recognize(tuple((
+            digits(4),
+            char('-'),
+            digits(2),
+            char('-'),
+            digits(2),
+        )))(input)?;
+        let (rest, time) = opt(preceded(
+            char(' '),
+            recognize(tuple((
+                digits(2),
+                char(':'),
+                digits(2),
+                char(':'),
+                digits(2),
+                opt(pair(char('.'), digit1)),
+            ))),
+        ))(rest)?;
+
+        let time = match time {
+            Some(time) => time,
+            None => {
+                let (rest, _) =
+                    word_end()(rest).map_err(|_: nom::Err<E>| failure(input, "invalid date"))?;
+                return match chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                    Ok(date) => Ok((rest, Value::Date(date))),
+                    Err(_) => Err(failure(input, "invalid date")),
+                };
+            }
+        };
+        let (rest, zone) = terminated(
+            preceded(
+                char(' '),
+                alt((tag("UTC"), recognize(pair(one_of("+-"), digits(4))))),
+            ),
+            word_end(),
+        )(rest)
+        .map_err(|_: nom::Err<E>| failure(rest, "expected a time zone"))?;
+        let zone = if zone == "UTC" { "+0000" } else { zone };
+        let text = format!("{} {} {}", date, time, zone);
+        match chrono::DateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f %z") {
+            Ok(time) => Ok((rest, Value::DateTime(time))),
+            Err(_) => Err(failure(input, "invalid time")),
+        }
+    }
+}
+
+/// Parses a timestamp where `strict_timestamps` allows one, and otherwise a
+/// number.
+fn timestamp_or_number<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        #[cfg(feature = "chrono")]
+        if state.options.strict_timestamps {
+            return alt((parse_timestamp(state), parse_number(state)))(input);
+        }
+        parse_number(state)(input)
+    }
+}
+
+/// Parses an integer or float literal. Literals with a decimal point or an
+/// exponent are floats and everything else is an integer, except that a
+/// decimal integer too big for an `i64`, as in `9223372036854775808`, is a
+/// float too. Either may have a
+/// leading `+` or `-`, and so may a float's exponent, as in `+1.5e+3`. Digits
+/// may be grouped with underscores, as in `1_000.000_1e1_0`.
+fn parse_number<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if let Some(sign) = input.chars().next().filter(|c| *c == '+' || *c == '-') {
+            let after = &input[1..];
+            if after
+                .chars()
+                .next()
+                .is_none_or(|c| " \t\r\n,)]}>".contains(c))
+            {
+                return Err(failure(
+                    input,
+                    if sign == '+' {
+                        "expected digits after '+'"
+                    } else {
+                        "expected digits after '-'"
+                    },
+                ));
+            }
+        }
+
+        if let Ok((rest, float)) = parse_non_finite(input) {
+            return Ok((rest, Value::Float(float)));
+        }
+
+        match parse_prefixed_integer(input) {
+            Ok((rest, integer)) => {
+                #[cfg(feature = "decimal")]
+                if state.options.decimal_numbers {
+                    return Ok((rest, Value::Decimal(integer.into())));
+                }
+                return Ok((rest, (state.options.on_integer)(integer)));
+            }
+            Err(nom::Err::Error(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        let (rest, text) = recognize_number(input)?;
+        let text = &*without_separators(text);
+        #[cfg(feature = "decimal")]
+        if state.options.decimal_numbers {
+            return match parse_decimal(text, state.options.leading_zero_octal) {
+                Ok(decimal) => Ok((rest, Value::Decimal(decimal))),
+                Err(message) => Err(failure(input, message)),
+            };
+        }
+        if text.contains(['.', 'e', 'E']) {
+            match text.parse() {
+                Ok(float) => Ok((rest, Value::Float(float))),
+                Err(_) => Err(failure(input, "invalid float")),
+            }
+        } else {
+            match parse_integer(text, state.options.leading_zero_octal) {
+                Ok(integer) => Ok((rest, (state.options.on_integer)(integer))),
+                // Ruby would make a bignum of a decimal integer that doesn't
+                // fit, and a float is the closest there is.
+                Err(_) if !is_octal(text, state.options.leading_zero_octal) => match text.parse() {
+                    Ok(float) => Ok((rest, Value::Float(float))),
+                    Err(_) => Err(failure(input, "invalid float")),
+                },
+                Err(message) => Err(failure(input, message)),
+            }
+        }
+    }
+}
+
+/// Parses an integer with a radix prefix, `0x`, `0b`, `0o` or `0d` in either
+/// case, as in `0x1F` or `-0b101`. Underscores may go between digits, as in
+/// other numbers.
+fn parse_prefixed_integer<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, i64, E> {
+    let (unsigned, sign) = opt(alt((char('+'), char('-'))))(input)?;
+    let (after, prefix) = preceded(char('0'), satisfy(|c| "xXbBoOdD".contains(c)))(unsigned)?;
+    let (radix, expected) = match prefix.to_ascii_lowercase() {
+        'x' => (16, "expected digits after '0x'"),
+        'b' => (2, "expected digits after '0b'"),
+        'o' => (8, "expected digits after '0o'"),
+        _ => (10, "expected digits after '0d'"),
+    };
+    let digits = || take_while1(move |c: char| c.is_digit(radix));
+    let result = recognize(pair(digits(), many0_count(pair(char('_'), digits()))))(after);
+    let (rest, digits) = match result {
+        Ok(parsed) => parsed,
+        Err(nom::Err::Error(_)) => return Err(failure(after, expected)),
+        Err(err) => return Err(err),
+    };
+    if rest.starts_with('_') {
+        return Err(failure(
+            rest,
+            "underscore in a number must be between digits",
+        ));
+    }
+    if rest.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(failure(rest, "invalid digit for the integer's radix"));
+    }
+    let text = format!("{}{}", sign.unwrap_or('+'), without_separators(digits));
+    match i64::from_str_radix(&text, radix) {
+        Ok(integer) => Ok((rest, integer)),
+        Err(_) => Err(failure(input, "integer out of range")),
+    }
+}
+
+/// Recognizes a number literal, which is an optional sign, digits with an
+/// optional fraction, or a fraction alone, and then an optional exponent.
+/// Each run of digits may have single underscores between its digits, but an
+/// underscore anywhere else, such as next to the `.`, `e` or sign, fails.
+fn recognize_number<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    let misplaced = |at| failure(at, "underscore in a number must be between digits");
+    let digits = || recognize(pair(digit1, many0_count(pair(char('_'), digit1))));
+    let sign = || alt((char('+'), char('-')));
+    let no_underscore = |input: &'a str| match input.starts_with('_') {
+        true => Err(misplaced(input)),
+        false => Ok((input, ())),
+    };
+
+    let (rest, _) = tuple((
+        opt(pair(sign(), no_underscore)),
+        alt((
+            recognize(pair(digits(), opt(pair(char('.'), opt(digits()))))),
+            recognize(pair(char('.'), digits())),
+        )),
+        opt(tuple((
+            alt((char('e'), char('E'))),
+            opt(sign()),
+            no_underscore,
+            cut(digits()),
+        ))),
+        no_underscore,
+    ))(input)?;
+    Ok((rest, &input[..input.len() - rest.len()]))
+}
+
+/// Removes the underscores that group the digits of a number literal.
+fn without_separators(text: &str) -> Cow<'_, str> {
+    match text.contains('_') {
+        true => Cow::Owned(text.replace('_', "")),
+        false => Cow::Borrowed(text),
+    }
+}
+
+/// Parses a signed run of digits, reading it as octal if it has a leading
+/// zero and `leading_zero_octal` is set.
+fn parse_integer(text: &str, leading_zero_octal: bool) -> Result<i64, &'static str> {
+    let digits = text.trim_start_matches(['+', '-']);
+    if is_octal(text, leading_zero_octal) {
+        if digits.contains(['8', '9']) {
+            return Err("invalid octal digit");
+        }
+        let magnitude = i64::from_str_radix(digits, 8).map_err(|_| "integer out of range")?;
+        return Ok(if text.starts_with('-') {
+            -magnitude
+        } else {
+            magnitude
+        });
+    }
+    text.parse().map_err(|_| "integer out of range")
+}
+
+/// Whether [`parse_integer`] reads `text` as octal.
+fn is_octal(text: &str, leading_zero_octal: bool) -> bool {
+    let digits = text.trim_start_matches(['+', '-']);
+    leading_zero_octal && digits.len() > 1 && digits.starts_with('0')
+}
+
+/// Reads a number literal as an exact decimal, failing rather than rounding
+/// when it has more digits than a decimal can hold.
+#[cfg(feature = "decimal")]
+fn parse_decimal(text: &str, leading_zero_octal: bool) -> Result<Decimal, &'static str> {
+    use std::convert::TryFrom;
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (
+            &text[..at],
+            text[at + 1..]
+                .parse::<i32>()
+                .map_err(|_| "exponent out of range")?,
+        ),
+        None => (text, 0),
+    };
+    let digits = text.trim_start_matches(['+', '-']);
+    if leading_zero_octal && exponent == 0 && digits.len() > 1 && digits.starts_with('0') {
+        return parse_integer(text, leading_zero_octal).map(Decimal::from);
+    }
+    let mantissa = mantissa.strip_prefix('+').unwrap_or(mantissa);
+    let mut decimal = Decimal::from_str_exact(mantissa.strip_suffix('.').unwrap_or(mantissa))
+        .map_err(|_| "decimal out of range")?;
+
+    // Zero has no digits to move, however large its exponent, and any other
+    // exponent past the most digits a decimal has can't fit.
+    if decimal.is_zero() {
+        decimal.set_scale(0).map_err(|_| "decimal out of range")?;
+        return Ok(decimal);
+    }
+    if exponent.unsigned_abs() > Decimal::MAX_SCALE {
+        return Err("exponent out of range");
+    }
+
+    // Moving the decimal point right first uses up the mantissa's scale and
+    // then multiplies, and moving it left only raises the scale.
+    let scale = i64::from(decimal.scale()) - i64::from(exponent);
+    if scale < 0 {
+        decimal.set_scale(0).map_err(|_| "decimal out of range")?;
+        for _ in 0..-scale {
+            decimal = decimal
+                .checked_mul(Decimal::TEN)
+                .ok_or("decimal out of range")?;
+        }
+    } else {
+        let scale = u32::try_from(scale).map_err(|_| "decimal out of range")?;
+        decimal
+            .set_scale(scale)
+            .map_err(|_| "decimal out of range")?;
+    }
+    Ok(decimal)
+}
+
+fn parse_symbol_key<T, E: ParseError<T>>(input: T) -> IResult<T, T, E>
+where
+    T: std::fmt::Display,
+    T: InputTakeAtPosition,
+    <T as InputTakeAtPosition>::Item: AsChar,
+{
+    take_while1(move |item: <T as InputTakeAtPosition>::Item| {
+        let c = item.as_char();
+        c.is_alphanum() || c == '_'
+    })(input)
+}
+
+/// The operator method names that can be written as bare symbols, such as
+/// `:+` or `:[]=`. Longer names come before their prefixes.
+pub(crate) const OPERATOR_SYMBOLS: &[&str] = &[
+    "[]=", "[]", "<=>", "===", "==", "=~", "!=", "!~", "!", "<<", "<=", "<", ">>", ">=", ">", "**",
+    "+@", "-@", "+", "-", "*", "/", "%", "~", "&", "|", "^",
+];
+
+fn parse_operator_symbol<'a, E: ParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    match OPERATOR_SYMBOLS.iter().find(|op| input.starts_with(*op)) {
+        Some(op) => Ok((&input[op.len()..], &input[..op.len()])),
+        None => Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag))),
+    }
+}
+
+/// Parses the name of a bare symbol after its `:`: an identifier, which may
+/// end in `?`, `!` or `=` as method names do, or an operator. The `=` of
+/// `:a=>` starts the arrow rather than ending the name. An identifier may
+/// start with underscores, as in `:_x` and `:__method__`; `:__END__` and
+/// `:__FILE__` are symbols like any other, since only the barewords mean
+/// something.
+fn parse_symbol_name<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    let suffix = alt((
+        terminated(alt((char('?'), char('!'))), not(char('='))),
+        terminated(char('='), not(alt((char('='), char('~'), char('>'))))),
+    ));
+    alt((
+        recognize(pair(parse_symbol_key, opt(suffix))),
+        parse_operator_symbol,
+    ))(input)
+}
+
+/// Parses a symbol such as `:name`, `:+` or `:"quoted name"`. Quoted symbols
+/// unescape like strings with the same quotes, so only `\\` and `\'` are
+/// escapes in `:'it\'s'`.
+fn parse_symbol<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| map(parse_symbol_text(state), Value::Symbol)(input)
+}
+
+/// Parses a symbol like [`parse_symbol`], returning its name.
+fn parse_symbol_text<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        preceded(
+            char(':'),
+            alt((parse_string(state), map(parse_symbol_name, String::from))),
+        )(input)
+    }
+}
+
+/// Recognizes a constant path like `ActiveSupport::TimeZone`, where every
+/// segment starts with an uppercase letter.
+fn constant_path<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    let segment = || {
+        preceded(
+            satisfy(|c| c.is_uppercase()),
+            take_while(|c: char| c.is_alphanumeric() || c == '_'),
+        )
+    };
+    recognize(pair(segment(), many0(preceded(tag("::"), segment()))))(input)
+}
+
+fn parse_constant<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Value, E> {
+    map(constant_path, |path: &str| {
+        Value::Constant(path.to_string())
+    })(input)
+}
+
+/// The conversions that [`parse_conversion_call`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Conversion {
+    Integer,
+    Float,
+    #[cfg(feature = "decimal")]
+    BigDecimal,
+}
+
+/// Parses `Integer(...)` and `Float(...)` calls around a string or number,
+/// such as `Integer("42")`, converting the argument as Ruby does. Strings may
+/// have surrounding whitespace, underscores between digits and, for
+/// integers, a `0x`, `0b`, `0o` or `0` prefix, and `Integer(4.7)` truncates;
+/// a number that doesn't fit in an `i64` is out of range.
+/// With the `decimal` feature, `BigDecimal("1.23")` is an exact decimal.
+fn parse_conversion_call<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if !state.options.conversion_calls {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag)));
+        }
+        #[cfg(feature = "decimal")]
+        let big_decimal = value(Conversion::BigDecimal, tag("BigDecimal("));
+        #[cfg(not(feature = "decimal"))]
+        let big_decimal = nom::combinator::fail;
+        let (args, conversion) = alt((
+            value(Conversion::Integer, tag("Integer(")),
+            value(Conversion::Float, tag("Float(")),
+            big_decimal,
+        ))(input)?;
+        let _depth = state.descend(args)?;
+        let (arg_start, _) = state.sp()(args)?;
+        let (rest, arg) = cut(terminated(
+            parse_value(state),
+            preceded(state.sp(), char(')')),
+        ))(arg_start)?;
+
+        let converted = match conversion {
+            Conversion::Integer => {
+                let integer = match arg {
+                    Value::Integer(integer) => Some(integer),
+                    // `i64::MAX as f64` rounds up to 2^63, which is itself
+                    // out of range, and `as` would saturate to it.
+                    Value::Float(float)
+                        if float.is_finite()
+                            && float.trunc() >= i64::MIN as f64
+                            && float.trunc() < i64::MAX as f64 =>
+                    {
+                        Some(float.trunc() as i64)
+                    }
+                    Value::Float(float) if float.is_finite() => {
+                        return Err(failure(arg_start, "integer out of range"))
+                    }
+                    #[cfg(feature = "decimal")]
+                    Value::Decimal(decimal) => match decimal.trunc().to_i64() {
+                        Some(integer) => Some(integer),
+                        None => return Err(failure(arg_start, "integer out of range")),
+                    },
+                    Value::Bytes(text) => ruby_integer(&text),
+                    _ => None,
+                };
+                integer.map(state.options.on_integer)
+            }
+            Conversion::Float => {
+                let float = match arg {
+                    Value::Integer(integer) => Some(integer as f64),
+                    Value::Float(float) => Some(float),
+                    #[cfg(feature = "decimal")]
+                    Value::Decimal(decimal) => decimal.to_f64(),
+                    Value::Bytes(text) => ruby_float(&text),
+                    _ => None,
+                };
+                float.map(Value::Float)
+            }
+            #[cfg(feature = "decimal")]
+            Conversion::BigDecimal => {
+                let decimal = match arg {
+                    Value::Integer(integer) => Some(Decimal::from(integer)),
+                    Value::Decimal(decimal) => Some(decimal),
+                    Value::Bytes(text) => ruby_big_decimal(&text),
+                    _ => None,
+                };
+                decimal.map(Value::Decimal)
+            }
+        };
+        match converted {
+            Some(value) => Ok((rest, value)),
+            None => Err(failure(
+                arg_start,
+                match conversion {
+                    Conversion::Integer => "invalid value for Integer()",
+                    Conversion::Float => "invalid value for Float()",
+                    #[cfg(feature = "decimal")]
+                    Conversion::BigDecimal => "invalid value for BigDecimal()",
+                },
+            )),
+        }
+    }
+}
+
+/// Reads a string the way Ruby's `Integer()` does.
+fn ruby_integer(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", text.strip_prefix('+').unwrap_or(text)),
+    };
+    let prefix = unsigned.get(..2).map(str::to_ascii_lowercase);
+    let (radix, digits) = match prefix.as_deref() {
+        Some("0x") => (16, &unsigned[2..]),
+        Some("0b") => (2, &unsigned[2..]),
+        Some("0o") => (8, &unsigned[2..]),
+        Some("0d") => (10, &unsigned[2..]),
+        _ if unsigned.len() > 1 && unsigned.starts_with('0') => (8, &unsigned[1..]),
+        _ => (10, unsigned),
+    };
+    let digits = without_underscores(digits)?;
+    i64::from_str_radix(&format!("{}{}", sign, digits), radix).ok()
+}
+
+/// Reads a string the way Ruby's `Float()` does.
+fn ruby_float(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let unsigned = text.trim_start_matches(['+', '-']);
+    if unsigned.len() > 2 && unsigned[..2].eq_ignore_ascii_case("0x") {
+        return ruby_integer(text).map(|integer| integer as f64);
+    }
+    let text = without_underscores(text.strip_prefix('+').unwrap_or(text))?;
+    match coerce_numeric(&text, true)? {
+        Value::Integer(integer) => Some(integer as f64),
+        Value::Float(float) => Some(float),
+        _ => None,
+    }
+}
+
+/// Reads a string the way Ruby's `BigDecimal()` does, exactly. Infinities
+/// and NaN, which a decimal can't hold, give `None`.
+#[cfg(feature = "decimal")]
+fn ruby_big_decimal(text: &str) -> Option<Decimal> {
+    let text = text.trim();
+    match recognize_number::<()>(text) {
+        Ok(("", _)) => parse_decimal(&without_separators(text), false).ok(),
+        _ => None,
+    }
+}
+
+/// Removes underscores that stand between two digits, as in `1_000`, or
+/// returns `None` if any other underscore is found.
+fn without_underscores(text: &str) -> Option<Cow<'_, str>> {
+    if !text.contains('_') {
+        return Some(Cow::Borrowed(text));
+    }
+    let bytes = text.as_bytes();
+    for (index, byte) in bytes.iter().enumerate() {
+        let between_digits = index > 0
+            && bytes[index - 1].is_ascii_alphanumeric()
+            && bytes.get(index + 1).is_some_and(u8::is_ascii_alphanumeric);
+        if *byte == b'_' && !between_digits {
+            return None;
+        }
+    }
+    Some(Cow::Owned(text.replace('_', "")))
+}
+
+/// Parses the key of a `key => value` entry: a string, a symbol, a number,
+/// or with [`bareword_arrow_keys`](ParseOptions::bareword_arrow_keys), a
+/// bareword. `nil`, `true` and `false` keys are stored as
+/// [`Value::NIL_KEY`], [`Value::TRUE_KEY`] and [`Value::FALSE_KEY`], unless
+/// [`key_style`](ParseOptions::key_style) asks for strings or symbols.
+fn parse_key_arrow_hash<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        if let Ok((rest, (name, key))) = parse_literal_key::<E>(input) {
+            return match state.options.key_style {
+                KeyStyle::Preserve => Ok((rest, key.to_string())),
+                _ => Ok((rest, state.styled_key(input, name.to_string(), false)?)),
+            };
+        }
+        let (rest, (name, marked)) = alt((
+            map(parse_string(state), |key| (key, false)),
+            map(
+                preceded(
+                    char(':'),
+                    alt((parse_string(state), map(parse_symbol_name, String::from))),
+                ),
+                |name| (name, true),
+            ),
+            map(parse_numeric_key, |key| (key, false)),
+            |input| match state.options.bareword_arrow_keys {
+                true => map(parse_symbol_key, |key: &str| (key.to_string(), false))(input),
+                false => {
+                    // A malformed arrow is still named after a bareword, as it
+                    // is after any other key.
+                    if let Ok((rest, _)) = parse_symbol_key::<_, E>(input) {
+                        let (rest, _) = state.sp()(rest)?;
+                        if rest.starts_with('=') {
+                            parse_arrow_separator::<E>(rest)?;
+                        }
+                    }
+                    Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag)))
+                }
+            },
+        ))(input)?;
+        Ok((rest, state.styled_key(input, name, marked)?))
+    }
+}
+
+/// Parses a `nil`, `true` or `false` key, returning how it is written and the
+/// key it is stored under.
+fn parse_literal_key<'a, E: ParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, (&'a str, &'static str), E> {
+    alt((
+        map(keyword("nil"), |name| (name, Value::NIL_KEY)),
+        map(keyword("true"), |name| (name, Value::TRUE_KEY)),
+        map(keyword("false"), |name| (name, Value::FALSE_KEY)),
+    ))(input)
+}
+
+/// Parses the floats that have no digits, as [`Value::to_ruby`] writes them,
+/// `Float::NAN`, `Float::INFINITY` and `-Float::INFINITY`, or as `inspect`
+/// does, `NaN`, `Infinity` and `-Infinity`. These are floats rather than
+/// constants wherever they are nested.
+fn parse_non_finite(input: &str) -> IResult<&str, f64, ()> {
+    let (rest, sign) = opt(alt((char('+'), char('-'))))(input)?;
+    let (rest, _) = opt(tag("Float::"))(rest)?;
+    let (rest, float) = alt((
+        value(f64::NAN, alt((keyword("NAN"), keyword("NaN")))),
+        value(
+            f64::INFINITY,
+            alt((keyword("INFINITY"), keyword("Infinity"))),
+        ),
+    ))(rest)?;
+    // `Infinity::Rank` is a constant.
+    not(tag("::"))(rest)?;
+    match sign {
+        Some('-') => Ok((rest, -float)),
+        _ => Ok((rest, float)),
+    }
+}
+
+/// Parses an integer or float key with the same grammar as number values.
+/// The key is stored in canonical form, so that `+1` and `1` are the same key
+/// and `1.0` and `1` are different ones.
+fn parse_numeric_key<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, String, E> {
+    match parse_prefixed_integer(input) {
+        Ok((rest, integer)) => return Ok((rest, integer.to_string())),
+        Err(nom::Err::Error(_)) => {}
+        Err(err) => return Err(err),
+    }
+    let (rest, text) = recognize_number(input)?;
+    let text = &*without_separators(text);
+    let key = if text.contains(['.', 'e', 'E']) {
+        text.parse::<f64>().map(|float| format!("{:?}", float)).ok()
+    } else {
+        text.parse::<i64>().map(|integer| integer.to_string()).ok()
+    };
+    Ok((rest, key.unwrap_or_else(|| text.to_string())))
+}
+
+/// Parses the key of a `key: value` entry, bare as in `a:` or quoted as in
+/// `"a":`. The `:` isn't part of the key, so in `{"a": :sym}` the space
+/// before `:sym` keeps it a symbol value, while `{"a"::sym}` fails at the
+/// separator.
+fn parse_key_colon_hash<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, String, E> + 's {
+    move |input| {
+        let extra = &state.options.extra_key_chars;
+        let bare_key = recognize(pair(
+            parse_symbol_key,
+            many0_count(alt((
+                parse_symbol_key,
+                recognize(satisfy(|c| extra.contains(&c))),
+            ))),
+        ));
+        let (rest, name) = alt((
+            parse_string(state),
+            map(alt((bare_key, digit1)), String::from),
+        ))(input)?;
+        Ok((rest, state.styled_key(input, name, false)?))
+    }
+}
+
+/// Parses zero or more comma separated elements, allowing a comma after the
+/// last one as Ruby does.
+fn comma_separated<'a, O, E: HashParseError<&'a str>>(
+    state: &State,
+    mut element: impl Parser<&'a str, O, E>,
+    close: char,
+    input: &'a str,
+) -> IResult<&'a str, Vec<O>, E> {
+    if !state.recover.get() {
+        return map(
+            opt(terminated(
+                separated_list1(preceded(state.sp(), char(',')), element),
+                opt(preceded(state.sp(), char(','))),
+            )),
+            Option::unwrap_or_default,
+        )(input);
+    }
+
+    // An element that fails, or that is followed by something other than a
+    // comma or `close`, is skipped up to the next comma or `close`.
+    let mut elements = Vec::new();
+    let mut rest = input;
+    loop {
+        let (start, _) = state.sp()(rest)?;
+        if start.starts_with(close) {
+            return Ok((start, elements));
+        }
+        let recorded = state.diagnostics.borrow().len();
+        let (at, error) = match element.parse(start) {
+            Ok((after, value)) => {
+                let (next, _) = state.sp()(after)?;
+                if next.starts_with(',') || next.starts_with(close) {
+                    elements.push(value);
+                    (next, None)
+                } else {
+                    let message = format!("expected ',' or '{}'", close);
+                    let error = crate::ParseError::Syntax { offset: 0, message };
+                    (next, Some(error))
+                }
+            }
+            Err(nom::Err::Error(_)) if start.starts_with(',') => {
+                let message = "expected a value".to_string();
+                (
+                    start,
+                    Some(crate::ParseError::Syntax { offset: 0, message }),
+                )
+            }
+            Err(nom::Err::Error(_)) if value_len(start, close) == 0 => {
+                return Ok((start, elements))
+            }
+            Err(nom::Err::Error(_)) | Err(nom::Err::Failure(_)) if value_len(start, close) > 0 => {
+                (start, Some(state.describe(start, close)))
+            }
+            Err(err) => return Err(err),
+        };
+
+        let next = match error {
+            Some(error) => {
+                // Whatever was found inside the skipped input goes with it.
+                state.diagnostics.borrow_mut().truncate(recorded);
+                let end = &start[value_len(start, close)..];
+                state.record(at, end, error);
+                state.sp()(end)?.0
+            }
+            None => at,
+        };
+        match next.strip_prefix(',') {
+            Some(after) => rest = after,
+            None => return Ok((next, elements)),
+        }
+    }
+}
+
+/// Parses a comma separated list of values between `open` and `close`. As
+/// in Ruby, `key: value` and `key => value` entries at the end of the list
+/// are gathered into one hash, the last element, so `[1, a: 2]` is
+/// `[1, {"a" => 2}]`.
+fn parse_list<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+    open: char,
+    close: char,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Value>, E> + 's {
+    move |input| {
+        preceded(char(open), |input| {
+            let _depth = state.descend(input)?;
+            // Entries are parsed at the index of the hash they end up in,
+            // after every value.
+            let count = Cell::new(0);
+            let entry_count = Cell::new(0);
+            let element = |input| {
+                let (rest, (start, element)) =
+                    state.nested(&[count.get()], parse_list_element(state), input)?;
+                match &element {
+                    ListElement::Value(_) => {
+                        state.record_span(&[count.get()], start, rest);
+                        count.set(count.get() + 1);
+                    }
+                    ListElement::Values(values) => count.set(count.get() + values.len()),
+                    ListElement::Entry(_) => entry_count.set(entry_count.get() + 1),
+                    ListElement::Entries(entries) => {
+                        entry_count.set(entry_count.get() + entries.len())
+                    }
+                }
+                // The trailing hash is one more element.
+                let len = count.get() + usize::from(entry_count.get() > 0);
+                let options = state.options;
+                state.check_len(start, len, options.max_array_len, None, ARRAY_TOO_LONG)?;
+                state.check_len(
+                    start,
+                    entry_count.get(),
+                    options.max_object_keys,
+                    Some(count.get()),
+                    HASH_TOO_LONG,
+                )?;
+                Ok((rest, (start, element)))
+            };
+            let (rest, elements) = cut(terminated(
+                |input| comma_separated(state, element, close, input),
+                preceded(state.sp(), char(close)),
+            ))(input)?;
+
+            let mut values = Vec::with_capacity(elements.len());
+            let mut entries = Vec::new();
+            for (start, element) in elements {
+                match element {
+                    ListElement::Value(value) if entries.is_empty() => values.push(value),
+                    ListElement::Values(more) if entries.is_empty() => values.extend(more),
+                    ListElement::Value(_) | ListElement::Values(_) => {
+                        return Err(failure(start, "expected a hash entry after hash entries"))
+                    }
+                    ListElement::Entry(entry) => entries.push(entry),
+                    ListElement::Entries(more) => entries.extend(more),
+                }
+            }
+            if !entries.is_empty() {
+                values.push(Value::Object(state.build_object(entries)?));
+            }
+            Ok((rest, values))
+        })(input)
+    }
+}
+
+/// An element of a list, which may be an entry of a trailing hash, or a
+/// splat of several values or entries.
+enum ListElement<'a> {
+    Value(Value),
+    Values(Vec<Value>),
+    Entry((&'a str, Vec<String>, Value)),
+    Entries(Vec<(&'a str, Vec<String>, Value)>),
+}
+
+/// Parses an element of a list, returning where it starts. An element is a
+/// hash entry if `=>` follows its value, `:` follows a string, as in
+/// `"a b": 1`, or it only parses as a `name:` label. As in Ruby, `*[1, 2]`
+/// splats the array's elements into the list, and `**{a: 1}` the hash's
+/// entries into the trailing hash, as for the arguments of a call.
+fn parse_list_element<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (&'a str, ListElement<'a>), E> + 's {
+    move |input| {
+        let (start, _) = state.sp()(input)?;
+        if let Some(hash) = start.strip_prefix("**") {
+            if !hash.starts_with('{') {
+                return Err(failure(hash, "expected a hash after '**'"));
+            }
+            let _depth = state.descend(hash)?;
+            let (rest, entries) = preceded(
+                char('{'),
+                cut(terminated(
+                    |input| comma_separated(state, parse_tracked_entry(state), '}', input),
+                    preceded(state.sp(), char('}')),
+                )),
+            )(hash)?;
+            return Ok((rest, (start, ListElement::Entries(entries))));
+        }
+        if let Some(array) = start.strip_prefix('*') {
+            if !array.starts_with('[') {
+                return Err(failure(array, "expected an array after '*'"));
+            }
+            let (rest, values) = parse_list(state, '[', ']')(array)?;
+            return Ok((rest, (start, ListElement::Values(values))));
+        }
+        let entry = match parse_value(state)(start) {
+            Ok((rest, value)) => {
+                let (after, _) = state.sp()(rest)?;
+                let label = matches!(value, Value::Bytes(_))
+                    && rest.starts_with(':')
+                    && !rest.starts_with("::");
+                if !after.starts_with("=>") && !label {
+                    return Ok((rest, (start, ListElement::Value(value))));
+                }
+                parse_tracked_entry(state)(start)
+            }
+            Err(nom::Err::Error(err)) => match parse_tracked_entry(state)(start) {
+                Err(nom::Err::Error(_)) => Err(nom::Err::Error(err)),
+                entry => entry,
+            },
+            Err(err) => Err(err),
+        };
+        let (rest, entry) = entry?;
+        Ok((rest, (start, ListElement::Entry(entry))))
+    }
+}
+
+/// Parses the `{...}` or `[...]` that Ruby writes for a hash or array that
+/// contains itself, when enabled.
+fn parse_recursion_marker<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if !state.options.recursion_markers {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag)));
+        }
+        value(Value::Recursive, alt((tag("{...}"), tag("[...]"))))(input)
+    }
+}
+
+fn parse_array<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| context("array", map(parse_list(state, '[', ']'), Value::Array))(input)
+}
+
+/// Parses a parenthesized list such as `(1, 2, 3)` as an array when enabled.
+fn parse_tuple<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if !state.options.parenthesized_arrays {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Char)));
+        }
+        context("tuple", map(parse_list(state, '(', ')'), Value::Array))(input)
+    }
+}
+
+fn parse_key_value_arrow<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (String, Value), E> + 's {
+    move |input| {
+        let (rest, (spelling, key)) = terminated(
+            preceded(state.sp(), consumed(parse_key_arrow_hash(state))),
+            cut(preceded(state.sp(), parse_arrow_separator)),
+        )(input)?;
+        let (after, value) = state.nested(&[&key], parse_value(state), rest)?;
+        state.record_key(&[&key], spelling);
+        state.record_span(&[&key], rest, after);
+        Ok((after, (key, value)))
+    }
+}
+
+/// Parses a hash into an object. The entries are collected first and the
+/// map is bulk-built from them, which sorts them once, so a hash of `n`
+/// entries takes `O(n log n)` time whatever order its keys are in. The
+/// tracked path for `split_dotted_keys` and `on_duplicate_key` inserts each
+/// entry in turn, which has the same bound.
+fn parse_hash<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        context(
+            "map",
+            map(
+                preceded(char('{'), |input| {
+                    let _depth = state.descend(input)?;
+                    let count = Cell::new(0);
+                    let counted = |start| {
+                        count.set(count.get() + 1);
+                        let limit = state.options.max_object_keys;
+                        state.check_len(start, count.get(), limit, None, HASH_TOO_LONG)
+                    };
+                    let result = cut(terminated(
+                        |input| {
+                            if state.options.split_dotted_keys
+                                || state.options.on_duplicate_key != DuplicateKeys::Last
+                                || state.recover.get()
+                            {
+                                let entry = |input| {
+                                    let (rest, entry) = parse_tracked_entry(state)(input)?;
+                                    counted(entry.0)?;
+                                    Ok((rest, entry))
+                                };
+                                let (rest, entries) = comma_separated(state, entry, '}', input)?;
+                                Ok((rest, state.build_object(entries)?))
+                            } else {
+                                let entry = |input| {
+                                    let (start, _) = state.sp()(input)?;
+                                    let (rest, entry) = parse_key_value(state)(start)?;
+                                    counted(start)?;
+                                    Ok((rest, entry))
+                                };
+                                let (rest, entries) = comma_separated(state, entry, '}', input)?;
+                                Ok((rest, entries.into_iter().collect()))
+                            }
+                        },
+                        preceded(state.sp(), char('}')),
+                    ))(input);
+                    result
+                }),
+                Value::Object,
+            ),
+        )(input)
+    }
+}
+
+/// Parses a hash entry for [`State::build_object`], returning how its key was
+/// written and the path it splits into. With `split_dotted_keys`, keys
+/// written as symbols, such as `:"a.b" =>` or `"a.b":`, are split, as are
+/// all keys when every key is made a symbol anyway.
+fn parse_tracked_entry<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (&'a str, Vec<String>, Value), E> + 's {
+    move |input| {
+        let (start, _) = state.sp()(input)?;
+        let colon_key = terminated(
+            consumed(parse_key_colon_hash(state)),
+            preceded(state.sp(), parse_colon_separator),
+        )(start);
+        let (rest, (spelling, key), symbol) = match colon_key {
+            Ok((rest, key)) => (rest, key, true),
+            Err(nom::Err::Error(_)) => {
+                let (rest, key) = terminated(
+                    consumed(parse_key_arrow_hash(state)),
+                    cut(preceded(state.sp(), parse_arrow_separator)),
+                )(start)?;
+                (rest, key, start.starts_with(':'))
+            }
+            Err(err) => return Err(err),
+        };
+        let split = state.options.split_dotted_keys
+            && (symbol || state.options.key_style == KeyStyle::AllSymbols);
+        let path = if split { split_key(key) } else { vec![key] };
+        let (after, value) = if symbol {
+            state.nested(&path, cut(parse_value(state)), rest)?
+        } else {
+            state.nested(&path, parse_value(state), rest)?
+        };
+        state.record_key(&path, spelling);
+        state.record_span(&path, rest, after);
+        Ok((after, (spelling, path, value)))
+    }
+}
+
+/// Splits a key such as `":a.b"` into `[":a", ":b"]`, keeping the colon on
+/// each segment. Keys with an empty segment, like `":a..b"`, are kept whole.
+fn split_key(key: String) -> Vec<String> {
+    let (prefix, name) = match key.strip_prefix(':') {
+        Some(name) => (":", name),
+        None => ("", key.as_str()),
+    };
+    if !name.contains('.') || name.split('.').any(str::is_empty) {
+        return vec![key];
+    }
+    name.split('.')
+        .map(|segment| format!("{}{}", prefix, segment))
+        .collect()
+}
+
+/// Merges `new` into `existing` if both are objects, key by key at every
+/// depth, and otherwise replaces `existing`.
+pub(crate) fn merge_values(existing: &mut Value, new: Value) {
+    match (existing, new) {
+        (Value::Object(existing), Value::Object(new)) => {
+            for (key, value) in new {
+                match existing.entry(key) {
+                    btree_map::Entry::Occupied(entry) => merge_values(entry.into_mut(), value),
+                    btree_map::Entry::Vacant(entry) => {
+                        entry.insert(value);
+                    }
+                }
+            }
+        }
+        (existing, new) => *existing = new,
+    }
+}
+
+/// Parses the `#<BigDecimal:7fd5b30a4b50,'0.1234E1',18(18)>` form that
+/// `BigDecimal#inspect` wrote before Ruby 2.4, reading its quoted digits as an
+/// exact decimal.
+#[cfg(feature = "decimal")]
+fn parse_big_decimal_inspect<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Value, E> {
+    let (rest, digits) = preceded(
+        tuple((
+            tag("#<BigDecimal:"),
+            take_while1(|c: char| c.is_ascii_hexdigit()),
+            tag(",'"),
+        )),
+        cut(terminated(
+            take_while(|c| c != '\''),
+            tuple((char('\''), take_while(|c| c != '>'), char('>'))),
+        )),
+    )(input)?;
+    match ruby_big_decimal(digits) {
+        Some(decimal) => Ok((rest, Value::Decimal(decimal))),
+        None => Err(failure(digits, "invalid BigDecimal")),
+    }
+}
+
+/// Parses inspected objects such as `#<User id: 1, name: "x">`, and the
+/// positional `#<struct Point x=1, y=2>` form of structs and `Data` values,
+/// where the class name is optional and fields are separated by `=`. Named
+/// objects whose fields are written with `=`, such as
+/// `#<OpenStruct x=1, y="z">`, are structs too. With the `decimal` feature,
+/// an inspected `BigDecimal` is a `Value::Decimal`.
+///
+/// Each field's value is parsed as a whole value before the closing `>` is
+/// looked for, so fields may hold other inspected objects, as in
+/// `#<A b: #<B c: 1>>`, and strings with `>` in them.
+fn parse_inspected<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        #[cfg(feature = "decimal")]
+        if input.starts_with("#<BigDecimal:") {
+            return parse_big_decimal_inspect(input);
+        }
+        context(
+            "object",
+            preceded(tag("#<"), |input| {
+                let _depth = state.descend(input)?;
+                cut(|input| {
+                    let (input, positional) = opt(terminated(
+                        alt((keyword("struct"), keyword("data"))),
+                        state.sp(),
+                    ))(input)?;
+                    let (input, name) = match positional {
+                        Some(_) => opt(terminated(constant_path, state.sp()))(input)?,
+                        None => map(terminated(constant_path, state.sp()), Some)(input)?,
+                    };
+                    // Other objects can also write their fields with `=`, as
+                    // `#<OpenStruct x=1>` does, which the first field shows.
+                    let keyed_with_equals = preceded(
+                        state.sp(),
+                        terminated(parse_symbol_key::<_, ()>, char('=')),
+                    )(input)
+                    .is_ok();
+                    let separator = match positional.is_some() || keyed_with_equals {
+                        true => '=',
+                        false => ':',
+                    };
+                    let (input, fields) = terminated(
+                        separated_list0(preceded(state.sp(), char(',')), |input| {
+                            let (rest, key) = terminated(
+                                preceded(state.sp(), parse_symbol_key),
+                                char(separator),
+                            )(input)?;
+                            let (rest, value) = state.nested(&[key], parse_value(state), rest)?;
+                            Ok((rest, (key, value)))
+                        }),
+                        preceded(state.sp(), char('>')),
+                    )(input)?;
+
+                    let value = Value::Struct {
+                        name: name.unwrap_or_default().to_string(),
+                        fields: fields
+                            .into_iter()
+                            .map(|(key, value): (&str, Value)| (key.to_string(), value))
+                            .collect(),
+                    };
+                    Ok((input, value))
+                })(input)
+            }),
+        )(input)
+    }
+}
+
+/// Parses an Elixir `%{...}` map when enabled.
+fn parse_elixir_map<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if !state.options.elixir_maps {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag)));
+        }
+        preceded(char('%'), parse_hash(state))(input)
+    }
+}
+
+/// Captures an unrecognized bareword as a string when the options ask for it.
+/// Pseudo-constants such as `__FILE__` are captured too, but `__END__` never
+/// is, since it starts the data section.
+fn parse_unknown<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        if state.options.on_unknown != UnknownTokens::CaptureAsString {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Alt)));
+        }
+        let tuples = state.options.parenthesized_arrays;
+        let (rest, bareword) = take_while1(move |c: char| {
+            !(c.is_whitespace() || ",[]{}\"'".contains(c) || tuples && c == ')')
+        })(input)?;
+        if bareword == "__END__" {
+            return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Alt)));
+        }
+        state.check_string_len(input, bareword)?;
+        Ok((rest, Value::Bytes(bareword.to_string())))
+    }
+}
+
+/// Skips a chain of `.freeze`, `.dup` and `.to_s` calls after a string, array
+/// or hash literal, when the options ask for it. Returns whether the chain
+/// leaves the literal frozen: `.freeze` freezes it and `.dup` makes an
+/// unfrozen copy.
+fn parse_trailing_calls<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, bool, E> + 's {
+    move |input| {
+        if !state.options.ignore_trailing_calls {
+            return Ok((input, false));
+        }
+        let (rest, calls) = many0(preceded(
+            char('.'),
+            alt((keyword("freeze"), keyword("dup"), keyword("to_s"))),
+        ))(input)?;
+        let frozen = calls
+            .iter()
+            .rev()
+            .find(|call| **call != "to_s")
+            .is_some_and(|call| *call == "freeze");
+        Ok((rest, frozen))
+    }
+}
+
+/// Parses the literals that method calls can follow, such as `"x".freeze`.
+fn parse_literal<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        map(
+            pair(
+                alt((
+                    parse_recursion_marker(state),
+                    parse_hash(state),
+                    parse_elixir_map(state),
+                    parse_array(state),
+                    parse_tuple(state),
+                    map(parse_bytes(state), |bytes| state.string_value(bytes)),
+                    map(parse_heredoc_sum(state), |bytes| state.string_value(bytes)),
+                    parse_percent_array(state),
+                )),
+                parse_trailing_calls(state),
+            ),
+            |(value, frozen)| {
+                if let (true, Some(tracked)) = (frozen, &state.tracked) {
+                    let path = state.path.borrow().clone();
+                    tracked
+                        .borrow_mut()
+                        .frozen
+                        .insert(input.as_ptr() as usize, path);
+                }
+                match value {
+                    Value::Bytes(string) if frozen && state.options.record_frozen => {
+                        Value::FrozenBytes(string)
+                    }
+                    value => value,
+                }
+            },
+        )(input)
+    }
+}
+
+/// Parses any value. The alternatives are ordered so that each token means
+/// one thing wherever it is nested: `nil`, `true` and `false` are keywords
+/// only as whole words, so `:nil` is a symbol and `nilly` isn't `nil`, and
+/// numbers, including `Float::NAN` and `-Infinity`, come before constants, so
+/// that only other capitalized paths such as `Foo::Bar` are constants.
+fn parse_value<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Value, E> + 's {
+    move |input| {
+        let (start, _) = state.sp()(input)?;
+        // Most values can be told apart by their first character, which
+        // saves building an error for every alternative that doesn't match.
+        // Anything else, including input that the likely parser rejects,
+        // goes through every alternative so that errors are the same.
+        let likely = match start.as_bytes().first() {
+            Some(b'{' | b'[' | b'(' | b'"' | b'\'' | b'<' | b'%') => {
+                Some(parse_literal(state)(start))
+            }
+            Some(b'#') => Some(parse_inspected(state)(start)),
+            Some(b':') => Some(parse_symbol(state)(start)),
+            Some(b'?') => Some(parse_char_literal(state)(start)),
+            #[cfg(feature = "chrono")]
+            Some(b'0'..=b'9') if state.options.strict_timestamps => {
+                Some(alt((parse_timestamp(state), parse_number(state)))(start))
+            }
+            Some(b'0'..=b'9' | b'+' | b'-') => Some(parse_number(state)(start)),
+            _ => None,
+        };
+        let (rest, value) = match likely {
+            Some(Err(nom::Err::Error(_))) | None => alt((
+                parse_nil,
+                parse_literal(state),
+                parse_inspected(state),
+                parse_symbol(state),
+                parse_char_literal(state),
+                timestamp_or_number(state),
+                map(parse_boolean(state), Value::Boolean),
+                parse_conversion_call(state),
+                parse_constant,
+                parse_unknown(state),
+            ))(start)?,
+            Some(result) => result?,
+        };
+        state.check_type(start, &value)?;
+        Ok((rest, value))
+    }
+}
+
+/// Parses a `key: value` entry. The value may start on a later line, as in
+/// `name:\n  "value"`, since it skips whitespace and comments before it like
+/// any other value.
+fn parse_key_value_colon<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (String, Value), E> + 's {
+    move |input| {
+        let (rest, (spelling, key)) = terminated(
+            preceded(state.sp(), consumed(parse_key_colon_hash(state))),
+            preceded(state.sp(), parse_colon_separator),
+        )(input)?;
+        let (after, value) = state.nested(&[&key], cut(parse_value(state)), rest)?;
+        state.record_key(&[&key], spelling);
+        state.record_span(&[&key], rest, after);
+        Ok((after, (key, value)))
+    }
+}
+
+/// Parses the `:` after a key. It may be followed directly by a value, as in
+/// `a:1`, but not by another `:` or an `=`, so that `a::b` and `a:=1` are
+/// reported as malformed separators rather than failing somewhere in the value.
+fn parse_colon_separator<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    let (rest, separator) = tag(":")(input)?;
+    if rest.starts_with(':') {
+        return Err(failure(rest, "expected a value after ':', found ':'"));
+    }
+    if rest.starts_with('=') {
+        return Err(failure(rest, "expected a value after ':', found '='"));
+    }
+    Ok((rest, separator))
+}
+
+/// Parses the `=>` after a key. A `=` that isn't directly followed by `>`, as
+/// in a corrupted `= >`, is reported as a malformed separator. So is a `:`,
+/// as in `:a: 1`, which mixes both key styles and isn't valid Ruby either.
+fn parse_arrow_separator<'a, E: HashParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    if input.starts_with(':') {
+        return Err(failure(input, "expected '=>', found ':'"));
+    }
+    if let Some(after) = input.strip_prefix('=') {
+        if !after.starts_with('>') {
+            let (after_space, _) = sp::<E>(after)?;
+            if after_space.len() < after.len() && after_space.starts_with('>') {
+                return Err(failure(input, "expected '=>', found '= >'"));
+            }
+            return Err(failure(input, "expected '=>', found '='"));
+        }
+    }
+    tag("=>")(input)
+}
+
+fn parse_key_value<'a, 's, E: HashParseError<&'a str>>(
+    state: &'s State<'s>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (String, Value), E> + 's {
+    move |input| {
+        // `:name =>` is the most common key in inspected hashes, and no key
+        // written as `name:` starts with a colon.
+        let (start, _) = state.sp()(input)?;
+        if start.starts_with(':') {
+            return parse_key_value_arrow(state)(start);
+        }
+        alt((parse_key_value_colon(state), parse_key_value_arrow(state)))(start)
+    }
+}
+
+/// Parses an object key and its `:` or `=>` separator at `rest`, a suffix of
+/// `input`, returning the key and the input after the separator.
+pub(crate) fn parse_entry_key<'a>(
+    input: &'a str,
+    rest: &'a str,
+    options: &ParseOptions,
+) -> Result<(String, &'a str), crate::ParseError> {
+    let state = State::new(options);
+    let result = alt((
+        terminated(
+            preceded(
+                state.sp(),
+                parse_key_colon_hash::<VerboseError<&str>>(&state),
+            ),
+            preceded(state.sp(), parse_colon_separator),
+        ),
+        terminated(
+            preceded(state.sp(), parse_key_arrow_hash(&state)),
+            cut(preceded(state.sp(), parse_arrow_separator)),
+        ),
+    ))(rest);
+    match result {
+        Ok((rest, key)) => Ok((key, rest)),
+        Err(nom::Err::Failure(err)) => Err(crate::ParseError::from_verbose(input, err)),
+        Err(_) => Err(crate::ParseError::Syntax {
+            offset: input.offset(rest),
+            message: "expected a key in map".to_string(),
+        }),
+    }
+}
+
+/// Reads the token at `rest`, a suffix of `input`, for
+/// [`tokenize`](crate::tokenize), returning it with the input after it, or
+/// `None` at the end of input or an `__END__` line. `heredocs` carries the
+/// heredocs opened so far from one token to the next.
+pub(crate) fn lex_token<'a>(
+    input: &'a str,
+    rest: &'a str,
+    heredocs: &mut Vec<Heredoc>,
+    after_string: bool,
+) -> Result<Option<(Token, &'a str)>, crate::ParseError> {
+    let options = ParseOptions::default();
+    let state = State::new(&options);
+    state.heredocs.replace(std::mem::take(heredocs));
+    let result = lex::<VerboseError<&str>>(&state, input, rest, after_string);
+    *heredocs = state.heredocs.into_inner();
+
+    match result {
+        Ok((_, None)) => Ok(None),
+        Ok((after, Some((start, kind)))) => {
+            let span = input.offset(start)..input.offset(after);
+            Ok(Some((Token { kind, span }, after)))
+        }
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            Err(crate::ParseError::from_verbose(input, err))
+        }
+        Err(nom::Err::Incomplete(_)) => Err(crate::ParseError::Syntax {
+            offset: input.len(),
+            message: "unexpected end of input".to_string(),
+        }),
+    }
+}
+
+/// Lexes one token after any whitespace, returning where it starts.
+fn lex<'a, E: HashParseError<&'a str>>(
+    state: &State,
+    input: &'a str,
+    rest: &'a str,
+    after_string: bool,
+) -> IResult<&'a str, Option<(&'a str, TokenKind)>, E> {
+    let (start, _) = state.sp()(rest)?;
+    if start.is_empty() || data_section(input, start).is_some() {
+        return Ok((start, None));
+    }
+    // A `:` right after a string separates a quoted key from its value.
+    if after_string && start.len() == rest.len() && start.starts_with(':') {
+        if let Ok((after, _)) = parse_colon_separator::<E>(start) {
+            return Ok((after, Some((start, TokenKind::Colon))));
+        }
+    }
+
+    let percent_array = |input| {
+        let (rest, array) = percent_array(state, input)?;
+        let words = match array {
+            Value::Array(elements) => elements
+                .into_iter()
+                .filter_map(|element| match element {
+                    Value::Bytes(word) | Value::Symbol(word) => Some(word),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let kind = if input.starts_with("%w") {
+            TokenKind::WordArray(words)
+        } else {
+            TokenKind::SymbolArray(words)
+        };
+        Ok((rest, kind))
+    };
+    let number = |input| {
+        let (rest, number) = parse_number(state)(input)?;
+        let kind = match number {
+            Value::Float(float) => TokenKind::Float(float),
+            Value::Integer(integer) => TokenKind::Integer(integer),
+            _ => unreachable!("numbers are integers or floats with the default options"),
+        };
+        Ok((rest, kind))
+    };
+    let punctuation = alt((
+        value(TokenKind::OpenBrace, char('{')),
+        value(TokenKind::CloseBrace, char('}')),
+        value(TokenKind::OpenBracket, char('[')),
+        value(TokenKind::CloseBracket, char(']')),
+        value(TokenKind::OpenParen, char('(')),
+        value(TokenKind::CloseParen, char(')')),
+        value(TokenKind::CloseInspect, char('>')),
+        value(TokenKind::Comma, char(',')),
+        value(TokenKind::Colon, char(':')),
+        value(TokenKind::Equals, char('=')),
+        value(TokenKind::Dot, char('.')),
+        value(TokenKind::Percent, char('%')),
+    ));
+
+    let (after, kind) = alt((
+        value(TokenKind::Arrow, tag("=>")),
+        value(TokenKind::OpenInspect, tag("#<")),
+        percent_array,
+        map(parse_heredoc(state), TokenKind::String),
+        map(parse_string(state), TokenKind::String),
+        map(parse_char_literal(state), |value| match value {
+            Value::Bytes(string) => TokenKind::String(string),
+            _ => unreachable!("character literals are strings with the default options"),
+        }),
+        map(parse_symbol_text(state), TokenKind::Symbol),
+        map(
+            terminated(parse_symbol_key, pair(char(':'), not(char(':')))),
+            |name: &str| TokenKind::Label(name.to_string()),
+        ),
+        number,
+        value(TokenKind::Nil, keyword("nil")),
+        map(parse_boolean(state), TokenKind::Boolean),
+        map(constant_path, |path: &str| {
+            TokenKind::Constant(path.to_string())
+        }),
+        map(parse_symbol_key, |word: &str| {
+            TokenKind::Identifier(word.to_string())
+        }),
+        punctuation,
+    ))(start)
+    .map_err(|err| match err {
+        nom::Err::Error(_) => failure(start, "unexpected input"),
+        err => err,
+    })?;
+    Ok((after, Some((start, kind))))
+}
+
+/// Parses one value and any whitespace after it, returning the value and the
+/// unconsumed input.
+fn parse_leading<'a>(input: &'a str, state: &State) -> Result<(Value, &'a str), crate::ParseError> {
+    let (rest, _) = state.sp::<()>()(input).unwrap_or((input, ""));
+    if rest.is_empty() {
+        return Err(crate::ParseError::Empty);
+    }
+
+    let result = terminated(parse_value::<VerboseError<&str>>(state), state.sp())(input);
+    match result {
+        Ok((rest, value)) => Ok((value, rest)),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => Err(state.error(input, err)),
+        Err(nom::Err::Incomplete(_)) => Err(crate::ParseError::Syntax {
+            offset: input.len(),
+            message: "unexpected end of input".to_string(),
+        }),
+    }
+}
+
+/// If `rest` starts with an `__END__` line, returns the data section that
+/// follows it.
+fn data_section<'a>(input: &'a str, rest: &'a str) -> Option<&'a str> {
+    let offset = input.offset(rest);
+    if offset > 0 && !input[..offset].ends_with('\n') {
+        return None;
+    }
+
+    let after = rest.strip_prefix("__END__")?;
+    match after.find('\n') {
+        Some(end) if after[..end].trim_end_matches('\r').is_empty() => Some(&after[end + 1..]),
+        None if after.is_empty() => Some(after),
+        _ => None,
+    }
+}
+
+fn trailing_input_error(input: &str, rest: &str) -> crate::ParseError {
+    crate::ParseError::Syntax {
+        offset: input.offset(rest),
+        message: "unexpected trailing input".to_string(),
+    }
+}
+
+/// One parse of a document in the indented grammar of
+/// [`indent_mode`](ParseOptions::indent_mode). Every method returns the
+/// input at the start of a line, or at the end of input.
+struct Indented<'s> {
+    state: &'s State<'s>,
+    /// What indentation is made of, once a line has been indented.
+    unit: Cell<Option<char>>,
+}
+
+impl<'s> Indented<'s> {
+    /// Skips blank and comment lines. Returns the content of the next line,
+    /// after its indentation, along with how far it is indented, or `None`
+    /// at the end of input.
+    fn next_line<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+    ) -> Result<(&'a str, Option<usize>), nom::Err<E>> {
+        let mut rest = input;
+        while !rest.is_empty() {
+            let line_end = rest.find('\n').map_or(rest.len(), |index| index + 1);
+            let content = rest[..line_end].trim_start_matches([' ', '\t']);
+            if content.trim().is_empty() || self.is_comment(content) {
+                rest = &rest[line_end..];
+                continue;
+            }
+            let indentation = &rest[..line_end - content.len()];
+            return Ok((&rest[indentation.len()..], Some(self.width(indentation)?)));
+        }
+        Ok((rest, None))
+    }
+
+    /// How far `indentation` indents its line, failing if it isn't made of
+    /// the same character as every indentation before it.
+    fn width<'a, E: HashParseError<&'a str>>(
+        &self,
+        indentation: &'a str,
+    ) -> Result<usize, nom::Err<E>> {
+        let first = match indentation.chars().next() {
+            Some(first) => first,
+            None => return Ok(0),
+        };
+        if indentation.chars().any(|c| c != first) {
+            return Err(failure(indentation, "indentation mixes tabs and spaces"));
+        }
+        match self.unit.get() {
+            None => self.unit.set(Some(first)),
+            Some(unit) if unit != first && first == '\t' => {
+                return Err(failure(indentation, "indented with tabs after spaces"))
+            }
+            Some(unit) if unit != first => {
+                return Err(failure(indentation, "indented with spaces after tabs"))
+            }
+            Some(_) => {}
+        }
+        Ok(indentation.len())
+    }
+
+    fn is_comment(&self, text: &str) -> bool {
+        !text.starts_with("#<")
+            && self
+                .state
+                .options
+                .line_comments
+                .iter()
+                .any(|prefix| !prefix.is_empty() && text.starts_with(prefix.as_str()))
+    }
+
+    /// Whether `text` is the end of its line, or only a comment before it.
+    fn is_line_end(&self, text: &str) -> bool {
+        text.is_empty()
+            || text.starts_with('\n')
+            || text.starts_with("\r\n")
+            || self.is_comment(text)
+    }
+
+    /// Skips the spaces and comment after a value to the start of the next
+    /// line, failing if anything else is left on the line.
+    fn end_of_line<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+    ) -> Result<&'a str, nom::Err<E>> {
+        let rest = input.trim_start_matches([' ', '\t']);
+        if !self.is_line_end(rest) {
+            return Err(failure(rest, "expected the end of the line"));
+        }
+        Ok(rest
+            .find('\n')
+            .map_or(&rest[rest.len()..], |end| &rest[end + 1..]))
+    }
+
+    /// Parses a `key:` at the start of a line, which the end of the line or
+    /// a space must follow, so that `a::B` isn't taken for one.
+    fn key<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+    ) -> IResult<&'a str, (&'a str, String), E> {
+        let (rest, (spelling, key)) = consumed(parse_key_colon_hash(self.state))(input)?;
+        match rest.strip_prefix(':') {
+            Some(after) if after.is_empty() || after.starts_with(char::is_whitespace) => {
+                Ok((after, (spelling, key)))
+            }
+            _ => Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag))),
+        }
+    }
+
+    /// Parses the value after a `key:` or `-` on the same line, or if there
+    /// is none, the lines nested under it.
+    fn item<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+        indent: usize,
+        in_hash: bool,
+    ) -> IResult<&'a str, Value, E> {
+        let content = input.trim_start_matches([' ', '\t']);
+        if !self.is_line_end(content) {
+            let (rest, value) = parse_value(self.state)(content)?;
+            return Ok((self.end_of_line(rest)?, value));
+        }
+
+        let after = self.end_of_line(content)?;
+        match self.next_line(after)? {
+            (line, Some(width)) if width > indent => self.block(line, width),
+            (line, Some(width)) if width == indent && in_hash && is_list_item(line) => {
+                self.list(line, width, true)
+            }
+            _ => Ok((after, Value::Null)),
+        }
+    }
+
+    /// Parses the lines indented by `indent`, from the content of the first,
+    /// as a list if they start with `-` and otherwise as a hash.
+    fn block<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+        indent: usize,
+    ) -> IResult<&'a str, Value, E> {
+        let _depth = self.state.descend(input)?;
+        if is_list_item(input) {
+            self.list(input, indent, false)
+        } else {
+            self.hash(input, indent)
+        }
+    }
+
+    /// Parses lines of `- value`. With `under_key`, the list is a hash's
+    /// value at the hash's own indentation, and ends at its next key.
+    fn list<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+        indent: usize,
+        under_key: bool,
+    ) -> IResult<&'a str, Value, E> {
+        let mut values = Vec::new();
+        let mut line = input;
+        loop {
+            let after_dash = &line[1..];
+            let content = after_dash.trim_start_matches(' ');
+            let (rest, value) = match self.key::<E>(content) {
+                Ok(_) if content.len() < after_dash.len() => {
+                    let _depth = self.state.descend(content)?;
+                    self.hash(content, indent + line.len() - content.len())?
+                }
+                Err(nom::Err::Failure(err)) => return Err(nom::Err::Failure(err)),
+                _ => self.item(after_dash, indent, false)?,
+            };
+            values.push(value);
+
+            match self.next_line(rest)? {
+                (next, Some(width)) if width == indent && is_list_item(next) => line = next,
+                (next, Some(width)) if width > indent => {
+                    return Err(failure(next, "unexpected indentation"))
+                }
+                (next, Some(width)) if width == indent && !under_key => {
+                    return Err(failure(next, "expected '-' in a list"))
+                }
+                _ => return Ok((rest, Value::Array(values))),
+            }
+        }
+    }
+
+    /// Parses lines of `key: value`.
+    fn hash<'a, E: HashParseError<&'a str>>(
+        &self,
+        input: &'a str,
+        indent: usize,
+    ) -> IResult<&'a str, Value, E> {
+        let mut entries = Vec::new();
+        let mut line = input;
+        loop {
+            let (after_key, (spelling, key)) = match self.key(line) {
+                Ok(parsed) => parsed,
+                Err(nom::Err::Error(_)) => return Err(failure(line, "expected a key")),
+                Err(err) => return Err(err),
+            };
+            let (rest, value) = self.item(after_key, indent, true)?;
+            entries.push((spelling, vec![key], value));
+
+            match self.next_line(rest)? {
+                (next, Some(width)) if width == indent && !is_list_item(next) => line = next,
+                (next, Some(width)) if width > indent => {
+                    return Err(failure(next, "unexpected indentation"))
+                }
+                (next, Some(width)) if width == indent => {
+                    return Err(failure(next, "expected a key, found '-'"))
+                }
+                _ => return Ok((rest, Value::Object(self.state.build_object(entries)?))),
+            }
+        }
+    }
+}
+
+/// Whether a line's content is an item of an indented list: a `-` followed
+/// by a space or the end of the line, unlike a negative number.
+fn is_list_item(content: &str) -> bool {
+    match content.strip_prefix('-') {
+        Some(after) => after.is_empty() || after.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+/// Parses `input` as an indented document, for
+/// [`indent_mode`](ParseOptions::indent_mode).
+fn parse_indented(input: &str, state: &State) -> Result<Value, crate::ParseError> {
+    let indented = Indented {
+        state,
+        unit: Cell::new(None),
+    };
+    let result = indented
+        .next_line::<VerboseError<&str>>(input)
+        .and_then(|(line, indent)| match indent {
+            Some(indent) => {
+                let (rest, value) = indented.block(line, indent)?;
+                match indented.next_line(rest)? {
+                    (line, Some(_)) => Err(failure(line, "unexpected indentation")),
+                    (_, None) => Ok(Some(value)),
+                }
+            }
+            None => Ok(None),
+        });
+    match result {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err(crate::ParseError::Empty),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => Err(state.error(input, err)),
+        Err(nom::Err::Incomplete(_)) => Err(crate::ParseError::Syntax {
+            offset: input.len(),
+            message: "unexpected end of input".to_string(),
+        }),
+    }
+}
+
+/// Parses one value that must be followed by the end of input or an
+/// `__END__` line.
+fn parse_complete(input: &str, state: &State) -> Result<Value, crate::ParseError> {
+    let value = if state.options.indent_mode {
+        parse_indented(input, state)?
+    } else {
+        let (value, rest) = parse_leading(input, state)?;
+        if !rest.is_empty() && data_section(input, rest).is_none() {
+            return Err(trailing_input_error(input, rest));
+        }
+        value
+    };
+
+    if state.options.require_object_root && !matches!(value, Value::Object(_)) {
+        let (start, _) = state.sp::<()>()(input).unwrap_or((input, ""));
+        return Err(crate::ParseError::Syntax {
+            offset: input.offset(start),
+            message: format!(
+                "expected a hash at the top level, found {}",
+                value.type_name()
+            ),
+        });
+    }
+    Ok(value)
+}
+
+/// Parses a single Ruby value, requiring that the whole input is consumed.
+/// Whitespace and comments around the value are ignored. Braces are always a
+/// hash, never a block, so `{}` is an empty `Value::Object` just as `[]` is
+/// an empty `Value::Array`.
+///
+/// Parsing stops at an `__END__` line, and anything after it is ignored. Use
+/// [`parse_with_data`] to get at that text.
+pub fn parse(input: &str) -> Result<Value, crate::ParseError> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// Like [`parse`], but accepts the extra syntax enabled in `options`.
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Value, crate::ParseError> {
+    parse_complete(input, &State::new(options))
+}
+
+/// Parses a single Ruby value that may be followed by an `__END__` line,
+/// returning the value along with the `DATA` section after that line.
+pub fn parse_with_data(input: &str) -> Result<(Value, Option<&str>), crate::ParseError> {
+    let (value, rest) = parse_leading(input, &State::new(&ParseOptions::default()))?;
+    if rest.is_empty() {
+        return Ok((value, None));
+    }
+
+    match data_section(input, rest) {
+        Some(data) => Ok((value, Some(data))),
+        None => Err(trailing_input_error(input, rest)),
+    }
+}
+
+/// Parses the value at the start of `input` and ignores whatever follows it,
+/// such as a timestamp or log suffix.
+///
+/// This is for best-effort extraction: trailing input that [`parse`] would
+/// reject is silently discarded, so a truncated or mistyped value may go
+/// unnoticed.
+pub fn parse_lenient(input: &str) -> Result<Value, crate::ParseError> {
+    parse_leading(input, &State::new(&ParseOptions::default())).map(|(value, _)| value)
+}
+
+/// Parses the one value that starts at byte `offset` of `input`, after any
+/// whitespace and comments there, and returns it with the offset just past
+/// its end, for tooling that has already found where a value starts in a
+/// larger buffer. Whatever follows the value is left alone, so the end can
+/// be passed back in to parse the next one.
+///
+/// Errors have offsets into the whole of `input`. An `offset` past the end
+/// of `input` or inside a UTF-8 character is an error rather than a panic.
+pub fn parse_value_at(input: &str, offset: usize) -> Result<(Value, usize), crate::ParseError> {
+    if !input.is_char_boundary(offset) {
+        return Err(crate::ParseError::Syntax {
+            offset: offset.min(input.len()),
+            message: match offset > input.len() {
+                true => "offset is past the end of input".to_string(),
+                false => "offset is not at a character boundary".to_string(),
+            },
+        });
+    }
+
+    let options = ParseOptions::default();
+    let state = State::new(&options);
+    let start = &input[offset..];
+    let (start, _) = state.sp::<()>()(start).unwrap_or((start, ""));
+    if start.is_empty() {
+        return Err(crate::ParseError::Empty);
+    }
+
+    let result = parse_value::<VerboseError<&str>>(&state)(start);
+    match result {
+        Ok((rest, value)) => Ok((value, input.offset(rest))),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => Err(state.error(input, err)),
+        Err(nom::Err::Incomplete(_)) => Err(crate::ParseError::Syntax {
+            offset: input.len(),
+            message: "unexpected end of input".to_string(),
+        }),
+    }
+}
+
+/// Parses the value at the start of `input` like [`parse_lenient`], but also
+/// skips the hash entries and array elements it can't make sense of, such as
+/// `b: user&.name` with an operator leaked from an inspected expression, and
+/// returns what it skipped rather than failing.
+///
+/// A skipped entry or element runs up to the next `,` or closing bracket
+/// outside any brackets or strings, and is left out of the value. Each one
+/// is reported as an error whose offset is into `input`, in the order they
+/// were found, followed by any trailing input that was ignored. Input that
+/// is malformed outside any entry or element, such as an unclosed bracket,
+/// still fails.
+pub fn parse_lenient_with_diagnostics(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Value, Vec<crate::ParseError>), crate::ParseError> {
+    let state = State::new(options);
+    state.recover.set(true);
+    let (value, rest) = parse_leading(input, &state)?;
+    let mut diagnostics: Vec<_> = state
+        .take_diagnostics(input)
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| crate::ParseError::Syntax {
+            offset: diagnostic.range.start,
+            message: diagnostic.message,
+        })
+        .collect();
+    if !rest.is_empty() && data_section(input, rest).is_none() {
+        diagnostics.push(trailing_input_error(input, rest));
+    }
+    Ok((value, diagnostics))
+}
+
+/// Parses `input` for editor tooling, returning the value along with
+/// everything found wrong with the input, each with the bytes it covers and
+/// a [`DiagnosticCode`] classifying it.
+///
+/// As in [`parse_lenient_with_diagnostics`], hash entries and array elements
+/// that can't be parsed are skipped, so that one mistake doesn't hide the
+/// rest of the value, and trailing input is reported rather than failing. A
+/// key written twice in one hash is reported as a warning. The value is
+/// `None` only if the input can't be parsed at all, in which case the last
+/// diagnostic says why.
+pub fn parse_diagnostics(input: &str) -> (Option<Value>, Vec<Diagnostic>) {
+    let options = ParseOptions::default();
+    let state = State::new(&options);
+    state.recover.set(true);
+    let (value, rest) = match parse_leading(input, &state) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            let mut diagnostics = state.take_diagnostics(input);
+            diagnostics.push(Diagnostic::from_error(input, err));
+            return (None, diagnostics);
+        }
+    };
+    let mut diagnostics = state.take_diagnostics(input);
+    if !rest.is_empty() && data_section(input, rest).is_none() {
+        let message = "unexpected trailing input".to_string();
+        diagnostics.push(Diagnostic {
+            range: input.offset(rest)..input.trim_end().len(),
+            severity: Severity::Error,
+            code: DiagnosticCode::classify(&message),
+            message,
+        });
+    }
+    (Some(value), diagnostics)
+}
+
+/// Parses every value in `input`, separated by whitespace such as one value
+/// per line, and returns them in order. Empty input gives no values.
+///
+/// Parsing stops at the first malformed value, and the error's offset is into
+/// the whole input, so [`ParseError::line_column`](crate::ParseError::line_column)
+/// gives its line. As with [`parse`], an `__END__` line ends the input.
+pub fn parse_concatenated(input: &str) -> Result<Vec<Value>, crate::ParseError> {
+    parse_concatenated_with_options(input, &ParseOptions::default())
+}
+
+/// Like [`parse_concatenated`], but accepts the extra syntax enabled in
+/// `options`, including [`document_separators`](ParseOptions::document_separators)
+/// and [`semicolon_separators`](ParseOptions::semicolon_separators).
+pub fn parse_concatenated_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<Vec<Value>, crate::ParseError> {
+    let state = State::new(options);
+    let mut values = Vec::new();
+    let mut rest = input;
+    loop {
+        if options.document_separators {
+            if let Some(after) = document_separator(input, rest) {
+                rest = after;
+                continue;
+            }
+        }
+        if options.semicolon_separators {
+            let (after, _) = sp::<()>(rest).unwrap_or((rest, ""));
+            if let Some(after) = after.strip_prefix(';') {
+                rest = after;
+                continue;
+            }
+        }
+
+        let base = input.offset(rest);
+        let (value, after) = match parse_leading(rest, &state) {
+            Ok(parsed) => parsed,
+            Err(crate::ParseError::Empty) => return Ok(values),
+            Err(err) => return Err(err.offset_by(base)),
+        };
+        values.push(value);
+
+        if after.is_empty() || data_section(input, after).is_some() {
+            return Ok(values);
+        }
+        let separated = input[..input.offset(after)].ends_with(char::is_whitespace)
+            || options.semicolon_separators && after.starts_with(';');
+        if !separated {
+            return Err(crate::ParseError::Syntax {
+                offset: input.offset(after),
+                message: "expected whitespace between values".to_string(),
+            });
+        }
+        rest = after;
+    }
+}
+
+/// If `rest` starts with whitespace and then a `---` line, returns the input
+/// after that line.
+fn document_separator<'a>(input: &'a str, rest: &'a str) -> Option<&'a str> {
+    let line = rest.trim_start_matches([' ', '\t', '\r', '\n']);
+    let offset = input.offset(line);
+    if offset > 0 && !input[..offset].ends_with('\n') {
+        return None;
+    }
+
+    let after = line.strip_prefix("---")?;
+    match after.find('\n') {
+        Some(end) if after[..end].trim_end().is_empty() => Some(&after[end + 1..]),
+        None if after.trim_end().is_empty() => Some(&after[after.len()..]),
+        _ => None,
+    }
+}
+
+/// Parses a method call such as `foo(1, 2, a: 3, b: 4)`, returning the
+/// method's name and its arguments. The name may have a receiver, as in
+/// `User.find_by(id: 1)`. As in Ruby, `key: value` and `key => value`
+/// arguments at the end are gathered into one hash, the last argument, so
+/// that call gives `("foo", [1, 2, {"a" => 3, "b" => 4}])`. Splats of
+/// literals are expanded the same way, so `foo(*[1, 2], **{a: 3})` gives
+/// `("foo", [1, 2, {"a" => 3}])`.
+pub fn parse_call_args(input: &str) -> Result<(String, Vec<Value>), crate::ParseError> {
+    let options = ParseOptions::default();
+    let state = State::new(&options);
+    let name = recognize(pair(
+        take_while1(|c: char| c.is_alphanumeric() || "_.:@".contains(c)),
+        opt(alt((char('?'), char('!')))),
+    ));
+    let (start, _) = sp::<()>(input).unwrap_or((input, ""));
+    if start.is_empty() {
+        return Err(crate::ParseError::Empty);
+    }
+
+    let result = terminated(
+        pair(name, parse_list::<VerboseError<&str>>(&state, '(', ')')),
+        state.sp(),
+    )(start);
+    match result {
+        Ok(("", (name, args))) => Ok((name.to_string(), args)),
+        Ok((rest, _)) => Err(trailing_input_error(input, rest)),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => Err(state.error(input, err)),
+        Err(nom::Err::Incomplete(_)) => Err(crate::ParseError::Syntax {
+            offset: input.len(),
+            message: "unexpected end of input".to_string(),
+        }),
+    }
+}
+
+/// Finds the first hash embedded in other text, such as the one at the end
+/// of the log line `2023-01-01 INFO processed {:id=>1, :ok=>true}`, and
+/// returns it with its byte range in `input`.
+///
+/// Each `{` is tried in turn, and one that doesn't start a valid hash, like
+/// the brace in `{pid}`, is skipped. Braces in a hash's strings and nested
+/// hashes are part of the hash, but quotes in the text around it aren't
+/// tracked, so a hash inside a quoted part of the line is found too.
+pub fn scan_first_hash(input: &str) -> Option<(Range<usize>, Value)> {
+    let options = ParseOptions::default();
+    input.match_indices('{').find_map(|(start, _)| {
+        let state = State::new(&options);
+        let (rest, value) = parse_hash::<()>(&state)(&input[start..]).ok()?;
+        Some((start..input.offset(rest), value))
+    })
+}
+
+/// Parses a value that has itself been written as a Ruby string, such as the
+/// `"{:a=>\"b\"}"` that `inspect` gives for a stringified hash stored in a
+/// database column. The whole input has to be one double- or single-quoted
+/// string, and its decoded content is then parsed like [`parse`] does.
+///
+/// Errors in the outer string have offsets into `input`, but errors in the
+/// value have offsets into the decoded content.
+pub fn parse_quoted(input: &str) -> Result<Value, crate::ParseError> {
+    let options = ParseOptions::default();
+    let state = State::new(&options);
+    let (start, _) = sp::<()>(input).unwrap_or((input, ""));
+    if start.is_empty() {
+        return Err(crate::ParseError::Empty);
+    }
+
+    let content = match terminated(parse_string::<VerboseError<&str>>(&state), sp)(start) {
+        Ok(("", content)) => content,
+        Ok((rest, _)) => return Err(trailing_input_error(input, rest)),
+        Err(nom::Err::Failure(err)) => return Err(crate::ParseError::from_verbose(input, err)),
+        Err(_) => {
+            return Err(crate::ParseError::Syntax {
+                offset: input.offset(start),
+                message: "expected a quoted string".to_string(),
+            })
+        }
+    };
+    parse(&content)
+}
+
+/// Like [`parse_with_options`], with
+/// [`ignore_trailing_calls`](ParseOptions::ignore_trailing_calls) on, but
+/// also returns where the literals that their calls left frozen are, such as
+/// the array in `{list: [1, 2].freeze}`. Each is given as a JSON pointer, as
+/// [`Value::set_pointer`] takes, so that one is `"/list"`, and the empty
+/// pointer is the whole value. Frozen strings are included, whether or not
+/// [`record_frozen`](ParseOptions::record_frozen) marks them in the value.
+pub fn parse_with_frozen(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Value, BTreeSet<String>), crate::ParseError> {
+    let options = ParseOptions {
+        ignore_trailing_calls: true,
+        ..options.clone()
+    };
+    let state = State {
+        tracked: Some(RefCell::new(Tracked::default())),
+        ..State::new(&options)
+    };
+    let value = parse_complete(input, &state)?;
+    let tracked = state.tracked.map(RefCell::into_inner).unwrap_or_default();
+    Ok((value, tracked.frozen.into_values().collect()))
+}
+
+/// Like [`parse_with_options`], but also returns the comments in `input`,
+/// each attached to the value it is nearest, for writing back with
+/// [`Value::to_ruby_with_comments`]. A comment after a value on the same
+/// line trails that value, and any other comment leads the value after it,
+/// or for an entry of a hash, the value of the entry after it. Comments
+/// after the last value trail the whole value.
+///
+/// Comments inside inspected objects, and in an
+/// [`indent_mode`](ParseOptions::indent_mode) document, are dropped.
+pub fn parse_with_comments(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Value, Comments), crate::ParseError> {
+    let state = State {
+        tracked: Some(RefCell::new(Tracked::default())),
+        ..State::new(options)
+    };
+    let value = parse_complete(input, &state)?;
+    let tracked = state.tracked.map(RefCell::into_inner).unwrap_or_default();
+    let (root, _) = sp_with::<()>(options, input).unwrap_or((input, ""));
+    let base = input.as_ptr() as usize;
+    let spans = tracked
+        .spans
+        .into_iter()
+        .map(|(start, (end, path))| (start - base..end - base, path))
+        .collect();
+    let comments = tracked
+        .comments
+        .into_iter()
+        .map(|(start, end)| start - base..end - base)
+        .collect();
+    let comments = Comments::attach(input, input.offset(root), spans, comments);
+    Ok((value, comments))
+}
+
+/// Like [`parse_with_options`], but also returns where the key of each entry
+/// of a hash is written, for going from a position in the input to the key
+/// under it. Each entry is named by the JSON pointer of its value, as
+/// [`Value::set_pointer`] takes, so that the key of `{a: {b: 1}}`'s inner
+/// entry is at `"/a/b"`, and its range covers `b` as written, including any
+/// quotes or the colon of a `:b =>` key but not the separator after it. Every
+/// key in the input is listed at the pointer it was parsed at, so a key
+/// written more than once has the range of the last of them, whichever value
+/// [`on_duplicate_key`](ParseOptions::on_duplicate_key) keeps. A key split by
+/// [`split_dotted_keys`](ParseOptions::split_dotted_keys) is listed at the
+/// innermost pointer it makes.
+///
+/// The fields of inspected objects, and the keys of an
+/// [`indent_mode`](ParseOptions::indent_mode) document, aren't included.
+pub fn parse_with_key_spans(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Value, BTreeMap<String, Range<usize>>), crate::ParseError> {
+    let state = State {
+        tracked: Some(RefCell::new(Tracked::default())),
+        ..State::new(options)
+    };
+    let value = parse_complete(input, &state)?;
+    let tracked = state.tracked.map(RefCell::into_inner).unwrap_or_default();
+    let base = input.as_ptr() as usize;
+    let keys = tracked
+        .keys
+        .into_iter()
+        .map(|(start, (end, path))| (path, start - base..end - base))
+        .collect();
+    Ok((value, keys))
+}
+
+/// Like [`parse`], but decodes string escapes in `scratch` rather than a fresh
+/// buffer, so that the buffer's allocation can be reused across many parses.
+/// The contents of `scratch` are cleared.
+pub fn parse_with_scratch(input: &str, scratch: &mut String) -> Result<Value, crate::ParseError> {
+    let mut buffer = std::mem::take(scratch).into_bytes();
+    buffer.clear();
+    let options = ParseOptions::default();
+    let state = State {
+        scratch: RefCell::new(buffer),
+        ..State::new(&options)
+    };
+
+    let result = parse_complete(input, &state);
+
+    let mut buffer = state.scratch.into_inner();
+    buffer.clear();
+    *scratch = String::from_utf8(buffer).unwrap_or_default();
+    result
+}
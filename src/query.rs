@@ -0,0 +1,87 @@
+//! Selecting every value at a path with wildcards.
+
+use crate::Value;
+
+/// A path whose steps may match many values, for [`Value::query`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    segments: Vec<QuerySegment>,
+}
+
+/// One step of a [`Query`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuerySegment {
+    /// A key of an object or struct.
+    Key(String),
+    /// An element of an array, or the key written as this number, like
+    /// `1 =>`, of an object.
+    Index(usize),
+    /// Every element of an array, or every value of an object or struct.
+    Wildcard,
+}
+
+impl Query {
+    pub fn new(segments: Vec<QuerySegment>) -> Self {
+        Query { segments }
+    }
+
+    /// Reads a path with its steps separated by dots, such as `users.*.age`.
+    /// A `*` step is a wildcard and a step of digits is an index, and any
+    /// other step is a key, used as it is. The empty path is the value
+    /// queried.
+    pub fn parse(path: &str) -> Self {
+        if path.is_empty() {
+            return Query::new(Vec::new());
+        }
+        let segments = path
+            .split('.')
+            .map(|step| match step {
+                "*" => QuerySegment::Wildcard,
+                step if !step.is_empty() && step.bytes().all(|b| b.is_ascii_digit()) => {
+                    match step.parse() {
+                        Ok(index) => QuerySegment::Index(index),
+                        Err(_) => QuerySegment::Key(step.to_string()),
+                    }
+                }
+                step => QuerySegment::Key(step.to_string()),
+            })
+            .collect();
+        Query::new(segments)
+    }
+
+    pub fn segments(&self) -> &[QuerySegment] {
+        &self.segments
+    }
+}
+
+impl Value {
+    /// Every value that `query` leads to from this one, in order: array
+    /// elements by index and object values by key. So
+    /// `value.query(&Query::parse("users.*.age"))` is the age of each user,
+    /// skipping users without one.
+    pub fn query(&self, query: &Query) -> Vec<&Value> {
+        let mut found = vec![self];
+        for segment in &query.segments {
+            let mut next = Vec::new();
+            for value in found {
+                match (segment, value) {
+                    (QuerySegment::Key(key), value) => next.extend(value.get(key)),
+                    (QuerySegment::Index(index), Value::Array(values)) => {
+                        next.extend(values.get(*index))
+                    }
+                    (QuerySegment::Index(index), value) => {
+                        next.extend(value.get(index.to_string()))
+                    }
+                    (QuerySegment::Wildcard, Value::Array(values)) => next.extend(values),
+                    (
+                        QuerySegment::Wildcard,
+                        Value::Object(map) | Value::Struct { fields: map, .. },
+                    ) => next.extend(map.values()),
+                    (QuerySegment::Wildcard, _) => {}
+                }
+            }
+            found = next;
+        }
+        found
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::Read;
+
+use crate::{parse, parse_with_options, InvalidUtf8, ParseError, ParseOptions, ReadError, Value};
+
+impl Value {
+    /// Reads all of `reader` and parses it as a single value, like [`parse`].
+    ///
+    /// Input that isn't valid UTF-8 is reported as an
+    /// [`std::io::ErrorKind::InvalidData`] error.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Value, ReadError> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        Ok(parse(&input)?)
+    }
+}
+
+/// The first of the characters that stand for bytes that aren't UTF-8 while
+/// [`parse_slice`] parses with [`InvalidUtf8::Raw`]. The byte `b` is
+/// `RAW_BYTE + b`, so these are the last 128 characters of plane 16, which
+/// is for private use.
+const RAW_BYTE: u32 = 0x10ff00;
+
+/// Parses `input`, which may not be valid UTF-8, like
+/// [`parse_with_options`]. Bytes that aren't valid UTF-8 are handled as
+/// [`invalid_utf8`](ParseOptions::invalid_utf8) says. An error's offset is
+/// into `input` when it is valid UTF-8 and otherwise into the text it was
+/// decoded as.
+///
+/// With [`InvalidUtf8::Raw`], `input` may not have any of the characters
+/// U+10FF80 to U+10FFFF, which stand for the raw bytes while parsing.
+pub fn parse_slice(input: &[u8], options: &ParseOptions) -> Result<Value, ParseError> {
+    let err = match std::str::from_utf8(input) {
+        Ok(text) => return parse_with_options(text, options),
+        Err(err) => err,
+    };
+    match options.invalid_utf8 {
+        InvalidUtf8::Error => Err(ParseError::Syntax {
+            offset: err.valid_up_to(),
+            message: "invalid UTF-8".to_string(),
+        }),
+        InvalidUtf8::Lossy => parse_with_options(&String::from_utf8_lossy(input), options),
+        InvalidUtf8::Raw => {
+            let text = mark_raw_bytes(input)?;
+            let mut value = parse_with_options(&text, options)?;
+            value.walk_mut(&mut |value| match value {
+                Value::Bytes(string) | Value::FrozenBytes(string) if has_raw_bytes(string) => {
+                    *value = Value::RawBytes(unmark_raw_bytes(string))
+                }
+                Value::Symbol(string) | Value::Constant(string) if has_raw_bytes(string) => {
+                    *string = replace_raw_bytes(string)
+                }
+                Value::Object(map) | Value::Struct { fields: map, .. }
+                    if map.keys().any(|key| has_raw_bytes(key)) =>
+                {
+                    *map = std::mem::take(map)
+                        .into_iter()
+                        .map(|(key, value)| (replace_raw_bytes(&key), value))
+                        .collect()
+                }
+                _ => {}
+            });
+            Ok(value)
+        }
+    }
+}
+
+fn raw_byte(c: char) -> Option<u8> {
+    (c as u32)
+        .checked_sub(RAW_BYTE)
+        .filter(|byte| *byte >= 0x80)
+        .map(|byte| byte as u8)
+}
+
+/// Decodes `input`, with each byte that isn't UTF-8 as the character that
+/// stands for it.
+fn mark_raw_bytes(input: &[u8]) -> Result<String, ParseError> {
+    let mut text = String::with_capacity(input.len());
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid, 0),
+            Err(err) => (
+                std::str::from_utf8(&rest[..err.valid_up_to()]).unwrap_or_default(),
+                err.error_len().unwrap_or(rest.len() - err.valid_up_to()),
+            ),
+        };
+        if let Some(index) = valid.find(|c| raw_byte(c).is_some()) {
+            return Err(ParseError::Syntax {
+                offset: input.len() - rest.len() + index,
+                message: "character reserved for raw bytes".to_string(),
+            });
+        }
+        text.push_str(valid);
+        for byte in &rest[valid.len()..valid.len() + invalid] {
+            text.push(char::from_u32(RAW_BYTE + u32::from(*byte)).unwrap_or_default());
+        }
+        rest = &rest[valid.len() + invalid..];
+    }
+    Ok(text)
+}
+
+fn has_raw_bytes(text: &str) -> bool {
+    text.chars().any(|c| raw_byte(c).is_some())
+}
+
+fn unmark_raw_bytes(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match raw_byte(c) {
+            Some(byte) => bytes.push(byte),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Replaces each run of raw bytes with one U+FFFD.
+fn replace_raw_bytes(text: &str) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut in_run = false;
+    for c in text.chars() {
+        match raw_byte(c) {
+            Some(_) if in_run => {}
+            Some(_) => replaced.push(char::REPLACEMENT_CHARACTER),
+            None => replaced.push(c),
+        }
+        in_run = raw_byte(c).is_some();
+    }
+    replaced
+}
@@ -0,0 +1,528 @@
+//! Rendering values back to Ruby source.
+
+use std::fmt::{self, Write};
+
+use crate::{
+    comments::NodeComments, parser::OPERATOR_SYMBOLS, shape::push_segment, Comments, ObjectKey,
+    Value,
+};
+
+impl Value {
+    /// Renders the value as a Ruby literal that parses back to the same value.
+    pub fn to_ruby(&self) -> String {
+        self.to_string()
+    }
+
+    /// Writes the value as Ruby source to `w` as it goes, rather than
+    /// building the whole string first as [`to_ruby`](Value::to_ruby) does.
+    /// This is what `Display` writes.
+    pub fn write_ruby(&self, w: &mut impl Write) -> fmt::Result {
+        write!(w, "{}", self)
+    }
+
+    /// Renders the value the way Ruby 3.4's `inspect` does, for comparing
+    /// against output from Ruby itself. This differs from
+    /// [`to_ruby`](Value::to_ruby) in that:
+    ///
+    /// - symbol keys are written in the new style, `name: 1` when the name
+    ///   allows it and `"a b": 1` otherwise;
+    /// - floats use Ruby's formatting, such as `1.0e+20`, `1.0e-05`, `NaN` and
+    ///   `Infinity`;
+    /// - strings escape control characters as `\a`, `\b`, `\f`, `\v` or
+    ///   `\u0001`, with four uppercase hex digits, as Ruby does for UTF-8
+    ///   strings.
+    ///
+    /// Everything else, including `nil`, `=>` with a space on each side, `, `
+    /// between elements and the `#<struct Name a=1>` form, is the same. Keys
+    /// are in sorted order rather than Ruby's insertion order, so hashes with
+    /// several keys only match Ruby when it sorts them the same way.
+    ///
+    /// Two things the parser keeps differently from Ruby come back
+    /// differently too. Ruby reads the label keys of `{a: 1}` and `{"a": 1}`
+    /// as symbols, but they are stored as the strings they spell, so they
+    /// are written `{"a" => 1}`; parse with
+    /// [`KeyStyle::AllSymbols`](crate::KeyStyle::AllSymbols) to get symbols
+    /// instead. And an inspected object such as `#<User id: 1>` is a struct,
+    /// so it is written `#<struct User id=1>`.
+    pub fn to_inspect(&self) -> String {
+        Inspect(self).to_string()
+    }
+}
+
+impl Value {
+    /// Renders the value as Ruby source like [`to_ruby`](Value::to_ruby),
+    /// with `comments` written back where they were attached, as by
+    /// [`parse_with_comments`](crate::parse_with_comments). Arrays and
+    /// hashes are written with each element or entry on its own line,
+    /// indented by two spaces, so that comments have lines to go on, and a
+    /// comma after every one. Inspected objects are written on one line,
+    /// without comments.
+    pub fn to_ruby_with_comments(&self, comments: &Comments) -> String {
+        enum Pending<'v> {
+            Value(&'v Value, String, usize),
+            Text(String),
+        }
+
+        let none = NodeComments::default();
+        let node = |path: &str| comments.get(path).unwrap_or(&none);
+        let trailing = |path: &str, indent: usize| {
+            let mut text = String::new();
+            for (index, comment) in node(path).trailing.iter().enumerate() {
+                match index {
+                    0 => text.push(' '),
+                    _ => {
+                        text.push('\n');
+                        text.push_str(&"  ".repeat(indent));
+                    }
+                }
+                text.push_str(comment);
+            }
+            text
+        };
+        let leading = |out: &mut String, path: &str, indent: usize| {
+            for comment in &node(path).leading {
+                out.push_str(&"  ".repeat(indent));
+                out.push_str(comment);
+                out.push('\n');
+            }
+        };
+
+        let mut out = String::new();
+        leading(&mut out, "", 0);
+        let mut stack = vec![
+            Pending::Text(trailing("", 0) + "\n"),
+            Pending::Value(self, String::new(), 0),
+        ];
+        while let Some(pending) = stack.pop() {
+            let (value, path, indent) = match pending {
+                Pending::Value(value, path, indent) => (value, path, indent),
+                Pending::Text(text) => {
+                    out.push_str(&text);
+                    continue;
+                }
+            };
+            let inner = "  ".repeat(indent + 1);
+            match value {
+                Value::Object(map) if !map.is_empty() => {
+                    out.push_str("{\n");
+                    stack.push(Pending::Text(format!("{}}}", "  ".repeat(indent))));
+                    for (key, value) in map.iter().rev() {
+                        let mut child = path.clone();
+                        push_segment(&mut child, key);
+                        let mut before = String::new();
+                        leading(&mut before, &child, indent + 1);
+                        before.push_str(&inner);
+                        before.push_str(&Key(key).to_string());
+                        stack.push(Pending::Text(format!(
+                            ",{}\n",
+                            trailing(&child, indent + 1)
+                        )));
+                        stack.push(Pending::Value(value, child, indent + 1));
+                        stack.push(Pending::Text(before));
+                    }
+                }
+                Value::Array(values) if !values.is_empty() => {
+                    out.push_str("[\n");
+                    stack.push(Pending::Text(format!("{}]", "  ".repeat(indent))));
+                    for (index, value) in values.iter().enumerate().rev() {
+                        let mut child = path.clone();
+                        push_segment(&mut child, &index.to_string());
+                        let mut before = String::new();
+                        leading(&mut before, &child, indent + 1);
+                        before.push_str(&inner);
+                        stack.push(Pending::Text(format!(
+                            ",{}\n",
+                            trailing(&child, indent + 1)
+                        )));
+                        stack.push(Pending::Value(value, child, indent + 1));
+                        stack.push(Pending::Text(before));
+                    }
+                }
+                value => out.push_str(&value.to_ruby()),
+            }
+        }
+        out
+    }
+}
+
+/// An object key and its separator, as [`Value::to_ruby`] writes them.
+struct Key<'k>(&'k str);
+
+impl fmt::Display for Key<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_key(f, self.0, Style::Source)
+    }
+}
+
+/// Which flavour of Ruby output to write.
+#[derive(Clone, Copy, PartialEq)]
+enum Style {
+    /// Source that parses back to the same value.
+    Source,
+    /// Ruby's own `inspect` output.
+    Inspect,
+    /// Source, but with decimals written as `BigDecimal("1.5")` so that they
+    /// can't be mistaken for floats or integers.
+    Debug,
+}
+
+struct Inspect<'v>(&'v Value);
+
+impl fmt::Display for Inspect<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self.0, Style::Inspect)
+    }
+}
+
+/// What is left to write while rendering a value.
+enum Pending<'v> {
+    Value(&'v Value),
+    Text(&'static str),
+    /// An object key, written with the separator after it.
+    Key(&'v str),
+    /// A struct field name, written as is.
+    Field(&'v str),
+}
+
+/// Writes the value as Ruby source, such as `{"a" => [1, 2.0, :b, nil]}`.
+///
+/// Nested values are written from an explicit stack rather than by recursion,
+/// so that deeply nested values can't overflow the call stack.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, Style::Source)
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, value: &Value, style: Style) -> fmt::Result {
+    let mut stack = vec![Pending::Value(value)];
+    while let Some(pending) = stack.pop() {
+        let value = match pending {
+            Pending::Value(value) => value,
+            Pending::Text(text) => {
+                f.write_str(text)?;
+                continue;
+            }
+            Pending::Key(key) => {
+                write_key(f, key, style)?;
+                continue;
+            }
+            Pending::Field(field) => {
+                f.write_str(field)?;
+                continue;
+            }
+        };
+
+        match value {
+            Value::Bytes(string) => write_string(f, string, style)?,
+            // Ruby's `inspect` doesn't show whether a string is frozen.
+            Value::FrozenBytes(string) => {
+                write_string(f, string, style)?;
+                if style != Style::Inspect {
+                    f.write_str(".freeze")?;
+                }
+            }
+            Value::RawBytes(bytes) => write_raw_string(f, bytes, style)?,
+            Value::Symbol(name) => write_symbol(f, name, style)?,
+            Value::Constant(path) => f.write_str(path)?,
+            Value::Integer(integer) => write!(f, "{}", integer)?,
+            Value::Float(float) => write_float(f, *float, style)?,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => write_decimal(f, decimal, style)?,
+            #[cfg(feature = "chrono")]
+            Value::DateTime(time) => write_time(f, time)?,
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => write_date(f, date, style)?,
+            Value::Boolean(boolean) => write!(f, "{}", boolean)?,
+            Value::Object(map) => {
+                f.write_char('{')?;
+                stack.push(Pending::Text("}"));
+                for (index, (key, value)) in map.iter().enumerate().rev() {
+                    stack.push(Pending::Value(value));
+                    stack.push(Pending::Key(key));
+                    if index > 0 {
+                        stack.push(Pending::Text(", "));
+                    }
+                }
+            }
+            // There is no literal syntax for structs, so this is the
+            // inspect form, which the parser accepts back.
+            Value::Struct { name, fields } => {
+                f.write_str("#<struct")?;
+                if !name.is_empty() {
+                    write!(f, " {}", name)?;
+                }
+                // A last symbol like `:>` or `:name=` would run into the
+                // closing `>`.
+                let close = match fields.values().next_back() {
+                    Some(Value::Symbol(name))
+                        if style != Style::Inspect && name.ends_with(['>', '=']) =>
+                    {
+                        " >"
+                    }
+                    _ => ">",
+                };
+                stack.push(Pending::Text(close));
+                for (index, (key, value)) in fields.iter().enumerate().rev() {
+                    stack.push(Pending::Value(value));
+                    stack.push(Pending::Text("="));
+                    stack.push(Pending::Field(key));
+                    stack.push(Pending::Text(if index > 0 { ", " } else { " " }));
+                }
+            }
+            Value::Array(values) => {
+                f.write_char('[')?;
+                stack.push(Pending::Text("]"));
+                for (index, value) in values.iter().enumerate().rev() {
+                    stack.push(Pending::Value(value));
+                    if index > 0 {
+                        stack.push(Pending::Text(", "));
+                    }
+                }
+            }
+            Value::Null => f.write_str("nil")?,
+            // Which of the two markers it was isn't kept.
+            Value::Recursive => f.write_str("{...}")?,
+        }
+    }
+    Ok(())
+}
+
+/// Uses the Ruby rendering, which keeps strings, symbols, integers and floats
+/// visually distinct while being much shorter than the derived form. Decimals
+/// are written as `BigDecimal("1.50")` rather than `1.50`, to tell them apart
+/// from floats.
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, Style::Debug)
+    }
+}
+
+/// Writes `"key" => ` or `:name => `, or for [`Style::Inspect`], symbol keys
+/// as `name: ` or `"name": `, as Ruby 3.4 does. `nil`, `true` and `false`
+/// keys are written bare.
+fn write_key(f: &mut fmt::Formatter<'_>, key: &str, style: Style) -> fmt::Result {
+    match ObjectKey::of(key) {
+        ObjectKey::Symbol(name) if style == Style::Inspect => {
+            if is_label(name) {
+                f.write_str(name)?;
+            } else {
+                write_string(f, name, style)?;
+            }
+            return f.write_str(": ");
+        }
+        ObjectKey::Symbol(name) => write_symbol(f, name, style)?,
+        ObjectKey::String(text) => write_string(f, text, style)?,
+        ObjectKey::Nil => f.write_str("nil")?,
+        ObjectKey::Boolean(boolean) => write!(f, "{}", boolean)?,
+    }
+    f.write_str(" => ")
+}
+
+/// Floats always have a decimal point or exponent so they can't be mistaken
+/// for integers.
+fn write_float(f: &mut fmt::Formatter<'_>, float: f64, style: Style) -> fmt::Result {
+    if style == Style::Inspect {
+        return write_inspect_float(f, float);
+    }
+    if float.is_nan() {
+        f.write_str("Float::NAN")
+    } else if float.is_infinite() {
+        f.write_str(if float > 0.0 {
+            "Float::INFINITY"
+        } else {
+            "-Float::INFINITY"
+        })
+    } else {
+        write!(f, "{:?}", float)
+    }
+}
+
+/// Writes a float like Ruby's `Float#inspect`, which switches to an exponent
+/// below `1e-4` and from `1e16` on, with a sign and at least two digits:
+/// `1.0e+16`, `1.5e-07`.
+fn write_inspect_float(f: &mut fmt::Formatter<'_>, float: f64) -> fmt::Result {
+    if float.is_nan() {
+        return f.write_str("NaN");
+    }
+    if float.is_infinite() {
+        return f.write_str(if float > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+    // Rust's shortest round-trip digits, with exponents at the same
+    // thresholds as Ruby.
+    let text = format!("{:?}", float);
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some(parts) => parts,
+        None => return f.write_str(&text),
+    };
+    f.write_str(mantissa)?;
+    if !mantissa.contains('.') {
+        f.write_str(".0")?;
+    }
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    write!(f, "e{}{:0>2}", sign, digits)
+}
+
+/// Decimals are written as plain numbers with every digit kept, such as
+/// `1.50`, which parse back as decimals with
+/// [`decimal_numbers`](crate::ParseOptions::decimal_numbers). `inspect` style
+/// follows Ruby's `BigDecimal#inspect`, as in `0.15e1`, and `Debug` wraps them
+/// as `BigDecimal("1.50")`.
+#[cfg(feature = "decimal")]
+fn write_decimal(
+    f: &mut fmt::Formatter<'_>,
+    decimal: &rust_decimal::Decimal,
+    style: Style,
+) -> fmt::Result {
+    match style {
+        Style::Source => return write!(f, "{}", decimal),
+        Style::Debug => return write!(f, "BigDecimal(\"{}\")", decimal),
+        Style::Inspect => {}
+    }
+    if decimal.is_zero() {
+        return f.write_str(if decimal.is_sign_negative() {
+            "-0.0"
+        } else {
+            "0.0"
+        });
+    }
+    let digits = decimal.mantissa().unsigned_abs().to_string();
+    let exponent = digits.len() as i64 - i64::from(decimal.scale());
+    let sign = if decimal.is_sign_negative() { "-" } else { "" };
+    write!(f, "{}0.{}e{}", sign, digits.trim_end_matches('0'), exponent)
+}
+
+/// Writes a time the way `Time#inspect` does, with the fraction of a second
+/// only when there is one and `UTC` for a zero offset.
+#[cfg(feature = "chrono")]
+fn write_time(
+    f: &mut fmt::Formatter<'_>,
+    time: &chrono::DateTime<chrono::FixedOffset>,
+) -> fmt::Result {
+    use chrono::Timelike;
+
+    write!(f, "{}", time.format("%Y-%m-%d %H:%M:%S"))?;
+    let nanos = time.nanosecond();
+    if nanos > 0 {
+        write!(f, ".{}", format!("{:09}", nanos).trim_end_matches('0'))?;
+    }
+    if time.offset().local_minus_utc() == 0 {
+        f.write_str(" UTC")
+    } else {
+        write!(f, " {}", time.format("%z"))
+    }
+}
+
+/// Dates are written bare, as in `2023-01-01`, which parses back with
+/// [`strict_timestamps`](crate::ParseOptions::strict_timestamps). `inspect`
+/// style follows Ruby's `Date#inspect`, with the Julian day number.
+#[cfg(feature = "chrono")]
+fn write_date(f: &mut fmt::Formatter<'_>, date: &chrono::NaiveDate, style: Style) -> fmt::Result {
+    use chrono::Datelike;
+
+    if style != Style::Inspect {
+        return write!(f, "{}", date);
+    }
+    // The Julian day that day 1 of the common era falls on, less one.
+    const JULIAN_DAY_OFFSET: i32 = 1_721_425;
+    write!(
+        f,
+        "#<Date: {} (({}j,0s,0n),+0s,2299161j)>",
+        date,
+        date.num_days_from_ce() + JULIAN_DAY_OFFSET
+    )
+}
+
+/// Writes a double-quoted string, escaping anything that Ruby would otherwise
+/// interpret.
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str, style: Style) -> fmt::Result {
+    f.write_char('"')?;
+    write_escaped(f, string, style)?;
+    f.write_char('"')
+}
+
+/// Writes a string with bytes that aren't UTF-8, as `\xNN` escapes.
+fn write_raw_string(f: &mut fmt::Formatter<'_>, bytes: &[u8], style: Style) -> fmt::Result {
+    f.write_char('"')?;
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid, 0),
+            Err(err) => (
+                std::str::from_utf8(&rest[..err.valid_up_to()]).unwrap_or_default(),
+                err.error_len().unwrap_or(rest.len() - err.valid_up_to()),
+            ),
+        };
+        write_escaped(f, valid, style)?;
+        for byte in &rest[valid.len()..valid.len() + invalid] {
+            write!(f, "\\x{:02X}", byte)?;
+        }
+        rest = &rest[valid.len() + invalid..];
+    }
+    f.write_char('"')
+}
+
+/// Writes the content of a double-quoted string, escaping what needs it.
+fn write_escaped(f: &mut fmt::Formatter<'_>, string: &str, style: Style) -> fmt::Result {
+    let mut chars = string.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            '\x1b' => f.write_str("\\e")?,
+            '\x07' if style == Style::Inspect => f.write_str("\\a")?,
+            '\x08' if style == Style::Inspect => f.write_str("\\b")?,
+            '\x0b' if style == Style::Inspect => f.write_str("\\v")?,
+            '\x0c' if style == Style::Inspect => f.write_str("\\f")?,
+            c if c.is_control() && style == Style::Inspect => write!(f, "\\u{:04X}", c as u32)?,
+            // `#{`, `#$` and `#@` would start interpolation.
+            '#' if matches!(chars.peek(), Some('{') | Some('$') | Some('@')) => {
+                f.write_str("\\#")?
+            }
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes `:name`, quoting the name when it isn't an identifier, a method
+/// name such as `:empty?`, `:save!` or `:name=`, or an operator.
+fn write_symbol(f: &mut fmt::Formatter<'_>, name: &str, style: Style) -> fmt::Result {
+    f.write_char(':')?;
+    if is_bare_symbol(name) {
+        f.write_str(name)
+    } else {
+        write_string(f, name, style)
+    }
+}
+
+/// Whether `name:` is a valid hash key for a symbol, as for `empty?: true`.
+fn is_label(name: &str) -> bool {
+    is_identifier(name) || (name.ends_with(['?', '!']) && is_identifier(&name[..name.len() - 1]))
+}
+
+/// Whether `:name` is a valid symbol without quotes.
+pub(crate) fn is_bare_symbol(name: &str) -> bool {
+    is_identifier(name)
+        || (name.ends_with(['?', '!', '=']) && is_identifier(&name[..name.len() - 1]))
+        || OPERATOR_SYMBOLS.contains(&name)
+}
+
+/// Whether `name` is an ASCII identifier. Ruby also allows other letters, but
+/// the parser only reads bare symbols in ASCII.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
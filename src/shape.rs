@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::Value;
+
+/// Describes the expected structure of a value, for use with
+/// [`Value::matches_shape`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// Matches any value.
+    Any,
+    String,
+    Symbol,
+    Integer,
+    Float,
+    /// Matches an integer, a float or a decimal.
+    Number,
+    Boolean,
+    Null,
+    /// An array whose elements all match the shape.
+    Array(Box<Shape>),
+    /// An object with the given keys. Keys not listed are allowed and not
+    /// checked.
+    Object(BTreeMap<String, Shape>),
+    /// Inside an object, a key that may be missing but has to match the shape
+    /// when present. Anywhere else it is the same as the inner shape.
+    Optional(Box<Shape>),
+}
+
+impl Shape {
+    pub fn array(element: Shape) -> Self {
+        Shape::Array(Box::new(element))
+    }
+
+    pub fn object<K: Into<String>>(fields: impl IntoIterator<Item = (K, Shape)>) -> Self {
+        Shape::Object(
+            fields
+                .into_iter()
+                .map(|(key, shape)| (key.into(), shape))
+                .collect(),
+        )
+    }
+
+    pub fn optional(shape: Shape) -> Self {
+        Shape::Optional(Box::new(shape))
+    }
+
+    /// The name used for this shape in error messages, matching
+    /// [`Value::type_name`].
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Shape::Any => "any",
+            Shape::String => "string",
+            Shape::Symbol => "symbol",
+            Shape::Integer => "integer",
+            Shape::Float => "float",
+            Shape::Number => "number",
+            Shape::Boolean => "boolean",
+            Shape::Null => "null",
+            Shape::Array(_) => "array",
+            Shape::Object(_) => "object",
+            Shape::Optional(shape) => shape.type_name(),
+        }
+    }
+}
+
+/// A place where a value doesn't match a [`Shape`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeError {
+    /// JSON pointer to the offending value, or to the missing key.
+    pub path: String,
+    pub kind: ShapeErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeErrorKind {
+    /// The value has the wrong type.
+    Mismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A required key is missing.
+    Missing,
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        match self.kind {
+            ShapeErrorKind::Mismatch { expected, found } => {
+                write!(f, "{}: expected {}, found {}", path, expected, found)
+            }
+            ShapeErrorKind::Missing => write!(f, "{}: missing key", path),
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+impl Value {
+    /// Checks the value against `shape`, returning every mismatch found.
+    pub fn matches_shape(&self, shape: &Shape) -> Result<(), Vec<ShapeError>> {
+        let mut errors = Vec::new();
+        check(self, shape, &mut String::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Value {
+    /// Describes this value as a [`Shape`] that it matches, for deriving a
+    /// shape from an example to check other values against. Objects list
+    /// every key as required, with the shape of its value. An array's
+    /// elements have to share a shape, or else they can be anything, as can
+    /// those of an empty array. Values that shapes don't describe, such as
+    /// constants, times and structs, are [`Shape::Any`], and decimals are
+    /// [`Shape::Number`].
+    pub fn infer_shape(&self) -> Shape {
+        match self {
+            Value::Bytes(_) | Value::FrozenBytes(_) | Value::RawBytes(_) => Shape::String,
+            Value::Symbol(_) => Shape::Symbol,
+            Value::Integer(_) => Shape::Integer,
+            Value::Float(_) => Shape::Float,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(_) => Shape::Number,
+            Value::Boolean(_) => Shape::Boolean,
+            Value::Null => Shape::Null,
+            Value::Array(values) => {
+                let mut shapes = values.iter().map(Value::infer_shape);
+                let element = match shapes.next() {
+                    Some(first) if shapes.all(|shape| shape == first) => first,
+                    _ => Shape::Any,
+                };
+                Shape::array(element)
+            }
+            Value::Object(map) => Shape::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.infer_shape()))
+                    .collect(),
+            ),
+            _ => Shape::Any,
+        }
+    }
+}
+
+fn check(value: &Value, shape: &Shape, path: &mut String, errors: &mut Vec<ShapeError>) {
+    let matches = match (shape, value) {
+        (Shape::Optional(shape), _) => return check(value, shape, path, errors),
+        (Shape::Any, _)
+        | (Shape::String, Value::Bytes(_) | Value::FrozenBytes(_) | Value::RawBytes(_))
+        | (Shape::Symbol, Value::Symbol(_))
+        | (Shape::Integer, Value::Integer(_))
+        | (Shape::Float, Value::Float(_))
+        | (Shape::Number, Value::Integer(_))
+        | (Shape::Number, Value::Float(_))
+        | (Shape::Boolean, Value::Boolean(_))
+        | (Shape::Null, Value::Null) => true,
+        #[cfg(feature = "decimal")]
+        (Shape::Number, Value::Decimal(_)) => true,
+        (Shape::Array(element), Value::Array(values)) => {
+            for (index, value) in values.iter().enumerate() {
+                let len = path.len();
+                push_segment(path, &index.to_string());
+                check(value, element, path, errors);
+                path.truncate(len);
+            }
+            true
+        }
+        (Shape::Object(fields), Value::Object(map)) => {
+            for (key, shape) in fields {
+                let len = path.len();
+                push_segment(path, key);
+                match map.get(key) {
+                    Some(value) => check(value, shape, path, errors),
+                    None if matches!(shape, Shape::Optional(_)) => {}
+                    None => errors.push(ShapeError {
+                        path: path.clone(),
+                        kind: ShapeErrorKind::Missing,
+                    }),
+                }
+                path.truncate(len);
+            }
+            true
+        }
+        _ => false,
+    };
+
+    if !matches {
+        errors.push(ShapeError {
+            path: path.clone(),
+            kind: ShapeErrorKind::Mismatch {
+                expected: shape.type_name(),
+                found: value.type_name(),
+            },
+        });
+    }
+}
+
+/// Appends a JSON pointer segment, escaping `~` and `/`.
+pub(crate) fn push_segment(path: &mut String, segment: &str) {
+    path.push('/');
+    for c in segment.chars() {
+        match c {
+            '~' => path.push_str("~0"),
+            '/' => path.push_str("~1"),
+            c => path.push(c),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Values whose children are shared, for cheap clones of large trees.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::Value;
+
+/// A [`Value`] whose arrays, objects and structs hold their children behind
+/// an [`Arc`], so that cloning one, or any part of one, only bumps reference
+/// counts. Made by [`Value::into_shared`]. Changing a value through the
+/// `_mut` methods copies the part being changed if it is shared, as
+/// [`Arc::make_mut`] does, and leaves the other clones as they were.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedValue {
+    Object(Arc<BTreeMap<String, SharedValue>>),
+    /// An inspected object or struct, as in [`Value::Struct`].
+    Struct {
+        name: String,
+        fields: Arc<BTreeMap<String, SharedValue>>,
+    },
+    Array(Arc<Vec<SharedValue>>),
+    /// Any other value, which has no children.
+    Scalar(Arc<Value>),
+}
+
+impl Value {
+    /// Converts the value for sharing. This moves every string and number
+    /// rather than copying it, but allocates for each node.
+    pub fn into_shared(self) -> SharedValue {
+        match self {
+            Value::Object(map) => SharedValue::Object(Arc::new(share_entries(map))),
+            Value::Struct { name, fields } => SharedValue::Struct {
+                name,
+                fields: Arc::new(share_entries(fields)),
+            },
+            Value::Array(values) => SharedValue::Array(Arc::new(
+                values.into_iter().map(Value::into_shared).collect(),
+            )),
+            scalar => SharedValue::Scalar(Arc::new(scalar)),
+        }
+    }
+}
+
+fn share_entries(map: BTreeMap<String, Value>) -> BTreeMap<String, SharedValue> {
+    map.into_iter()
+        .map(|(key, value)| (key, value.into_shared()))
+        .collect()
+}
+
+impl From<Value> for SharedValue {
+    fn from(value: Value) -> Self {
+        value.into_shared()
+    }
+}
+
+impl SharedValue {
+    /// Converts back to a plain value, copying whatever is still shared with
+    /// another clone.
+    pub fn into_value(self) -> Value {
+        fn entries(map: Arc<BTreeMap<String, SharedValue>>) -> BTreeMap<String, Value> {
+            unwrap_or_clone(map)
+                .into_iter()
+                .map(|(key, value)| (key, value.into_value()))
+                .collect()
+        }
+
+        match self {
+            SharedValue::Object(map) => Value::Object(entries(map)),
+            SharedValue::Struct { name, fields } => Value::Struct {
+                name,
+                fields: entries(fields),
+            },
+            SharedValue::Array(values) => Value::Array(
+                unwrap_or_clone(values)
+                    .into_iter()
+                    .map(SharedValue::into_value)
+                    .collect(),
+            ),
+            SharedValue::Scalar(value) => unwrap_or_clone(value),
+        }
+    }
+
+    /// Looks up a key of an object or struct. Returns `None` if there is no
+    /// such key or the value is some other kind.
+    pub fn get(&self, key: &str) -> Option<&SharedValue> {
+        match self {
+            SharedValue::Object(map) | SharedValue::Struct { fields: map, .. } => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Looks up an element of an array. Returns `None` if there is no such
+    /// element or the value is some other kind.
+    pub fn get_index(&self, index: usize) -> Option<&SharedValue> {
+        match self {
+            SharedValue::Array(values) => values.get(index),
+            _ => None,
+        }
+    }
+
+    /// The value, if it has no children.
+    pub fn as_scalar(&self) -> Option<&Value> {
+        match self {
+            SharedValue::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The entries of an object or struct, copied first if shared.
+    pub fn as_object_mut(&mut self) -> Option<&mut BTreeMap<String, SharedValue>> {
+        match self {
+            SharedValue::Object(map) | SharedValue::Struct { fields: map, .. } => {
+                Some(Arc::make_mut(map))
+            }
+            _ => None,
+        }
+    }
+
+    /// The elements of an array, copied first if shared.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<SharedValue>> {
+        match self {
+            SharedValue::Array(values) => Some(Arc::make_mut(values)),
+            _ => None,
+        }
+    }
+
+    /// The value, if it has no children, copied first if shared.
+    pub fn as_scalar_mut(&mut self) -> Option<&mut Value> {
+        match self {
+            SharedValue::Scalar(value) => Some(Arc::make_mut(value)),
+            _ => None,
+        }
+    }
+
+    /// Whether `self` and `other` are the same shared node, rather than
+    /// merely equal.
+    pub fn ptr_eq(&self, other: &SharedValue) -> bool {
+        match (self, other) {
+            (SharedValue::Object(a), SharedValue::Object(b)) => Arc::ptr_eq(a, b),
+            (SharedValue::Struct { fields: a, .. }, SharedValue::Struct { fields: b, .. }) => {
+                Arc::ptr_eq(a, b)
+            }
+            (SharedValue::Array(a), SharedValue::Array(b)) => Arc::ptr_eq(a, b),
+            (SharedValue::Scalar(a), SharedValue::Scalar(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+fn unwrap_or_clone<T: Clone>(shared: Arc<T>) -> T {
+    Arc::try_unwrap(shared).unwrap_or_else(|shared| (*shared).clone())
+}
@@ -0,0 +1,483 @@
+//! Finds where values end in a stream of bytes without parsing them, so that
+//! each value can be handed to the parser once all of it has arrived.
+
+use std::{collections::VecDeque, ops::Range};
+
+/// Which values the splitter looks for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Split {
+    /// Whitespace separated top-level values.
+    Values,
+    /// The elements of a single top-level array.
+    ArrayElements,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Lexical {
+    Code,
+    Str {
+        delimiter: u8,
+        escaped: bool,
+    },
+    /// Seen a `%` that may start a percent array.
+    PercentSigil,
+    /// Seen `%w` or `%i`, the delimiter comes next.
+    PercentKind,
+    Percent {
+        open: u8,
+        close: u8,
+        nesting: usize,
+        escaped: bool,
+    },
+    /// In a `#` comment, up to the end of the line.
+    Comment,
+    /// Seen the `?` of a character literal such as `?a` or `?\n`.
+    Char {
+        escaped: bool,
+    },
+    /// A top-level value just closed at this offset, and may yet be followed
+    /// by calls such as `.freeze`.
+    Closed(usize),
+    /// In the bodies of the heredocs opened on the line before.
+    Heredoc,
+}
+
+/// The line that ends a heredoc's body.
+#[derive(Debug)]
+struct Terminator {
+    id: Vec<u8>,
+    /// Whether the terminator may be indented, as with `<<~` and `<<-`.
+    indented: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Event {
+    /// A complete value spans this range of the buffer.
+    Value(Range<usize>),
+    /// The array being split was closed.
+    End,
+    /// The byte at this offset can't appear here, or the input ended early
+    /// if the offset is the end of the buffer.
+    Unexpected(usize),
+}
+
+#[derive(Debug)]
+pub(crate) struct Splitter {
+    split: Split,
+    lexical: Lexical,
+    /// The closing bracket of each bracket the scan is inside, innermost
+    /// last. Inspected objects such as `#<User id: 1>` close with `>`.
+    brackets: Vec<u8>,
+    /// Where the value being scanned starts.
+    start: Option<usize>,
+    /// How far the buffer has been scanned.
+    pos: usize,
+    /// The last byte scanned, which tells a `?` ending a name such as
+    /// `:empty?` from one starting a character literal.
+    last: u8,
+    /// Whether `#` starts a comment.
+    comments: bool,
+    /// The heredocs opened on the current line, whose bodies follow it.
+    heredocs: VecDeque<Terminator>,
+    /// Whether the current value ended on a heredoc's opening line, and is
+    /// reported once the bodies have been scanned.
+    deferred: bool,
+    /// Whether the array was closed on a heredoc's opening line, which is
+    /// reported after the deferred value.
+    closes: bool,
+    /// Whether the opening `[` has been seen when splitting an array.
+    opened: bool,
+    /// Whether the closing `]` has been seen when splitting an array.
+    closed: bool,
+}
+
+impl Splitter {
+    pub(crate) fn new(split: Split) -> Self {
+        Splitter {
+            split,
+            lexical: Lexical::Code,
+            brackets: Vec::new(),
+            start: None,
+            pos: 0,
+            last: b' ',
+            comments: true,
+            heredocs: VecDeque::new(),
+            deferred: false,
+            closes: false,
+            opened: false,
+            closed: false,
+        }
+    }
+
+    /// Sets whether `#` starts a comment, as it does by default.
+    pub(crate) fn set_comments(&mut self, comments: bool) {
+        self.comments = comments;
+    }
+
+    /// Scans `buffer` from where the last call stopped and returns the next
+    /// event, or `None` if more input is needed. `eof` says that `buffer`
+    /// holds the rest of the input, so that nothing is left waiting on bytes
+    /// that won't come.
+    ///
+    /// A `,` or `]` that ends an array element on the line opening a heredoc
+    /// is overwritten with a space, so that the element can be reported with
+    /// the heredoc's body that follows the line.
+    pub(crate) fn next(&mut self, buffer: &mut [u8], eof: bool) -> Option<Event> {
+        if self.closes && !self.deferred {
+            self.closes = false;
+            self.closed = true;
+            return Some(Event::End);
+        }
+
+        while self.pos < buffer.len() {
+            let at = self.pos;
+            let byte = buffer[at];
+
+            match self.lexical {
+                Lexical::Heredoc => {
+                    if let Some(event) = self.heredoc_line(buffer, eof)? {
+                        return Some(event);
+                    }
+                    continue;
+                }
+                Lexical::Code if self.incomplete(&buffer[at..], eof) => return None,
+                _ => {}
+            }
+            self.pos += 1;
+            let last = std::mem::replace(&mut self.last, byte);
+
+            match self.lexical {
+                Lexical::Code => {}
+                Lexical::Str { delimiter, escaped } => {
+                    if escaped {
+                        self.lexical = Lexical::Str {
+                            delimiter,
+                            escaped: false,
+                        };
+                    } else if byte == b'\\' {
+                        self.lexical = Lexical::Str {
+                            delimiter,
+                            escaped: true,
+                        };
+                    } else if byte == delimiter {
+                        self.lexical = Lexical::Code;
+                        self.closed_at(at);
+                    }
+                    continue;
+                }
+                Lexical::PercentSigil => {
+                    self.lexical = Lexical::Code;
+                    if byte == b'w' || byte == b'i' {
+                        self.lexical = Lexical::PercentKind;
+                        continue;
+                    }
+                }
+                Lexical::PercentKind => {
+                    self.lexical = Lexical::Code;
+                    if !byte.is_ascii_alphanumeric() && !is_space(byte) {
+                        let close = match byte {
+                            b'(' => b')',
+                            b'[' => b']',
+                            b'{' => b'}',
+                            b'<' => b'>',
+                            other => other,
+                        };
+                        self.lexical = Lexical::Percent {
+                            open: byte,
+                            close,
+                            nesting: 0,
+                            escaped: false,
+                        };
+                        continue;
+                    }
+                }
+                Lexical::Percent {
+                    open,
+                    close,
+                    nesting,
+                    escaped,
+                } => {
+                    let (nesting, escaped) = if escaped {
+                        (nesting, false)
+                    } else if byte == b'\\' {
+                        (nesting, true)
+                    } else if byte == close && nesting == 0 {
+                        self.lexical = Lexical::Code;
+                        self.closed_at(at);
+                        continue;
+                    } else if byte == close {
+                        (nesting - 1, false)
+                    } else if byte == open {
+                        (nesting + 1, false)
+                    } else {
+                        (nesting, false)
+                    };
+                    self.lexical = Lexical::Percent {
+                        open,
+                        close,
+                        nesting,
+                        escaped,
+                    };
+                    continue;
+                }
+                // The newline ending a comment may also start heredoc bodies.
+                Lexical::Comment if byte == b'\n' => self.lexical = Lexical::Code,
+                Lexical::Comment => continue,
+                Lexical::Char { escaped } => {
+                    self.lexical = match byte {
+                        b'\\' if !escaped => Lexical::Char { escaped: true },
+                        _ => Lexical::Code,
+                    };
+                    continue;
+                }
+                Lexical::Closed(end) => {
+                    self.lexical = Lexical::Code;
+                    if byte != b'.' {
+                        // Look at this byte again once the value is reported.
+                        self.pos = at;
+                        self.last = last;
+                        return self.start.take().map(|start| Event::Value(start..end + 1));
+                    }
+                }
+                Lexical::Heredoc => unreachable!("heredoc bodies are scanned by line"),
+            }
+
+            if let Some(event) = self.code(buffer, at, last) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// Reports what remains once the input has ended.
+    pub(crate) fn finish(&mut self, len: usize) -> Option<Event> {
+        match self.split {
+            Split::Values => self.start.take().map(|start| Event::Value(start..len)),
+            Split::ArrayElements if self.closed => None,
+            Split::ArrayElements if !self.opened && self.start.is_none() => None,
+            Split::ArrayElements => {
+                self.closed = true;
+                Some(Event::Unexpected(len))
+            }
+        }
+    }
+
+    /// Forgets the first `len` bytes of the buffer, which the caller is about
+    /// to drop. Nothing before `len` may still be needed.
+    pub(crate) fn consume(&mut self, len: usize) {
+        self.pos -= len;
+        self.start = self.start.map(|start| start - len);
+    }
+
+    /// Whether the token starting `input` can't be told apart until more of
+    /// it has arrived.
+    fn incomplete(&self, input: &[u8], eof: bool) -> bool {
+        !eof && match input {
+            [b'#'] => true,
+            [b'<', ..] => heredoc_opener(input).is_err(),
+            _ => false,
+        }
+    }
+
+    fn code(&mut self, buffer: &mut [u8], at: usize, last: u8) -> Option<Event> {
+        let byte = buffer[at];
+        if self.split == Split::ArrayElements && (!self.opened || self.closed) {
+            return match byte {
+                _ if is_space(byte) => None,
+                b'[' if !self.opened => {
+                    self.opened = true;
+                    None
+                }
+                _ => Some(Event::Unexpected(at)),
+            };
+        }
+
+        if self.brackets.is_empty() {
+            // The end of a value that opened heredocs waits for their bodies.
+            let pending = !self.heredocs.is_empty();
+            match (self.split, byte) {
+                (Split::Values, _) if is_space(byte) && pending => self.deferred = true,
+                (Split::Values, _) if is_space(byte) => {
+                    return self.start.take().map(|start| Event::Value(start..at));
+                }
+                (Split::ArrayElements, b',' | b']') if pending => {
+                    buffer[at] = b' ';
+                    self.deferred = true;
+                    self.closes |= byte == b']';
+                    return None;
+                }
+                (Split::ArrayElements, b',') => {
+                    return Some(match self.start.take() {
+                        Some(start) => Event::Value(start..at),
+                        None => Event::Unexpected(at),
+                    });
+                }
+                (Split::ArrayElements, b']') => {
+                    return Some(match self.start.take() {
+                        Some(start) => {
+                            // Report the closing bracket on the next call.
+                            self.pos = at;
+                            Event::Value(start..at)
+                        }
+                        None => {
+                            self.closed = true;
+                            Event::End
+                        }
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if byte == b'\n' && !self.heredocs.is_empty() {
+            self.lexical = Lexical::Heredoc;
+            return None;
+        }
+        if is_space(byte) {
+            return None;
+        }
+        if byte == b'#' && buffer.get(at + 1) != Some(&b'<') && self.comments {
+            self.lexical = Lexical::Comment;
+            return None;
+        }
+        if self.start.is_none() {
+            self.start = Some(at);
+        }
+
+        match byte {
+            b'"' | b'\'' => {
+                self.lexical = Lexical::Str {
+                    delimiter: byte,
+                    escaped: false,
+                }
+            }
+            b'%' => self.lexical = Lexical::PercentSigil,
+            b'?' if !is_word(last) => self.lexical = Lexical::Char { escaped: false },
+            b'#' if buffer.get(at + 1) == Some(&b'<') => {
+                self.pos = at + 2;
+                self.last = b'<';
+                self.brackets.push(b'>');
+            }
+            b'<' => {
+                if let Ok(Some((len, terminator))) = heredoc_opener(&buffer[at..]) {
+                    self.pos = at + len;
+                    self.last = buffer[at + len - 1];
+                    self.heredocs.push_back(terminator);
+                }
+            }
+            b'(' => self.brackets.push(b')'),
+            b'[' => self.brackets.push(b']'),
+            b'{' => self.brackets.push(b'}'),
+            b')' | b']' | b'}' => {
+                self.brackets.pop();
+                self.closed_at(at);
+            }
+            b'>' if self.brackets.last() == Some(&b'>') => {
+                self.brackets.pop();
+                self.closed_at(at);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Scans the heredoc body line at `pos`. Returns `None` if the line
+    /// hasn't all arrived, and the deferred value once the last body ends.
+    fn heredoc_line(&mut self, buffer: &[u8], eof: bool) -> Option<Option<Event>> {
+        let at = self.pos;
+        let end = match buffer[at..].iter().position(|&byte| byte == b'\n') {
+            Some(index) => at + index,
+            None if eof => buffer.len(),
+            None => return None,
+        };
+        self.pos = (end + 1).min(buffer.len());
+        self.last = b'\n';
+
+        let terminator = &self.heredocs[0];
+        let mut line = &buffer[at..end];
+        if let [rest @ .., b'\r'] = line {
+            line = rest;
+        }
+        if terminator.indented {
+            while let [b' ' | b'\t', rest @ ..] = line {
+                line = rest;
+            }
+        }
+        if line != terminator.id.as_slice() {
+            return Some(None);
+        }
+
+        self.heredocs.pop_front();
+        if !self.heredocs.is_empty() {
+            return Some(None);
+        }
+        self.lexical = Lexical::Code;
+        if !self.deferred {
+            return Some(None);
+        }
+        self.deferred = false;
+        Some(self.start.take().map(|start| Event::Value(start..self.pos)))
+    }
+
+    /// Called when a string or bracket closes at `at`; at the top level this
+    /// completes the current value, unless calls such as `.freeze` follow.
+    fn closed_at(&mut self, at: usize) {
+        if self.split == Split::Values && self.brackets.is_empty() && self.heredocs.is_empty() {
+            self.lexical = Lexical::Closed(at);
+        }
+    }
+}
+
+/// Reads the heredoc opener such as `<<~EOS` or `<<-'EOS'` starting `input`,
+/// returning its length and terminator, or `Err` if the input ends too soon
+/// to tell.
+fn heredoc_opener(input: &[u8]) -> Result<Option<(usize, Terminator)>, ()> {
+    let mut pos = match input {
+        [b'<', b'<', ..] => 2,
+        [b'<'] => return Err(()),
+        _ => return Ok(None),
+    };
+    let indented = match input.get(pos).ok_or(())? {
+        b'~' | b'-' => {
+            pos += 1;
+            true
+        }
+        _ => false,
+    };
+    let quote = match input.get(pos).ok_or(())? {
+        quote @ (b'\'' | b'"') => {
+            pos += 1;
+            Some(*quote)
+        }
+        _ => None,
+    };
+    let id_start = pos;
+    match input.get(pos).ok_or(())? {
+        byte if byte.is_ascii_alphabetic() || *byte == b'_' => pos += 1,
+        _ => return Ok(None),
+    }
+    while is_identifier(*input.get(pos).ok_or(())?) {
+        pos += 1;
+    }
+    let id = input[id_start..pos].to_vec();
+    if let Some(quote) = quote {
+        if *input.get(pos).ok_or(())? != quote {
+            return Ok(None);
+        }
+        pos += 1;
+    }
+    Ok(Some((pos, Terminator { id, indented })))
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn is_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Whether `byte` can end a name, so that a `?` after it ends the name too.
+fn is_word(byte: u8) -> bool {
+    is_identifier(byte) || byte >= 0x80
+}
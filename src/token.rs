@@ -0,0 +1,124 @@
+//! A tokenizer for the same syntax as the parser, for callers that want to
+//! drive their own logic without building a [`Value`](crate::Value) tree.
+
+use std::ops::Range;
+
+use crate::{
+    parser::{lex_token, Heredoc},
+    ParseError,
+};
+
+/// One token of the input, with the byte range it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// The kinds of [`Token`]. Literals hold their decoded value, so a string
+/// token holds the string with its escapes decoded, and a symbol token the
+/// name without its colon.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// `{`
+    OpenBrace,
+    /// `}`
+    CloseBrace,
+    /// `[`
+    OpenBracket,
+    /// `]`
+    CloseBracket,
+    /// `(`
+    OpenParen,
+    /// `)`
+    CloseParen,
+    /// `#<`, which opens an inspected object.
+    OpenInspect,
+    /// `>`, which closes an inspected object.
+    CloseInspect,
+    /// `,`
+    Comma,
+    /// `=>`
+    Arrow,
+    /// A `:` on its own, as after a quoted key in `"a b": 1`.
+    Colon,
+    /// `=`, as between the fields and values of `#<struct Point x=1>`.
+    Equals,
+    /// `.`, as before the method name in `"x".freeze`.
+    Dot,
+    /// `%`, as before the brace of an Elixir map.
+    Percent,
+    /// A bare key and its colon, such as `name:`, holding the name.
+    Label(String),
+    /// A string literal, a character literal such as `?a`, or a heredoc,
+    /// whose span is just its opener.
+    String(String),
+    Symbol(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Nil,
+    /// A constant path such as `ActiveSupport::TimeZone`.
+    Constant(String),
+    /// Any other bare word, such as `struct` or the method name of a call.
+    Identifier(String),
+    /// A `%w` array, holding its words.
+    WordArray(Vec<String>),
+    /// A `%i` array, holding its symbols' names.
+    SymbolArray(Vec<String>),
+}
+
+/// Splits `input` into tokens, skipping whitespace and comments, and stopping
+/// at the end of input or an `__END__` line.
+///
+/// Literals are read with the same rules as [`parse`](crate::parse), but
+/// nothing checks that the tokens fit together, so `] 1 {` tokenizes fine.
+/// The iterator ends after the first error.
+pub fn tokenize(input: &str) -> Tokens<'_> {
+    Tokens {
+        input,
+        rest: input,
+        heredocs: Vec::new(),
+        after_string: false,
+        done: false,
+    }
+}
+
+/// The iterator returned by [`tokenize`].
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    input: &'a str,
+    rest: &'a str,
+    /// The heredocs opened so far, whose bodies are skipped at the end of
+    /// their opening line.
+    heredocs: Vec<Heredoc>,
+    /// Whether the last token was a string, so that a `:` right after it is
+    /// a separator rather than the start of a symbol.
+    after_string: bool,
+    done: bool,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match lex_token(self.input, self.rest, &mut self.heredocs, self.after_string) {
+            Ok(Some((token, rest))) => {
+                self.rest = rest;
+                self.after_string = matches!(token.kind, TokenKind::String(_));
+                Some(Ok(token))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Bytes(String),
+    /// A string literal frozen with `.freeze`, as in `"x".freeze`. Strings
+    /// are only told apart this way when
+    /// [`ParseOptions::record_frozen`](crate::ParseOptions::record_frozen) is
+    /// set.
+    FrozenBytes(String),
+    /// A string with bytes that aren't valid UTF-8, parsed by
+    /// [`parse_slice`](crate::parse_slice) when
+    /// [`ParseOptions::invalid_utf8`](crate::ParseOptions::invalid_utf8) is
+    /// `Raw`.
+    RawBytes(Vec<u8>),
+    Symbol(String),
+    /// A constant reference such as `ActiveSupport::TimeZone`, holding the
+    /// full path.
+    Constant(String),
+    Integer(i64),
+    Float(f64),
+    /// An exact decimal number, which every numeric literal parses as when
+    /// [`ParseOptions::decimal_numbers`](crate::ParseOptions::decimal_numbers)
+    /// is set.
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+    /// A time as `Time#inspect` writes it, such as `2023-01-01 12:00:00 UTC`,
+    /// parsed when
+    /// [`ParseOptions::strict_timestamps`](crate::ParseOptions::strict_timestamps)
+    /// is set.
+    #[cfg(feature = "chrono")]
+    DateTime(chrono::DateTime<chrono::FixedOffset>),
+    /// A bare date such as `2023-01-01`, parsed when
+    /// [`ParseOptions::strict_timestamps`](crate::ParseOptions::strict_timestamps)
+    /// is set.
+    #[cfg(feature = "chrono")]
+    Date(chrono::NaiveDate),
+    Boolean(bool),
+    Object(BTreeMap<String, Value>),
+    /// An inspected object or struct, such as `#<User id: 1>`. The name is
+    /// empty for an anonymous struct.
+    Struct {
+        name: String,
+        fields: BTreeMap<String, Value>,
+    },
+    Array(Vec<Value>),
+    Null,
+    /// A `{...}` or `[...]` that Ruby writes in place of a hash or array
+    /// that contains itself, parsed when
+    /// [`ParseOptions::recursion_markers`](crate::ParseOptions::recursion_markers)
+    /// is set.
+    Recursive,
+}
+
+impl Value {
+    /// The key that a `nil => value` entry is stored under. See [`ObjectKey`]
+    /// for how keys are stored.
+    pub const NIL_KEY: &'static str = "\0nil";
+    /// The key that a `true => value` entry is stored under.
+    pub const TRUE_KEY: &'static str = "\0true";
+    /// The key that a `false => value` entry is stored under.
+    pub const FALSE_KEY: &'static str = "\0false";
+
+    /// A short, stable, lowercase name for the kind of value, for use in
+    /// messages such as "expected integer, found string".
+    pub fn type_name(&self) -> &'static str {
+        self.value_type().name()
+    }
+
+    /// Takes the value out, leaving `Value::Null` in its place.
+    pub fn take(&mut self) -> Value {
+        std::mem::replace(self, Value::Null)
+    }
+
+    /// Replaces the value with `new`, returning the previous value.
+    pub fn replace(&mut self, new: Value) -> Value {
+        std::mem::replace(self, new)
+    }
+
+    /// Iterates over the keys and values of an object, or the fields of a
+    /// struct, in key order. Any other value has no entries.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let map = match self {
+            Value::Object(map) | Value::Struct { fields: map, .. } => Some(map),
+            _ => None,
+        };
+        map.into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Like [`Value::entries`], but with mutable values.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = (&str, &mut Value)> {
+        let map = match self {
+            Value::Object(map) | Value::Struct { fields: map, .. } => Some(map),
+            _ => None,
+        };
+        map.into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Keeps only the array elements for which `f` returns `true`. Any other
+    /// value is left alone.
+    pub fn retain(&mut self, mut f: impl FnMut(&Value) -> bool) {
+        if let Value::Array(values) = self {
+            values.retain(|value| f(value));
+        }
+    }
+
+    /// Keeps only the entries of an object, or the fields of a struct, for
+    /// which `f` returns `true`. Any other value is left alone.
+    pub fn retain_entries(&mut self, mut f: impl FnMut(&str, &Value) -> bool) {
+        if let Value::Object(map) | Value::Struct { fields: map, .. } = self {
+            map.retain(|key, value| f(key, value));
+        }
+    }
+
+    /// Takes the map out of an object, or the fields out of a struct, without
+    /// copying them. Any other value is handed back as the error.
+    pub fn into_object(self) -> Result<BTreeMap<String, Value>, Value> {
+        match self {
+            Value::Object(map) | Value::Struct { fields: map, .. } => Ok(map),
+            other => Err(other),
+        }
+    }
+
+    /// Takes the elements out of an array without copying them. Any other
+    /// value is handed back as the error.
+    pub fn into_array(self) -> Result<Vec<Value>, Value> {
+        match self {
+            Value::Array(values) => Ok(values),
+            other => Err(other),
+        }
+    }
+
+    /// Moves the elements out of an array, in order. Any other value has no
+    /// elements.
+    pub fn into_array_iter(self) -> impl Iterator<Item = Value> {
+        self.into_array().unwrap_or_default().into_iter()
+    }
+
+    /// Moves the keys and values out of an object, or the fields out of a
+    /// struct, in key order. Any other value has no entries.
+    pub fn into_object_iter(self) -> impl Iterator<Item = (String, Value)> {
+        self.into_object().unwrap_or_default().into_iter()
+    }
+
+    // The accessors from here on return owned copies, for closures and
+    // iterator chains that can't hold on to a borrow of the value.
+
+    /// A copy of the string, if this is a string, frozen or not.
+    pub fn string(&self) -> Option<String> {
+        match self {
+            Value::Bytes(string) | Value::FrozenBytes(string) => Some(string.clone()),
+            _ => None,
+        }
+    }
+
+    /// A copy of the symbol's name, without the colon.
+    pub fn symbol(&self) -> Option<String> {
+        match self {
+            Value::Symbol(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is an integer.
+    pub fn integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// The float, if this is a float. Integers aren't converted; see
+    /// [`Value::coerce_to`] for that.
+    pub fn float(&self) -> Option<f64> {
+        match self {
+            Value::Float(float) => Some(*float),
+            _ => None,
+        }
+    }
+
+    /// Whether this is the float `-0.0`, which parses and renders with its
+    /// sign kept but compares equal to `0.0`.
+    pub fn is_negative_zero(&self) -> bool {
+        matches!(self, Value::Float(float) if *float == 0.0 && float.is_sign_negative())
+    }
+
+    /// The decimal, if this is a decimal.
+    #[cfg(feature = "decimal")]
+    pub fn decimal(&self) -> Option<rust_decimal::Decimal> {
+        match self {
+            Value::Decimal(decimal) => Some(*decimal),
+            _ => None,
+        }
+    }
+
+    /// The boolean, if this is `true` or `false`.
+    pub fn boolean(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    /// A copy of the elements, if this is an array.
+    pub fn array(&self) -> Option<Vec<Value>> {
+        match self {
+            Value::Array(values) => Some(values.clone()),
+            _ => None,
+        }
+    }
+
+    /// A copy of the entries of an object, or the fields of a struct.
+    pub fn object(&self) -> Option<BTreeMap<String, Value>> {
+        match self {
+            Value::Object(map) | Value::Struct { fields: map, .. } => Some(map.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// What a key of an object stands for. Keys are stored as text: a symbol key
+/// `:name =>` as `":name"`, a string key as its text, and `nil`, `true` and
+/// `false` keys as [`Value::NIL_KEY`], [`Value::TRUE_KEY`] and
+/// [`Value::FALSE_KEY`], which start with a NUL. A string that itself starts
+/// with `:` or a NUL is stored with a NUL in front, so that `":a" =>` and
+/// `:a =>` are different keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKey<'k> {
+    String(&'k str),
+    /// A symbol, by its name without the colon.
+    Symbol(&'k str),
+    Nil,
+    Boolean(bool),
+}
+
+impl<'k> ObjectKey<'k> {
+    /// Reads a key as it is stored. A lone `":"` is the empty symbol `:""`,
+    /// and anything else starting with a NUL that isn't one of the forms
+    /// above is a string as it is.
+    pub fn of(key: &'k str) -> Self {
+        match key {
+            Value::NIL_KEY => return ObjectKey::Nil,
+            Value::TRUE_KEY => return ObjectKey::Boolean(true),
+            Value::FALSE_KEY => return ObjectKey::Boolean(false),
+            _ => {}
+        }
+        match key.strip_prefix('\0') {
+            Some(text) if text.starts_with([':', '\0']) => ObjectKey::String(text),
+            Some(_) => ObjectKey::String(key),
+            None => match key.strip_prefix(':') {
+                Some(name) => ObjectKey::Symbol(name),
+                None => ObjectKey::String(key),
+            },
+        }
+    }
+
+    /// The text the key is stored as.
+    pub fn stored(self) -> Cow<'k, str> {
+        match self {
+            ObjectKey::String(text) if text.starts_with([':', '\0']) => {
+                Cow::Owned(format!("\0{}", text))
+            }
+            ObjectKey::String(text) => Cow::Borrowed(text),
+            ObjectKey::Symbol(name) => Cow::Owned(format!(":{}", name)),
+            ObjectKey::Nil => Cow::Borrowed(Value::NIL_KEY),
+            ObjectKey::Boolean(true) => Cow::Borrowed(Value::TRUE_KEY),
+            ObjectKey::Boolean(false) => Cow::Borrowed(Value::FALSE_KEY),
+        }
+    }
+
+    /// The key as plain text, as Ruby's `to_s` gives it: a symbol's name
+    /// without the colon, `""` for `nil`, and `"true"` or `"false"`.
+    pub fn text(self) -> &'k str {
+        match self {
+            ObjectKey::String(text) | ObjectKey::Symbol(text) => text,
+            ObjectKey::Nil => "",
+            ObjectKey::Boolean(true) => "true",
+            ObjectKey::Boolean(false) => "false",
+        }
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::Integer(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Bytes(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Bytes(value.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Value::Object(value)
+    }
+}
+
+/// `None` becomes `Value::Null`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
@@ -0,0 +1,554 @@
+use std::{
+    collections::{btree_map, hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    mem,
+};
+
+use crate::{parser::merge_values, shape::push_segment, DuplicateKeys, Value};
+
+/// An error from [`Value::rename_keys`], for two keys of an object that are
+/// renamed to the same key when that isn't allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameKeysError {
+    /// The key they both end up as.
+    pub key: String,
+}
+
+impl fmt::Display for RenameKeysError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key {} after renaming", self.key)
+    }
+}
+
+impl std::error::Error for RenameKeysError {}
+
+impl Value {
+    /// Finds every object entry, at any depth, whose key matches `pred`, such
+    /// as every `id` or `*_id` field. Returns each value with the JSON pointer
+    /// to it, depth first and in key order, including entries inside arrays
+    /// and structs.
+    pub fn find_keys(&self, pred: impl Fn(&str) -> bool) -> Vec<(String, &Value)> {
+        let mut found = Vec::new();
+        // Each entry to visit, with whether its key matched.
+        let mut stack = vec![(String::new(), self, false)];
+        while let Some((path, value, matched)) = stack.pop() {
+            let len = stack.len();
+            match value {
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    for (key, value) in map {
+                        let mut path = path.clone();
+                        push_segment(&mut path, key);
+                        stack.push((path, value, pred(key)));
+                    }
+                }
+                Value::Array(values) => {
+                    for (index, value) in values.iter().enumerate() {
+                        let mut path = path.clone();
+                        push_segment(&mut path, &index.to_string());
+                        stack.push((path, value, false));
+                    }
+                }
+                _ => {}
+            }
+            stack[len..].reverse();
+
+            if matched {
+                found.push((path, value));
+            }
+        }
+        found
+    }
+
+    /// Gathers every string in the tree, frozen or not, with the JSON pointer
+    /// to it, as for extracting the text to translate from a config. With
+    /// `include_symbols`, symbols are gathered too, by their names. Strings
+    /// come depth first and in key order, as for [`Value::find_keys`], and
+    /// object keys themselves aren't gathered.
+    pub fn collect_strings(&self, include_symbols: bool) -> Vec<(String, &str)> {
+        let mut found = Vec::new();
+        let mut stack = vec![(String::new(), self)];
+        while let Some((path, value)) = stack.pop() {
+            let len = stack.len();
+            match value {
+                Value::Bytes(string) | Value::FrozenBytes(string) => found.push((path, &**string)),
+                Value::Symbol(name) if include_symbols => found.push((path, &**name)),
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    for (key, value) in map {
+                        let mut path = path.clone();
+                        push_segment(&mut path, key);
+                        stack.push((path, value));
+                    }
+                }
+                Value::Array(values) => {
+                    for (index, value) in values.iter().enumerate() {
+                        let mut path = path.clone();
+                        push_segment(&mut path, &index.to_string());
+                        stack.push((path, value));
+                    }
+                }
+                _ => {}
+            }
+            stack[len..].reverse();
+        }
+        found
+    }
+
+    /// Calls `f` on every value in the tree, parents before their children
+    /// and siblings in order. Children are visited as they are after `f` has
+    /// run on their parent, so `f` may replace a value with new children and
+    /// those are visited too.
+    ///
+    /// The tree is walked with an explicit stack rather than recursion, so
+    /// deeply nested values can't overflow the call stack.
+    pub fn walk_mut(&mut self, f: &mut impl FnMut(&mut Value)) {
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            f(value);
+            match value {
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    stack.extend(map.values_mut().rev())
+                }
+                Value::Array(values) => stack.extend(values.iter_mut().rev()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Renames the keys of every object in the tree that `f` gives a new
+    /// name for, as for converting between `snake_case` and `camelCase`, and
+    /// leaves the keys it returns `None` for as they are. Arrays are walked
+    /// into, but struct fields keep their names.
+    ///
+    /// Keys that end up the same are resolved as `on_duplicate_key` says, as
+    /// when parsing, taking them in the old key order: `Last` keeps the value
+    /// of the last of them, as Ruby's `transform_keys` does. With
+    /// [`DuplicateKeys::Error`], the first object with such keys is left as
+    /// it was and an error names the key, and objects outside it keep their
+    /// new keys.
+    pub fn rename_keys(
+        &mut self,
+        on_duplicate_key: DuplicateKeys,
+        mut f: impl FnMut(&str) -> Option<String>,
+    ) -> Result<(), RenameKeysError> {
+        let mut duplicate = None;
+        self.walk_mut(&mut |value| {
+            let map = match value {
+                Value::Object(map) if duplicate.is_none() => map,
+                _ => return,
+            };
+            let keys: Vec<String> = map
+                .keys()
+                .map(|key| f(key).unwrap_or_else(|| key.clone()))
+                .collect();
+            if on_duplicate_key == DuplicateKeys::Error {
+                let mut seen = BTreeSet::new();
+                if let Some(key) = keys.iter().find(|key| !seen.insert(*key)) {
+                    duplicate = Some(key.clone());
+                    return;
+                }
+            }
+
+            let mut renamed = BTreeMap::new();
+            for (key, value) in keys.into_iter().zip(mem::take(map).into_values()) {
+                let existing = match renamed.entry(key) {
+                    btree_map::Entry::Vacant(entry) => {
+                        entry.insert(value);
+                        continue;
+                    }
+                    btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                };
+                match (on_duplicate_key, existing, value) {
+                    (DuplicateKeys::First | DuplicateKeys::Error, _, _) => {}
+                    (DuplicateKeys::Merge, existing, value) => merge_values(existing, value),
+                    (DuplicateKeys::MergeArrays, Value::Array(values), Value::Array(more)) => {
+                        values.extend(more)
+                    }
+                    (_, existing, value) => *existing = value,
+                }
+            }
+            *map = renamed;
+        });
+        match duplicate {
+            Some(key) => Err(RenameKeysError { key }),
+            None => Ok(()),
+        }
+    }
+
+    /// Sorts the keys of every object and struct in the tree by their bytes,
+    /// for canonical output. Maps are `BTreeMap`s, which always keep their
+    /// keys in this order, so this does nothing; it is here so that code
+    /// that needs sorted keys can say so rather than rely on how maps are
+    /// stored.
+    pub fn sort_keys(&mut self) {}
+
+    /// Folds `f` over every value in the tree, in the same order as
+    /// [`Value::walk_mut`], stopping at the first error. For example, all the
+    /// integers in a tree can be summed, failing on overflow, with
+    /// `value.try_fold(0i64, |sum, value| match value { Value::Integer(n) =>
+    /// sum.checked_add(*n).ok_or("overflow"), _ => Ok(sum) })`.
+    pub fn try_fold<B, E>(
+        &self,
+        init: B,
+        mut f: impl FnMut(B, &Value) -> Result<B, E>,
+    ) -> Result<B, E> {
+        let mut acc = init;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            acc = f(acc, value)?;
+            match value {
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    stack.extend(map.values().rev())
+                }
+                Value::Array(values) => stack.extend(values.iter().rev()),
+                _ => {}
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Like [`Value::walk_mut`], but calls `f` on children before their
+    /// parent, so that `f` sees each object or array with its contents
+    /// already transformed.
+    pub fn walk_mut_post(&mut self, f: &mut impl FnMut(&mut Value)) {
+        // Values with children are taken apart so that each child can be
+        // visited on its own, and put back together once all of them have
+        // been.
+        struct Frame {
+            parent: Value,
+            keys: Vec<String>,
+            pending: Vec<Value>,
+            done: Vec<Value>,
+        }
+
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut value = self.take();
+        loop {
+            let (keys, mut children) = match &mut value {
+                Value::Object(map) | Value::Struct { fields: map, .. } if !map.is_empty() => {
+                    std::mem::take(map).into_iter().unzip()
+                }
+                Value::Array(values) if !values.is_empty() => (Vec::new(), std::mem::take(values)),
+                _ => (Vec::new(), Vec::new()),
+            };
+            children.reverse();
+            if let Some(first) = children.pop() {
+                stack.push(Frame {
+                    parent: std::mem::replace(&mut value, first),
+                    keys,
+                    done: Vec::with_capacity(children.len() + 1),
+                    pending: children,
+                });
+                continue;
+            }
+
+            // The value has no children left to visit, so finish it and every
+            // parent whose last child it was.
+            f(&mut value);
+            loop {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => {
+                        *self = value;
+                        return;
+                    }
+                };
+                frame.done.push(value);
+                if let Some(child) = frame.pending.pop() {
+                    value = child;
+                    break;
+                }
+                let frame = stack.pop().unwrap_or_else(|| unreachable!());
+                value = reassemble(frame.parent, frame.keys, frame.done);
+                f(&mut value);
+            }
+        }
+    }
+
+    /// Turns every float in the tree with no fractional part, such as `3.0`,
+    /// into an integer, for systems that tell integers and floats apart.
+    /// Floats such as `3.5`, infinities, NaN and whole floats beyond the
+    /// range of an `i64` are left alone. `-0.0` becomes `0`.
+    pub fn normalize_numbers(&mut self) {
+        // 2^63, the first float past `i64::MAX`.
+        const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+        self.walk_mut(&mut |value| {
+            if let Value::Float(float) = *value {
+                if float.fract() == 0.0 && (-LIMIT..LIMIT).contains(&float) {
+                    *value = Value::Integer(float as i64);
+                }
+            }
+        });
+    }
+
+    /// The number of values in the tree, counting this one, every element
+    /// and every object or struct field at any depth.
+    pub fn count_nodes(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            count += 1;
+            match value {
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    stack.extend(map.values())
+                }
+                Value::Array(values) => stack.extend(values),
+                _ => {}
+            }
+        }
+        count
+    }
+
+    /// A rough count of the bytes the tree has allocated on the heap, for
+    /// sizing caches of parsed values. This counts the capacity of every
+    /// string, including object keys and struct names, the capacity of every
+    /// array, and for each object entry the size of its key and value. It
+    /// leaves out the `Value` itself, which is wherever the caller put it,
+    /// the B-tree's own node headers and unused slots, and allocator
+    /// overhead, so the real figure is somewhat higher.
+    pub fn memory_estimate(&self) -> usize {
+        let entry = std::mem::size_of::<(String, Value)>();
+        let mut bytes = 0;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Bytes(string)
+                | Value::FrozenBytes(string)
+                | Value::Symbol(string)
+                | Value::Constant(string) => bytes += string.capacity(),
+                Value::RawBytes(raw) => bytes += raw.capacity(),
+                Value::Object(map) | Value::Struct { fields: map, .. } => {
+                    if let Value::Struct { name, .. } = value {
+                        bytes += name.capacity();
+                    }
+                    for (key, value) in map {
+                        bytes += entry + key.capacity();
+                        stack.push(value);
+                    }
+                }
+                Value::Array(values) => {
+                    bytes += values.capacity() * std::mem::size_of::<Value>();
+                    stack.extend(values);
+                }
+                _ => {}
+            }
+        }
+        bytes
+    }
+
+    /// Fills in the keys of `defaults` that this object is missing, with
+    /// copies of their values, as when loading user config over a template.
+    /// Where both have an object under the same key, that object is filled in
+    /// the same way, at any depth. Keys that are already here keep their
+    /// values, and arrays are never merged. Does nothing unless both this and
+    /// `defaults` are objects.
+    pub fn apply_defaults(&mut self, defaults: &Value) {
+        let mut stack = match (self, defaults) {
+            (Value::Object(map), Value::Object(defaults)) => vec![(map, defaults)],
+            _ => return,
+        };
+        while let Some((map, defaults)) = stack.pop() {
+            for (key, default) in defaults {
+                if !map.contains_key(key) {
+                    map.insert(key.clone(), default.clone());
+                }
+            }
+            for (key, value) in map.iter_mut() {
+                if let (Value::Object(map), Some(Value::Object(defaults))) =
+                    (value, defaults.get(key))
+                {
+                    stack.push((map, defaults));
+                }
+            }
+        }
+    }
+
+    /// Whether everything in this value is also in `other`, for checking an
+    /// expected value against an actual one that may have more in it. Each
+    /// key of an object or struct has to be in `other` with a value that
+    /// this one's value is in turn a subset of, and extra keys in `other`
+    /// are ignored. Arrays are compared by position: each element has to be
+    /// a subset of the element at the same index in `other`, which may have
+    /// more elements after them. Structs also need the same name, and
+    /// anything else has to be equal.
+    pub fn is_subset_of(&self, other: &Value) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((value, other)) = stack.pop() {
+            match (value, other) {
+                (Value::Object(map), Value::Object(other_map))
+                | (
+                    Value::Struct { fields: map, .. },
+                    Value::Struct {
+                        fields: other_map, ..
+                    },
+                ) if struct_name(value) == struct_name(other) => {
+                    for (key, value) in map {
+                        match other_map.get(key) {
+                            Some(other) => stack.push((value, other)),
+                            None => return false,
+                        }
+                    }
+                }
+                (Value::Array(values), Value::Array(other)) if values.len() <= other.len() => {
+                    stack.extend(values.iter().zip(other))
+                }
+                _ if value == other => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Whether this value equals `other`, except that floats only have to be
+    /// within `epsilon` of each other, as for comparing computed numbers in
+    /// tests. The tolerance is absolute, so `1.0000001` is approximately
+    /// `1.0` with an `epsilon` of `1e-6`, but large floats need a larger one.
+    /// An integer is compared with a float the same way, and with another
+    /// integer exactly. Objects need the same keys and arrays the same
+    /// length, with their values approximately equal, and anything else has
+    /// to be equal. A NaN is never approximately equal to anything.
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((value, other)) = stack.pop() {
+            let close = |a: f64, b: f64| a == b || (a - b).abs() <= epsilon;
+            match (value, other) {
+                (Value::Float(a), Value::Float(b)) if close(*a, *b) => {}
+                (Value::Float(a), Value::Integer(b)) if close(*a, *b as f64) => {}
+                (Value::Integer(a), Value::Float(b)) if close(*a as f64, *b) => {}
+                (Value::Object(map), Value::Object(other_map))
+                | (
+                    Value::Struct { fields: map, .. },
+                    Value::Struct {
+                        fields: other_map, ..
+                    },
+                ) if map.len() == other_map.len() && struct_name(value) == struct_name(other) => {
+                    for ((key, value), (other_key, other)) in map.iter().zip(other_map) {
+                        if key != other_key {
+                            return false;
+                        }
+                        stack.push((value, other));
+                    }
+                }
+                (Value::Array(values), Value::Array(other)) if values.len() == other.len() => {
+                    stack.extend(values.iter().zip(other))
+                }
+                _ if value == other => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Removes repeated elements from every array in the tree, keeping the
+    /// first of each in its place, so `[1, {a: [2, 2]}, 1, {a: [2]}]` becomes
+    /// `[1, {a: [2]}]`. Arrays inside arrays are deduplicated first, so that
+    /// elements which only differed by repeats count as the same. Objects are
+    /// walked into but keep all their entries. Elements are compared with
+    /// `==`, so `-0.0` repeats `0.0` and a NaN never repeats anything.
+    ///
+    /// Each array takes linear time, as elements are bucketed by a hash
+    /// before being compared.
+    pub fn dedup_arrays(&mut self) {
+        self.walk_mut_post(&mut |value| {
+            if let Value::Array(values) = value {
+                // The indices into `kept` of the elements with each hash.
+                let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+                let mut kept: Vec<Value> = Vec::with_capacity(values.len());
+                for value in mem::take(values) {
+                    let bucket = seen.entry(fingerprint(&value)).or_default();
+                    if bucket.iter().all(|&index| kept[index] != value) {
+                        bucket.push(kept.len());
+                        kept.push(value);
+                    }
+                }
+                *values = kept;
+            }
+        });
+    }
+
+    /// Removes object entries whose value is `nil`, and when `drop_empty` is
+    /// set, entries whose value is an empty string, array or object. This
+    /// works bottom up, so an object left empty by pruning is itself removed.
+    /// Array elements are never removed, so that positions are kept.
+    pub fn prune(&mut self, drop_empty: bool) {
+        let prunable = |value: &Value| match value {
+            Value::Null => true,
+            Value::Bytes(string) | Value::FrozenBytes(string) => drop_empty && string.is_empty(),
+            Value::RawBytes(bytes) => drop_empty && bytes.is_empty(),
+            Value::Array(values) => drop_empty && values.is_empty(),
+            Value::Object(map) => drop_empty && map.is_empty(),
+            _ => false,
+        };
+        self.walk_mut_post(&mut |value| {
+            if let Value::Object(map) | Value::Struct { fields: map, .. } = value {
+                map.retain(|_, value| !prunable(value));
+            }
+        });
+    }
+}
+
+/// Puts visited children back into the emptied `parent`, ready for `f`.
+fn reassemble(mut parent: Value, keys: Vec<String>, done: Vec<Value>) -> Value {
+    match &mut parent {
+        Value::Object(map) | Value::Struct { fields: map, .. } => {
+            *map = keys.into_iter().zip(done).collect::<BTreeMap<_, _>>();
+        }
+        Value::Array(values) => *values = done,
+        _ => {}
+    }
+    parent
+}
+
+/// The name of a struct, to tell apart structs with the same fields.
+fn struct_name(value: &Value) -> Option<&str> {
+    match value {
+        Value::Struct { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// A hash of `value` that agrees with `==`: values that are equal always
+/// have the same fingerprint.
+fn fingerprint(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        mem::discriminant(value).hash(&mut hasher);
+        match value {
+            Value::Bytes(string)
+            | Value::FrozenBytes(string)
+            | Value::Symbol(string)
+            | Value::Constant(string) => string.hash(&mut hasher),
+            Value::RawBytes(bytes) => bytes.hash(&mut hasher),
+            Value::Integer(integer) => integer.hash(&mut hasher),
+            // `-0.0 == 0.0`, so both hash as `0.0`.
+            Value::Float(float) => match *float == 0.0 {
+                true => 0.0f64.to_bits().hash(&mut hasher),
+                false => float.to_bits().hash(&mut hasher),
+            },
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => decimal.hash(&mut hasher),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(time) => time.hash(&mut hasher),
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => date.hash(&mut hasher),
+            Value::Boolean(boolean) => boolean.hash(&mut hasher),
+            Value::Object(map) | Value::Struct { fields: map, .. } => {
+                if let Value::Struct { name, .. } = value {
+                    name.hash(&mut hasher);
+                }
+                map.len().hash(&mut hasher);
+                for (key, value) in map {
+                    key.hash(&mut hasher);
+                    stack.push(value);
+                }
+            }
+            Value::Array(values) => {
+                values.len().hash(&mut hasher);
+                stack.extend(values);
+            }
+            Value::Null | Value::Recursive => {}
+        }
+    }
+    hasher.finish()
+}
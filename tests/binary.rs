@@ -0,0 +1,39 @@
+#![cfg(all(feature = "serde", feature = "bincode"))]
+
+use nompom::{parse, Value};
+
+#[test]
+fn complex_value_round_trips_through_bytes() {
+    let mut value = parse(
+        r#"{:a => [1, -2.5, "x\ny", :"b c", nil, true, Float::INFINITY],
+            "d" => {e: #<User id: 1, tags: [:x]>, nil => Foo::Bar},
+            false => {}, 1 => []}"#,
+    )
+    .unwrap();
+    if let Value::Object(map) = &mut value {
+        map.insert("frozen".to_string(), Value::FrozenBytes("f".to_string()));
+        map.insert("raw".to_string(), Value::RawBytes(vec![0xff, 0]));
+        map.insert("recursive".to_string(), Value::Recursive);
+    }
+    let bytes = value.to_bytes();
+    assert_eq!(Value::from_bytes(&bytes).unwrap(), value);
+    for end in 0..bytes.len() {
+        assert!(Value::from_bytes(&bytes[..end]).is_err());
+    }
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn decimals_round_trip_through_bytes() {
+    let value = Value::Array(vec![Value::Decimal("1.50".parse().unwrap())]);
+    assert_eq!(Value::from_bytes(&value.to_bytes()).unwrap(), value);
+}
+
+#[cfg(feature = "quickcheck")]
+#[test]
+fn bytes_round_trip() {
+    fn property(value: Value) -> bool {
+        Value::from_bytes(&value.to_bytes()).as_ref() == Ok(&value)
+    }
+    quickcheck::quickcheck(property as fn(Value) -> bool);
+}
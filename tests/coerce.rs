@@ -0,0 +1,88 @@
+use nompom::{parse, Value, ValueType};
+
+fn string(text: &str) -> Value {
+    Value::Bytes(text.to_string())
+}
+
+#[test]
+fn coercions() {
+    let coerce = |input: &str, ty| parse(input).unwrap().coerce_to(ty);
+    assert_eq!(
+        coerce(r#""42""#, ValueType::Integer),
+        Ok(Value::Integer(42))
+    );
+    assert_eq!(coerce(":42", ValueType::Integer), Ok(Value::Integer(42)));
+    assert_eq!(coerce(r#""1.5""#, ValueType::Float), Ok(Value::Float(1.5)));
+    assert_eq!(
+        coerce(r#""true""#, ValueType::Boolean),
+        Ok(Value::Boolean(true))
+    );
+    assert_eq!(
+        coerce(r#""x""#, ValueType::Symbol),
+        Ok(Value::Symbol("x".to_string()))
+    );
+    assert_eq!(coerce(":x", ValueType::Bytes), Ok(string("x")));
+    assert_eq!(coerce("Foo", ValueType::Bytes), Ok(string("Foo")));
+    assert_eq!(coerce("3", ValueType::Float), Ok(Value::Float(3.0)));
+    assert_eq!(coerce("3.0", ValueType::Integer), Ok(Value::Integer(3)));
+    assert_eq!(coerce("1.5", ValueType::Bytes), Ok(string("1.5")));
+    assert_eq!(coerce("true", ValueType::Bytes), Ok(string("true")));
+    assert_eq!(coerce("1", ValueType::Boolean), Ok(Value::Boolean(true)));
+    assert_eq!(coerce("0", ValueType::Boolean), Ok(Value::Boolean(false)));
+    assert_eq!(coerce("false", ValueType::Integer), Ok(Value::Integer(0)));
+    assert_eq!(coerce("true", ValueType::Float), Ok(Value::Float(1.0)));
+    assert_eq!(
+        coerce("#<A b: 1>", ValueType::Object),
+        Ok(parse(r#"{"b" => 1}"#).unwrap())
+    );
+    assert_eq!(coerce("[1]", ValueType::Array), Ok(parse("[1]").unwrap()));
+}
+
+#[test]
+fn impossible_coercions_fail() {
+    let error = |input: &str, ty| parse(input).unwrap().coerce_to(ty).unwrap_err().to_string();
+    assert_eq!(
+        error(r#""x""#, ValueType::Integer),
+        "cannot coerce string to integer"
+    );
+    assert_eq!(
+        error(r#""inf""#, ValueType::Float),
+        "cannot coerce string to float"
+    );
+    assert_eq!(
+        error("2", ValueType::Boolean),
+        "cannot coerce integer to boolean"
+    );
+    assert_eq!(
+        error("1.5", ValueType::Integer),
+        "cannot coerce float to integer"
+    );
+    assert_eq!(
+        error("1e300", ValueType::Integer),
+        "cannot coerce float to integer"
+    );
+    assert_eq!(
+        error("9007199254740993", ValueType::Float),
+        "cannot coerce integer to float"
+    );
+    assert_eq!(
+        error("nil", ValueType::Bytes),
+        "cannot coerce null to string"
+    );
+    assert_eq!(
+        error("[1]", ValueType::Object),
+        "cannot coerce array to object"
+    );
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn decimal_coercions() {
+    let coerce = |input: &str, ty| parse(input).unwrap().coerce_to(ty);
+    let decimal = coerce(r#""1.25""#, ValueType::Decimal).unwrap();
+    assert_eq!(decimal.to_string(), "1.25");
+    assert_eq!(decimal.coerce_to(ValueType::Bytes), Ok(string("1.25")));
+    assert!(decimal.coerce_to(ValueType::Integer).is_err());
+    let whole = coerce("7", ValueType::Decimal).unwrap();
+    assert_eq!(whole.coerce_to(ValueType::Integer), Ok(Value::Integer(7)));
+}
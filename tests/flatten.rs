@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use nompom::{parse, Value};
+
+#[test]
+fn flatten_and_unflatten() {
+    let value = parse("{a: {b: 1}, c: [2, 3], d: {}, e: [], :f => {g: nil}}").unwrap();
+    let flat = value.flatten(".");
+    let expected: BTreeMap<String, Value> = vec![
+        (":f.g", Value::Null),
+        ("a.b", Value::Integer(1)),
+        ("c.0", Value::Integer(2)),
+        ("c.1", Value::Integer(3)),
+        // Empty collections are kept, so that they come back.
+        ("d", parse("{}").unwrap()),
+        ("e", Value::Array(Vec::new())),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value))
+    .collect();
+    assert_eq!(flat, expected);
+    assert_eq!(Value::unflatten(flat, "."), value);
+
+    let scalar = Value::Integer(1).flatten("/");
+    assert_eq!(scalar.keys().collect::<Vec<_>>(), [""]);
+    assert_eq!(Value::unflatten(scalar, "/"), Value::Integer(1));
+}
+
+#[test]
+fn rack_style_query_strings() {
+    let query = |input| parse(input).unwrap().to_query_string();
+    assert_eq!(
+        query(r#"{a: {b: 1, c: [1, 2]}, d: "x y&z=", :e => nil, f: {}, g: [], h: [{i: 1}]}"#)
+            .unwrap(),
+        "e=&a[b]=1&a[c][]=1&a[c][]=2&d=x%20y%26z%3D&f=&g[]=&h[][i]=1"
+    );
+    assert_eq!(
+        query(r#"{"a b" => {"[c]" => 1}}"#).unwrap(),
+        "a%20b[%5Bc%5D]=1"
+    );
+    assert_eq!(query("{}").unwrap(), "");
+    assert_eq!(
+        query("[1]").unwrap_err().to_string(),
+        "cannot encode array as a query string"
+    );
+    assert_eq!(
+        query("1").unwrap_err().to_string(),
+        "cannot encode integer as a query string"
+    );
+}
+
+#[test]
+fn env_vars_from_a_nested_config() {
+    let config = parse(
+        r#"{db: {host: "x", port: 5432, :ssl => true}, ratio: 1.0, tags: [:a, "b"], none: nil, e: {}, "a-b" => Foo, a_b: 1, s: #<S x: 1>}"#,
+    )
+    .unwrap();
+    let vars = config.to_env_vars("APP");
+    let expected: BTreeMap<String, String> = vec![
+        // `a_b` comes after `a-b` in key order, so it wins.
+        ("APP_A_B", "1"),
+        ("APP_DB_HOST", "x"),
+        ("APP_DB_PORT", "5432"),
+        ("APP_DB_SSL", "true"),
+        ("APP_E", ""),
+        ("APP_NONE", ""),
+        ("APP_RATIO", "1.0"),
+        ("APP_S_X", "1"),
+        ("APP_TAGS_0", "a"),
+        ("APP_TAGS_1", "b"),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+    assert_eq!(vars, expected);
+    assert!(config.to_env_vars("").contains_key("DB_HOST"));
+    assert_eq!(
+        Value::Integer(1)
+            .to_env_vars("APP")
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [("APP".to_string(), "1".to_string())]
+    );
+}
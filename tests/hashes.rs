@@ -0,0 +1,735 @@
+use std::collections::BTreeMap;
+
+use nompom::{
+    parse, parse_lenient_with_diagnostics, parse_with_key_spans, parse_with_options, DuplicateKeys,
+    MergeRules, MergeStrategy, ParseOptions, Value,
+};
+
+#[test]
+fn empty_hash_and_array_at_top_level() {
+    assert_eq!(parse("{}").unwrap(), Value::Object(BTreeMap::new()));
+    assert_eq!(parse("[]").unwrap(), Value::Array(Vec::new()));
+    assert_eq!(parse(" {} ").unwrap(), Value::Object(BTreeMap::new()));
+    assert_eq!(parse("\n[]\n").unwrap(), Value::Array(Vec::new()));
+}
+
+fn object(entries: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn symbol(name: &str) -> Value {
+    Value::Symbol(name.to_string())
+}
+
+#[test]
+fn quoted_colon_keys_before_symbol_values() {
+    assert_eq!(
+        parse(r#"{ "a": :sym, "b": 2 }"#).unwrap(),
+        object(vec![("a", symbol("sym")), ("b", Value::Integer(2))])
+    );
+    assert_eq!(
+        parse(r#"{"a": :"b c", "d": :e}"#).unwrap(),
+        object(vec![("a", symbol("b c")), ("d", symbol("e"))])
+    );
+    // Without a space, the symbol's colon would be a doubled separator.
+    assert_eq!(
+        parse(r#"{"d"::e}"#).unwrap_err().to_string(),
+        "expected a value after ':', found ':' in map at offset 5"
+    );
+}
+
+#[test]
+fn colon_key_values_on_the_next_line() {
+    assert_eq!(
+        parse("{name:\n  \"value\"}").unwrap(),
+        object(vec![("name", Value::Bytes("value".to_string()))])
+    );
+    assert_eq!(
+        parse("{\n  \"name\":\n    \"value\",\n  count:\n\n    2\n}").unwrap(),
+        object(vec![
+            ("name", Value::Bytes("value".to_string())),
+            ("count", Value::Integer(2)),
+        ])
+    );
+}
+
+#[test]
+fn nil_true_and_false_keys() {
+    let value = parse(r#"{nil => 1, true => 2, false => 3, "nil" => 4}"#).unwrap();
+    assert_eq!(
+        value,
+        object(vec![
+            (Value::NIL_KEY, Value::Integer(1)),
+            (Value::TRUE_KEY, Value::Integer(2)),
+            (Value::FALSE_KEY, Value::Integer(3)),
+            ("nil", Value::Integer(4)),
+        ])
+    );
+    assert_eq!(
+        value.to_string(),
+        r#"{false => 3, nil => 1, true => 2, "nil" => 4}"#
+    );
+    assert_eq!(parse(&value.to_string()).unwrap(), value);
+}
+
+#[test]
+fn nil_key() {
+    let value = parse("{nil => 1}").unwrap();
+    assert_eq!(value, object(vec![(Value::NIL_KEY, Value::Integer(1))]));
+    assert_eq!(value.to_string(), "{nil => 1}");
+}
+
+#[test]
+fn true_key() {
+    let value = parse("{true => 1}").unwrap();
+    assert_eq!(value, object(vec![(Value::TRUE_KEY, Value::Integer(1))]));
+    assert_eq!(value.to_string(), "{true => 1}");
+}
+
+#[test]
+fn false_key() {
+    let value = parse("{false => 1}").unwrap();
+    assert_eq!(value, object(vec![(Value::FALSE_KEY, Value::Integer(1))]));
+    assert_eq!(value.to_string(), "{false => 1}");
+}
+
+#[test]
+fn string_keys_starting_with_nul_are_not_literal_keys() {
+    let value = parse(r#"{"\u0000nil" => 1, nil => 2, "\u0000true" => 3}"#).unwrap();
+    assert_eq!(value.entries().count(), 3);
+    assert_eq!(value.get(Value::NIL_KEY), Some(&Value::Integer(2)));
+    assert_eq!(parse(&value.to_string()).unwrap(), value);
+    assert!(!value.to_string().contains("true =>"));
+}
+
+/// Every kind of key and value in one hash, so that one alternative of the
+/// grammar shadowing another, such as a constant parser taking a number or a
+/// symbol taking `nil`, shows up as a wrong value here.
+#[test]
+fn mixed_keys_and_values() {
+    let input = r#"{
+        :sym => :nil,
+        "string" => "nil",
+        1 => 1,
+        -2 => -2.5,
+        1.5 => 1.5e3,
+        label: [1, 2.0, :true, nil],
+        "quoted": %w[a b],
+        :"spaced key" => %i[c d],
+        :nothing => nil,
+        :yes => true,
+        :no => false,
+        :constant => Foo::Bar,
+        :infinity => Infinity::Rank,
+        :float => -Float::INFINITY,
+        :hex => 0x1F,
+        :nested => {inner: {:deep => [Nil, True, {}]}},
+        nil => :true,
+    }"#;
+    let array = Value::Array;
+    let string = |text: &str| Value::Bytes(text.to_string());
+    assert_eq!(
+        parse(input).unwrap(),
+        object(vec![
+            (":sym", symbol("nil")),
+            ("string", string("nil")),
+            ("1", Value::Integer(1)),
+            ("-2", Value::Float(-2.5)),
+            ("1.5", Value::Float(1500.0)),
+            (
+                "label",
+                array(vec![
+                    Value::Integer(1),
+                    Value::Float(2.0),
+                    symbol("true"),
+                    Value::Null,
+                ])
+            ),
+            ("quoted", array(vec![string("a"), string("b")])),
+            (":spaced key", array(vec![symbol("c"), symbol("d")])),
+            (":nothing", Value::Null),
+            (":yes", Value::Boolean(true)),
+            (":no", Value::Boolean(false)),
+            (":constant", Value::Constant("Foo::Bar".to_string())),
+            (":infinity", Value::Constant("Infinity::Rank".to_string())),
+            (":float", Value::Float(f64::NEG_INFINITY)),
+            (":hex", Value::Integer(31)),
+            (
+                ":nested",
+                object(vec![(
+                    "inner",
+                    object(vec![(
+                        ":deep",
+                        array(vec![
+                            Value::Constant("Nil".to_string()),
+                            Value::Constant("True".to_string()),
+                            object(Vec::new()),
+                        ])
+                    )])
+                )])
+            ),
+            (Value::NIL_KEY, symbol("true")),
+        ])
+    );
+}
+
+/// A pretty-printed config with comments after values, after commas and on
+/// lines of their own, at every level of nesting.
+#[test]
+fn commented_multi_line_hash() {
+    let input = r#"# Service configuration
+{
+  # Identity
+  :name => "billing", # the service name
+  :version => 3,
+  # Network settings follow.
+  :server => {
+    host: "0.0.0.0", # listen everywhere
+    port: 8080,
+    # TLS is optional.
+    tls: nil
+  },
+  :workers => [ # one per queue
+    "invoices", # billed monthly
+    # "refunds", disabled
+    "payments",
+ # a comment at odd indentation
+  ],
+  "retries" => # on the next line
+    5,
+  :limits => {
+    # per minute
+    :requests => 600 ,
+    :burst =>
+      # comment between an arrow and the value
+      50,
+  },
+  :tags => %w[a b], # words
+  :empty => {
+    # nothing here yet
+  }, # trailing comma above
+} # the end
+"#;
+    let string = |text: &str| Value::Bytes(text.to_string());
+    assert_eq!(
+        parse(input).unwrap(),
+        object(vec![
+            (":name", string("billing")),
+            (":version", Value::Integer(3)),
+            (
+                ":server",
+                object(vec![
+                    ("host", string("0.0.0.0")),
+                    ("port", Value::Integer(8080)),
+                    ("tls", Value::Null),
+                ])
+            ),
+            (
+                ":workers",
+                Value::Array(vec![string("invoices"), string("payments")])
+            ),
+            ("retries", Value::Integer(5)),
+            (
+                ":limits",
+                object(vec![
+                    (":requests", Value::Integer(600)),
+                    (":burst", Value::Integer(50)),
+                ])
+            ),
+            (":tags", Value::Array(vec![string("a"), string("b")])),
+            (":empty", object(Vec::new())),
+        ])
+    );
+}
+
+#[test]
+fn bareword_arrow_keys() {
+    let options = ParseOptions {
+        bareword_arrow_keys: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with_options(r#"{ name => "x" }"#, &options).unwrap(),
+        object(vec![("name", Value::Bytes("x".to_string()))])
+    );
+    assert_eq!(
+        parse_with_options(r#"{ name => 1, :name => 2, nil => 3 }"#, &options).unwrap(),
+        object(vec![
+            ("name", Value::Integer(1)),
+            (":name", Value::Integer(2)),
+            (Value::NIL_KEY, Value::Integer(3)),
+        ])
+    );
+    assert_eq!(
+        parse(r#"{ name => "x" }"#).unwrap_err().to_string(),
+        "expected '}' in map at offset 2"
+    );
+}
+
+#[test]
+fn extra_key_chars() {
+    let options = ParseOptions {
+        extra_key_chars: vec!['-', '.'],
+        ..Default::default()
+    };
+    let text = Value::Bytes("text".to_string());
+    assert_eq!(
+        parse_with_options(r#"{ "content-type": "text" }"#, &options).unwrap(),
+        object(vec![("content-type", text.clone())])
+    );
+    assert_eq!(
+        parse_with_options(r#"{ content-type: "text", a.b: 1 }"#, &options).unwrap(),
+        object(vec![("a.b", Value::Integer(1)), ("content-type", text)])
+    );
+    // Symbol values and the default key characters stay strict.
+    assert!(parse_with_options("{a: :content-type}", &options).is_err());
+    assert_eq!(
+        parse(r#"{ content-type: "text" }"#)
+            .unwrap_err()
+            .to_string(),
+        "expected '}' in map at offset 2"
+    );
+}
+
+#[test]
+fn require_object_root() {
+    let options = ParseOptions {
+        require_object_root: true,
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options).map_err(|err| err.to_string());
+    assert_eq!(
+        parse("42"),
+        Err("expected a hash at the top level, found integer at offset 0".to_string())
+    );
+    assert_eq!(
+        parse("[{a: 1}]"),
+        Err("expected a hash at the top level, found array at offset 0".to_string())
+    );
+    assert_eq!(parse("{a: 1}"), Ok(object(vec![("a", Value::Integer(1))])));
+    assert_eq!(parse("# config\n{}"), Ok(object(Vec::new())));
+    assert_eq!(
+        parse_with_options("42", &ParseOptions::default()),
+        Ok(Value::Integer(42))
+    );
+}
+
+#[test]
+fn collection_length_limits() {
+    let options = ParseOptions {
+        max_array_len: Some(3),
+        max_object_keys: Some(3),
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options).map_err(|err| err.to_string());
+    let integers =
+        |values: &[i64]| Value::Array(values.iter().copied().map(Value::Integer).collect());
+    assert_eq!(parse("[1, 2, 3]"), Ok(integers(&[1, 2, 3])));
+    assert_eq!(
+        parse("[1, 2, 3, 4]"),
+        Err("array exceeds max_array_len of 3 at offset 10".to_string())
+    );
+    assert_eq!(
+        parse("{a: [[1, 2, 3, 4]]}"),
+        Err("array at /a/0 exceeds max_array_len of 3 at offset 15".to_string())
+    );
+    assert_eq!(
+        parse("%w[a b c d]"),
+        Err("array exceeds max_array_len of 3 at offset 0".to_string())
+    );
+    assert!(parse("%i[a b c]").is_ok());
+    assert!(parse("{a: 1, b: 2, c: 3}").is_ok());
+    assert_eq!(
+        parse("[{a: 1, b: 2, c: 3, d: 4}]"),
+        Err("hash at /0 exceeds max_object_keys of 3 at offset 20".to_string())
+    );
+}
+
+#[test]
+fn colon_key_separators() {
+    let message = |input| parse(input).unwrap_err().to_string();
+    let one = object(vec![("a", Value::Integer(1))]);
+    assert_eq!(parse("{a:1}").unwrap(), one);
+    assert_eq!(parse("{a: 1}").unwrap(), one);
+    assert_eq!(parse("{a:\n1}").unwrap(), one);
+    assert_eq!(
+        parse("{a:[1]}").unwrap(),
+        object(vec![("a", Value::Array(vec![Value::Integer(1)]))])
+    );
+    assert_eq!(parse("{a: :b}").unwrap(), object(vec![("a", symbol("b"))]));
+    assert_eq!(
+        parse(r#"{"a": :b}"#).unwrap(),
+        object(vec![("a", symbol("b"))])
+    );
+    let options = ParseOptions {
+        on_unknown: nompom::UnknownTokens::CaptureAsString,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with_options("{a:b}", &options).unwrap(),
+        object(vec![("a", Value::Bytes("b".to_string()))])
+    );
+    assert_eq!(
+        message("{a::b}"),
+        "expected a value after ':', found ':' in map at offset 3"
+    );
+    assert_eq!(
+        message("{a := 1}"),
+        "expected a value after ':', found '=' in map at offset 4"
+    );
+}
+
+#[test]
+fn signed_and_float_keys() {
+    let string = |text: &str| Value::Bytes(text.to_string());
+    assert_eq!(
+        parse(r#"{ -1 => "x", 1 => "y", 1.5 => "z", -1.5 => 0, 1.0 => 2 }"#).unwrap(),
+        object(vec![
+            ("-1", string("x")),
+            ("-1.5", Value::Integer(0)),
+            ("1", string("y")),
+            ("1.0", Value::Integer(2)),
+            ("1.5", string("z")),
+        ])
+    );
+}
+
+#[test]
+fn split_rocket_is_reported() {
+    let message = |input| parse(input).unwrap_err().to_string();
+    assert_eq!(
+        message("{:a = > 1}"),
+        "expected '=>', found '= >' in map at offset 4"
+    );
+    assert_eq!(
+        message(r#"{"a" = > 1}"#),
+        "expected '=>', found '= >' in map at offset 5"
+    );
+    assert_eq!(
+        message("{:a => 1, :b = > 2}"),
+        "expected '=>', found '= >' in map at offset 13"
+    );
+    assert_eq!(
+        message("{:a =  1}"),
+        "expected '=>', found '=' in map at offset 4"
+    );
+    let options = ParseOptions {
+        bareword_arrow_keys: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with_options("{a = > 1}", &options)
+            .unwrap_err()
+            .to_string(),
+        "expected '=>', found '= >' in map at offset 3"
+    );
+}
+
+#[test]
+fn prune_cascades_to_emptied_parents() {
+    let mut value =
+        parse(r#"{:a => {:b => nil, :c => {:d => ""}}, :e => [nil, {}], :f => 1}"#).unwrap();
+    let mut kept = value.clone();
+    kept.prune(false);
+    assert_eq!(
+        kept,
+        parse(r#"{:a => {:c => {:d => ""}}, :e => [nil, {}], :f => 1}"#).unwrap()
+    );
+    value.prune(true);
+    // Array elements keep their places, even when nil or empty.
+    assert_eq!(value, parse("{:e => [nil, {}], :f => 1}").unwrap());
+
+    let mut deep = parse(&format!("{}nil{}", "{:a => ".repeat(60), "}".repeat(60))).unwrap();
+    deep.prune(true);
+    assert_eq!(deep, parse("{}").unwrap());
+}
+
+#[test]
+fn symbol_key_with_a_label_colon_is_rejected() {
+    let one = parse("{:a => 1}").unwrap();
+    assert_eq!(one.get(":a"), Some(&Value::Integer(1)));
+    assert_eq!(parse("{a: 1}").unwrap().get("a"), Some(&Value::Integer(1)));
+    assert_eq!(
+        parse("{:a: 1}").unwrap_err().to_string(),
+        "expected '=>', found ':' in map at offset 3"
+    );
+    assert_eq!(
+        parse(r#"{:"a": 1}"#).unwrap_err().to_string(),
+        "expected '=>', found ':' in map at offset 5"
+    );
+}
+
+#[test]
+fn retain_elements_and_entries() {
+    let mut array = parse("[1, nil, 2, :x]").unwrap();
+    array.retain(|value| matches!(value, Value::Integer(_)));
+    assert_eq!(array, parse("[1, 2]").unwrap());
+
+    let mut value = parse("{:a => 1, :b => nil, c: 3}").unwrap();
+    value.retain_entries(|key, value| key != "c" && *value != Value::Null);
+    assert_eq!(value, parse("{:a => 1}").unwrap());
+    let mut kept = value.clone();
+    kept.retain(|_| false);
+    assert_eq!(kept, value);
+
+    let mut scalar = Value::Integer(1);
+    scalar.retain_entries(|_, _| false);
+    assert_eq!(scalar, Value::Integer(1));
+}
+
+#[test]
+fn configured_comment_syntaxes() {
+    let lines = ParseOptions {
+        line_comments: vec!["//".to_string(), "--".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with_options("{a: 1, // x\n b: \"c // d\" -- y\n}", &lines).unwrap(),
+        parse(r#"{a: 1, b: "c // d"}"#).unwrap()
+    );
+    // Setting the prefixes replaces `#`.
+    assert_eq!(
+        parse_with_options("{a: 1, # x\n}", &lines)
+            .unwrap_err()
+            .to_string(),
+        "expected '}' in map at offset 7"
+    );
+
+    let blocks = ParseOptions {
+        block_comments: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with_options("{a: /* x\n y */ 1, b: \"/* no */\"} # z", &blocks).unwrap(),
+        parse(r#"{a: 1, b: "/* no */"}"#).unwrap()
+    );
+    assert_eq!(
+        parse_with_options("{a: 1, /* x", &blocks)
+            .unwrap_err()
+            .to_string(),
+        "unterminated comment in map at offset 7"
+    );
+}
+
+#[test]
+fn lenient_parse_skips_a_garbled_entry() {
+    let input = r#"{a: 1, b: user&.name, c: [2, x&.y, 3], d: "ok"} tail"#;
+    let (value, skipped) = parse_lenient_with_diagnostics(input, &Default::default()).unwrap();
+    assert_eq!(value, parse(r#"{a: 1, c: [2, 3], d: "ok"}"#).unwrap());
+    assert_eq!(
+        skipped.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "unexpected input at offset 10",
+            "unexpected input at offset 29",
+            "unexpected trailing input at offset 48",
+        ]
+    );
+    // An unclosed hash can't be skipped past.
+    assert_eq!(
+        parse_lenient_with_diagnostics("{a: 1, b: x&.y", &Default::default())
+            .unwrap_err()
+            .to_string(),
+        "expected '}', found end of input in map at offset 14"
+    );
+}
+
+#[test]
+fn duplicate_array_values_merge() {
+    let options = ParseOptions {
+        on_duplicate_key: DuplicateKeys::MergeArrays,
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options).unwrap().to_string();
+    assert_eq!(parse("{ a: [1], a: [2] }"), r#"{"a" => [1, 2]}"#);
+    assert_eq!(
+        parse("{a: [1], a: [2], a: [3, [4]]}"),
+        r#"{"a" => [1, 2, 3, [4]]}"#
+    );
+    // Anything else is last-wins, and nested arrays aren't merged.
+    assert_eq!(parse("{a: [1], a: 2}"), r#"{"a" => 2}"#);
+    assert_eq!(parse("{a: 1, a: [2]}"), r#"{"a" => [2]}"#);
+    assert_eq!(
+        parse("{a: {b: [1]}, a: {b: [2]}}"),
+        r#"{"a" => {"b" => [2]}}"#
+    );
+    // A symbol and a string are different keys.
+    assert_eq!(
+        parse(r#"{:a => [1], "a" => [2]}"#),
+        r#"{:a => [1], "a" => [2]}"#
+    );
+}
+
+#[test]
+fn defaults_fill_missing_keys() {
+    let mut config = parse("{a: 1, b: {c: 2}, d: [1], e: 5}").unwrap();
+    let defaults = parse("{a: 9, b: {c: 9, f: {g: 1}}, d: [2, 3], e: {x: 1}, h: nil}").unwrap();
+    config.apply_defaults(&defaults);
+    assert_eq!(
+        config,
+        parse("{a: 1, b: {c: 2, f: {g: 1}}, d: [1], e: 5, h: nil}").unwrap()
+    );
+
+    let mut array = parse("[1]").unwrap();
+    array.apply_defaults(&defaults);
+    assert_eq!(array, parse("[1]").unwrap());
+}
+
+#[test]
+fn dedup_arrays_with_nested_objects() {
+    let mut value =
+        parse(r#"[1, {a: [2, 2]}, 1, {a: [2]}, [{b: 1}, {b: 1}], [{b: 1}], 0.0, -0.0, :x, "x"]"#)
+            .unwrap();
+    value.dedup_arrays();
+    assert_eq!(
+        value,
+        parse(r#"[1, {a: [2]}, [{b: 1}], 0.0, :x, "x"]"#).unwrap()
+    );
+
+    // Objects keep their entries, even when the values repeat.
+    let mut object = parse("{a: [1, 1], b: [1, 1]}").unwrap();
+    object.dedup_arrays();
+    assert_eq!(object, parse("{a: [1], b: [1]}").unwrap());
+}
+
+#[test]
+fn rename_keys_to_uppercase() {
+    let input = "{a: [1], A: [2], b: {c_d: 1}, list: [{e: 1}], s: #<S x: 1>}";
+    let renamed = |on_duplicate_key| {
+        let mut value = parse(input).unwrap();
+        let result = value.rename_keys(on_duplicate_key, |key| Some(key.to_uppercase()));
+        (result, value.to_string())
+    };
+    // `A` sorts before `a`, so `a`'s value comes last.
+    let rest = r#""B" => {"C_D" => 1}, "LIST" => [{"E" => 1}], "S" => #<struct S x=1>}"#;
+    assert_eq!(
+        renamed(DuplicateKeys::Last),
+        (Ok(()), format!(r#"{{"A" => [1], {}"#, rest))
+    );
+    assert_eq!(
+        renamed(DuplicateKeys::First),
+        (Ok(()), format!(r#"{{"A" => [2], {}"#, rest))
+    );
+    assert_eq!(
+        renamed(DuplicateKeys::MergeArrays),
+        (Ok(()), format!(r#"{{"A" => [2, 1], {}"#, rest))
+    );
+    let (result, written) = renamed(DuplicateKeys::Error);
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "duplicate key A after renaming"
+    );
+    assert_eq!(written, parse(input).unwrap().to_string());
+
+    let mut value = parse("{a: 1, b: 2}").unwrap();
+    value
+        .rename_keys(DuplicateKeys::Error, |key| {
+            (key == "a").then(|| "c".to_string())
+        })
+        .unwrap();
+    assert_eq!(value, parse("{b: 2, c: 1}").unwrap());
+}
+
+#[test]
+fn recursion_markers() {
+    let options = ParseOptions {
+        recursion_markers: true,
+        ..Default::default()
+    };
+    let value = parse_with_options("{:a=>{...}, :b=>[1, [...]]}", &options).unwrap();
+    assert_eq!(value.get(":a"), Some(&Value::Recursive));
+    assert_eq!(
+        value.get(":b"),
+        Some(&Value::Array(vec![Value::Integer(1), Value::Recursive]))
+    );
+    assert_eq!(
+        parse_with_options("{...}", &options).unwrap(),
+        Value::Recursive
+    );
+    assert_eq!(
+        parse("{:a=>{...}}").unwrap_err().to_string(),
+        "expected '}' in map at offset 6"
+    );
+}
+
+#[test]
+fn key_spans() {
+    let input = r#"{a: {"b" => 1}, :c => 2, 'd': 3}"#;
+    let (value, spans) = parse_with_key_spans(input, &Default::default()).unwrap();
+    assert_eq!(value, parse(input).unwrap());
+    let expected: BTreeMap<String, std::ops::Range<usize>> = vec![
+        ("/:c", 16..18),
+        ("/a", 1..2),
+        ("/a/b", 5..8),
+        ("/d", 25..28),
+    ]
+    .into_iter()
+    .map(|(pointer, range)| (pointer.to_string(), range))
+    .collect();
+    assert_eq!(spans, expected);
+    assert_eq!(&input[spans["/a/b"].clone()], r#""b""#);
+}
+
+#[test]
+fn merge_rules_per_path() {
+    let mut base = parse(
+        "{tags: [1, 2], ids: [1, 2], sets: [1, 2], cfg: {a: 1, b: [1]}, servers: {x: {tags: [1]}}}",
+    )
+    .unwrap();
+    let other = parse(
+        "{tags: [2, 3], ids: [3], sets: [2, 3], cfg: {b: [2], c: 3}, servers: {x: {tags: [2]}, y: {tags: [3]}}}",
+    )
+    .unwrap();
+    let rules = MergeRules::new()
+        .rule("/tags", MergeStrategy::Concat)
+        .rule("/ids", MergeStrategy::Replace)
+        .rule("/sets", MergeStrategy::Union)
+        .rule("/servers/*/tags", MergeStrategy::Concat);
+    base.merge_with_rules(other, &rules);
+    assert_eq!(
+        base,
+        parse(
+            "{tags: [1, 2, 2, 3], ids: [3], sets: [1, 2, 3], cfg: {a: 1, b: [2], c: 3}, servers: {x: {tags: [1, 2]}, y: {tags: [3]}}}",
+        )
+        .unwrap()
+    );
+
+    let mut replaced = parse("{a: {b: 1}}").unwrap();
+    let rules = MergeRules::new().default_strategy(MergeStrategy::Replace);
+    replaced.merge_with_rules(parse("{a: {c: 2}}").unwrap(), &rules);
+    assert_eq!(replaced, parse("{a: {c: 2}}").unwrap());
+}
+
+#[test]
+fn draining_arrays_and_objects() {
+    let array = parse("[1, [2], {a: 3}]").unwrap();
+    assert_eq!(
+        array.into_array_iter().collect::<Vec<_>>(),
+        [
+            Value::Integer(1),
+            parse("[2]").unwrap(),
+            parse("{a: 3}").unwrap()
+        ]
+    );
+    let object = parse("{b: 1, :a => [2]}").unwrap();
+    assert_eq!(
+        object.into_object_iter().collect::<Vec<_>>(),
+        [
+            (":a".to_string(), parse("[2]").unwrap()),
+            ("b".to_string(), Value::Integer(1)),
+        ]
+    );
+    assert_eq!(
+        parse("#<S x: 1>")
+            .unwrap()
+            .into_object_iter()
+            .collect::<Vec<_>>(),
+        [("x".to_string(), Value::Integer(1))]
+    );
+    assert_eq!(Value::Integer(1).into_array_iter().count(), 0);
+    assert_eq!(parse("[1]").unwrap().into_object_iter().count(), 0);
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "serde_json")]
+
+use nompom::Value;
+
+/// Converting to JSON and back loses what JSON can't hold, such as the
+/// difference between symbols and strings, but a value converted once
+/// converts the same way again, and `write_json` writes the same JSON.
+#[cfg(feature = "quickcheck")]
+#[test]
+fn json_round_trips() {
+    fn property(value: Value) -> bool {
+        let json = serde_json::Value::from(value.clone());
+        let mut written = Vec::new();
+        value.write_json(&mut written).unwrap();
+        // Keys that only JSON makes the same, such as `nil` and `""`, are
+        // all written, and the last is the one read back.
+        serde_json::from_slice::<serde_json::Value>(&written).unwrap() == json
+            && serde_json::Value::from(Value::from(json.clone())) == json
+    }
+    quickcheck::quickcheck(property as fn(Value) -> bool);
+}
+
+#[test]
+fn write_json_writes_what_from_converts() {
+    let value = nompom::parse(
+        r#"{:a => [1, -2.5, "x\ny", :"b c", nil, true], "d" => {e: #<User id: 1>}, "f" => Foo::Bar}"#,
+    )
+    .unwrap();
+    let mut written = Vec::new();
+    value.write_json(&mut written).unwrap();
+    let json = serde_json::Value::from(value);
+    assert_eq!(String::from_utf8(written).unwrap(), json.to_string());
+    assert_eq!(
+        json.to_string(),
+        r#"{":a":[1,-2.5,"x\ny","b c",null,true],"d":{"e":{"id":1}},"f":"Foo::Bar"}"#
+    );
+}
+
+#[test]
+fn json_document_round_trips() {
+    let json: serde_json::Value = serde_json::from_str(
+        r#"{"name": "a", "count": 3, "ratio": 0.5, "big": 1e20, "tags": ["x", null, true],
+            "nested": {"": {}, ":colon": []}}"#,
+    )
+    .unwrap();
+    let value = Value::from(json.clone());
+    assert_eq!(value.get("count"), Some(&Value::Integer(3)));
+    assert_eq!(value.get("big"), Some(&Value::Float(1e20)));
+    assert_eq!(
+        value.to_ruby(),
+        r#"{"big" => 1e20, "count" => 3, "name" => "a", "nested" => {"" => {}, ":colon" => []}, "ratio" => 0.5, "tags" => ["x", nil, true]}"#
+    );
+    assert_eq!(nompom::parse(&value.to_ruby()).unwrap(), value);
+    assert_eq!(serde_json::Value::from(value), json);
+}
@@ -0,0 +1,237 @@
+use nompom::{parse, parse_with_options, ParseOptions, Value};
+
+#[test]
+fn exponent_makes_a_float() {
+    assert_eq!(parse("3e2").unwrap(), Value::Float(300.0));
+    assert_eq!(parse("3E2").unwrap(), Value::Float(300.0));
+    assert_eq!(parse("3.0e2").unwrap(), Value::Float(300.0));
+    assert_eq!(parse("3").unwrap(), Value::Integer(3));
+}
+
+#[test]
+fn exponent_makes_a_numeric_string_a_float() {
+    let options = ParseOptions {
+        coerce_numeric_strings: true,
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options).unwrap();
+    assert_eq!(parse(r#""3e2""#), Value::Float(300.0));
+    assert_eq!(parse(r#""3E2""#), Value::Float(300.0));
+    assert_eq!(parse(r#""3.0e2""#), Value::Float(300.0));
+    assert_eq!(parse(r#""3""#), Value::Integer(3));
+    assert_eq!(parse(r#""3e""#), Value::Bytes("3e".to_string()));
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn decimal_exponents_are_bounded() {
+    let options = ParseOptions {
+        decimal_numbers: true,
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options);
+    // A zero mantissa returns at once, however large the exponent.
+    let zero = Value::Decimal(Default::default());
+    assert_eq!(parse("0e200000000").unwrap(), zero);
+    assert_eq!(parse("0.0e2000000000").unwrap(), zero);
+    assert_eq!(
+        parse("1e2000000000").unwrap_err().to_string(),
+        "exponent out of range at offset 0"
+    );
+    assert_eq!(
+        parse("1e-29").unwrap_err().to_string(),
+        "exponent out of range at offset 0"
+    );
+    assert_eq!(parse("1.5e2").unwrap().to_string(), "150");
+}
+
+#[test]
+fn integers_too_big_for_i64_are_floats() {
+    assert_eq!(
+        parse("9223372036854775807").unwrap(),
+        Value::Integer(i64::MAX)
+    );
+    assert_eq!(
+        parse("9223372036854775808").unwrap(),
+        Value::Float(9223372036854775808.0)
+    );
+    assert_eq!(
+        parse("[-99999999999999999999]").unwrap(),
+        Value::Array(vec![Value::Float(-1e20)])
+    );
+}
+
+#[test]
+fn plus_signs() {
+    assert_eq!(parse("+5").unwrap(), Value::Integer(5));
+    assert_eq!(parse("+1.5").unwrap(), Value::Float(1.5));
+    assert_eq!(parse("1e+3").unwrap(), Value::Float(1000.0));
+    assert_eq!(parse("+0x1F").unwrap(), Value::Integer(31));
+    assert_eq!(
+        parse("[+5, -5]").unwrap(),
+        Value::Array(vec![Value::Integer(5), Value::Integer(-5)])
+    );
+    assert_eq!(
+        parse("+").unwrap_err().to_string(),
+        "expected digits after '+' at offset 0"
+    );
+    assert_eq!(
+        parse("[+]").unwrap_err().to_string(),
+        "expected digits after '+' in array at offset 1"
+    );
+    assert!(parse("+-1").is_err());
+}
+
+#[test]
+fn underscores_between_digits() {
+    assert_eq!(parse("1_000").unwrap(), Value::Integer(1000));
+    assert_eq!(parse("+1_0").unwrap(), Value::Integer(10));
+    assert_eq!(parse("0x1_F").unwrap(), Value::Integer(31));
+    assert_eq!(
+        parse("1_000.000_1e1_0").unwrap(),
+        Value::Float(1_000.000_1e10)
+    );
+    assert_eq!(parse("1e-1_0").unwrap(), Value::Float(1e-10));
+
+    let error = |input| parse(input).unwrap_err().to_string();
+    for (input, offset) in [
+        ("1__0", 1),
+        ("1_", 1),
+        ("-_1", 1),
+        ("1_.5", 1),
+        ("1._5", 2),
+        ("1.5_", 3),
+        ("1_e5", 1),
+        ("1e_5", 2),
+        ("1e+_5", 3),
+        ("1e5_", 3),
+    ] {
+        assert_eq!(
+            error(input),
+            format!(
+                "underscore in a number must be between digits at offset {}",
+                offset
+            ),
+            "{}",
+            input
+        );
+    }
+    assert_eq!(error("_1"), "unexpected input at offset 0");
+}
+
+#[test]
+fn whole_floats_normalize_to_integers() {
+    let mut value = parse(r#"{a: 3.0, b: [1.5, -0.0, 1e300, 2.0, {c: 4.0}], d: "5.0"}"#).unwrap();
+    value.normalize_numbers();
+    assert_eq!(
+        value,
+        parse(r#"{a: 3, b: [1.5, 0, 1e300, 2, {c: 4}], d: "5.0"}"#).unwrap()
+    );
+
+    let mut edges = Value::Array(vec![
+        Value::Float(f64::INFINITY),
+        Value::Float(i64::MAX as f64),
+        Value::Float(i64::MIN as f64),
+    ]);
+    edges.normalize_numbers();
+    assert_eq!(
+        edges,
+        Value::Array(vec![
+            Value::Float(f64::INFINITY),
+            Value::Float(i64::MAX as f64),
+            Value::Integer(i64::MIN),
+        ])
+    );
+}
+
+#[test]
+fn radix_prefixes() {
+    for (input, integer) in [
+        ("0x1f", 31),
+        ("0X1F", 31),
+        ("0o17", 15),
+        ("0O17", 15),
+        ("0b101", 5),
+        ("0B1", 1),
+        ("0d123", 123),
+        ("0D12", 12),
+        ("-0d5", -5),
+        ("0d1_0", 10),
+    ] {
+        assert_eq!(parse(input).unwrap(), Value::Integer(integer), "{}", input);
+    }
+    let error = |input| parse(input).unwrap_err().to_string();
+    assert_eq!(error("0d"), "expected digits after '0d' at offset 2");
+    assert_eq!(
+        error("[0D]"),
+        "expected digits after '0d' in array at offset 3"
+    );
+    assert_eq!(error("0x"), "expected digits after '0x' at offset 2");
+    assert_eq!(
+        error("0d1a"),
+        "invalid digit for the integer's radix at offset 3"
+    );
+    assert_eq!(
+        error("0b12"),
+        "invalid digit for the integer's radix at offset 3"
+    );
+}
+
+#[test]
+fn negative_zero_keeps_its_sign() {
+    let zero = parse("-0.0").unwrap();
+    assert!(zero.is_negative_zero());
+    assert!(parse("-0e0").unwrap().is_negative_zero());
+    assert_eq!(zero.to_string(), "-0.0");
+    assert_eq!(parse("[-0.0]").unwrap().to_string(), "[-0.0]");
+    // It still equals positive zero.
+    assert_eq!(zero, Value::Float(0.0));
+    assert!(!parse("0.0").unwrap().is_negative_zero());
+    // `-0` is an integer, which has no sign.
+    assert_eq!(parse("-0").unwrap(), Value::Integer(0));
+    assert!(!parse("-0").unwrap().is_negative_zero());
+}
+
+#[test]
+fn non_finite_floats_in_nested_hashes() {
+    let value = parse(
+        "{ ratio: Float::NAN, bound: -Float::INFINITY, deep: {list: [Infinity, -Infinity, NaN, Float::INFINITY]} }",
+    )
+    .unwrap();
+    let float = |value: Option<&Value>| value.and_then(Value::float).unwrap();
+    assert!(float(value.get("ratio")).is_nan());
+    assert_eq!(float(value.get("bound")), f64::NEG_INFINITY);
+    let list = value.get("deep").and_then(|deep| deep.get("list")).unwrap();
+    assert_eq!(float(list.get(0)), f64::INFINITY);
+    assert_eq!(float(list.get(1)), f64::NEG_INFINITY);
+    assert!(float(list.get(2)).is_nan());
+    assert_eq!(float(list.get(3)), f64::INFINITY);
+    assert_eq!(
+        value.to_string(),
+        "{\"bound\" => -Float::INFINITY, \"deep\" => {\"list\" => [Float::INFINITY, -Float::INFINITY, Float::NAN, Float::INFINITY]}, \"ratio\" => Float::NAN}"
+    );
+    // Other constants under `Float` stay constants.
+    assert_eq!(
+        parse("[Float::EPSILON, Float::NANX]").unwrap(),
+        Value::Array(vec![
+            Value::Constant("Float::EPSILON".to_string()),
+            Value::Constant("Float::NANX".to_string()),
+        ])
+    );
+}
+
+#[test]
+fn approximately_equal_floats() {
+    let a = parse("{a: [1.0000001, 2], b: 3.0}").unwrap();
+    assert!(a.approx_eq(&parse("{a: [1.0, 2], b: 3.0}").unwrap(), 1e-6));
+    assert!(!a.approx_eq(&parse("{a: [1.0, 2], b: 3.0}").unwrap(), 1e-9));
+    // Integers meet floats within the tolerance, but not each other.
+    assert!(Value::Integer(3).approx_eq(&Value::Float(3.0000001), 1e-6));
+    assert!(!Value::Integer(3).approx_eq(&Value::Integer(4), 10.0));
+    // The tolerance is absolute.
+    assert!(!Value::Float(1e20).approx_eq(&Value::Float(1.0000001e20), 1e-6));
+    assert!(!Value::Float(f64::NAN).approx_eq(&Value::Float(f64::NAN), 1.0));
+    assert!(Value::Float(f64::INFINITY).approx_eq(&Value::Float(f64::INFINITY), 1e-6));
+    assert!(!a.approx_eq(&parse("{a: [1.0, 2]}").unwrap(), 1e-6));
+    assert!(!a.approx_eq(&parse("{a: [1.0, 2, 3], b: 3.0}").unwrap(), 1e-6));
+}
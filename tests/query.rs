@@ -0,0 +1,76 @@
+use nompom::{parse, Query, Value};
+
+#[test]
+fn ages_of_every_user() {
+    let value = parse(
+        r#"{users: [
+            {name: "a", age: 30},
+            {name: "b"},
+            {name: "c", age: 41},
+        ], groups: {admins: [#<User age: 52>]}}"#,
+    )
+    .unwrap();
+    let ages = |path| value.query(&Query::parse(path));
+    assert_eq!(
+        ages("users.*.age"),
+        [&Value::Integer(30), &Value::Integer(41)]
+    );
+    assert_eq!(ages("users.1.name"), [&Value::Bytes("b".to_string())]);
+    assert_eq!(ages("groups.*.*.age"), [&Value::Integer(52)]);
+    assert!(ages("users.*.height").is_empty());
+    assert_eq!(ages(""), [&value]);
+}
+
+#[test]
+fn try_fold_visits_every_value() {
+    let value = parse(r#"{a: 1, b: [2, {c: 3}, "x"], d: 4}"#).unwrap();
+    let sum = value.try_fold(0, |sum, value| match value {
+        Value::Integer(n) => Ok::<_, ()>(sum + n),
+        _ => Ok(sum),
+    });
+    assert_eq!(sum, Ok(10));
+
+    // Parents come before their children, and the first error stops the fold.
+    let visited = value.try_fold(Vec::new(), |mut visited, value| {
+        if let Value::Integer(3) = value {
+            return Err(visited);
+        }
+        visited.push(value.to_string());
+        Ok(visited)
+    });
+    assert_eq!(
+        visited.unwrap_err(),
+        [
+            r#"{"a" => 1, "b" => [2, {"c" => 3}, "x"], "d" => 4}"#,
+            "1",
+            r#"[2, {"c" => 3}, "x"]"#,
+            "2",
+            r#"{"c" => 3}"#,
+        ]
+    );
+}
+
+#[test]
+fn collect_translatable_strings() {
+    let config = parse(
+        r#"{title: "Hi", menu: {items: [{label: "Open", id: 1}, {label: :save}]}, "a/b~c" => "p"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.collect_strings(false),
+        [
+            ("/a~1b~0c".to_string(), "p"),
+            ("/menu/items/0/label".to_string(), "Open"),
+            ("/title".to_string(), "Hi"),
+        ]
+    );
+    assert_eq!(
+        config.collect_strings(true),
+        [
+            ("/a~1b~0c".to_string(), "p"),
+            ("/menu/items/0/label".to_string(), "Open"),
+            ("/menu/items/1/label".to_string(), "save"),
+            ("/title".to_string(), "Hi"),
+        ]
+    );
+}
@@ -0,0 +1,304 @@
+use std::error::Error;
+
+use nompom::Value;
+
+#[test]
+fn read_errors_name_their_cause_once() {
+    let err = Value::from_reader(&b"[1,"[..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to parse input: expected ']', found end of input in array at offset 3"
+    );
+    assert!(err.source().is_none());
+
+    let err = Value::from_reader(&b"\xff"[..]).unwrap_err();
+    assert!(err.to_string().starts_with("failed to read input: "));
+    assert!(err.source().is_none());
+}
+
+/// Hands out its input a few bytes at a time, so that values are split
+/// across reads.
+#[cfg(feature = "tokio")]
+struct Chunked {
+    input: &'static [u8],
+    chunk: usize,
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for Chunked {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        let len = self.chunk.min(self.input.len()).min(buf.remaining());
+        buf.put_slice(&self.input[..len]);
+        self.input = &self.input[len..];
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+/// Reads `input` in chunks of every size up to its length, checking that
+/// each gives the same values as reading it whole.
+#[cfg(feature = "tokio")]
+async fn read_chunked(input: &'static str, array: bool) -> Vec<String> {
+    let mut whole = None;
+    for chunk in 1..=input.len() {
+        let reader = Chunked {
+            input: input.as_bytes(),
+            chunk,
+        };
+        let mut values = match array {
+            true => nompom::parse_array_from_async_read(reader),
+            false => nompom::parse_from_async_read(reader),
+        };
+        let mut read = Vec::new();
+        while let Some(value) = values.next_value().await {
+            read.push(match value {
+                Ok(value) => value.to_string(),
+                Err(err) => format!("error: {}", err),
+            });
+        }
+        match &whole {
+            None => whole = Some(read),
+            Some(whole) => assert_eq!(&read, whole, "in chunks of {}", chunk),
+        }
+    }
+    whole.unwrap()
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn values_split_across_chunks() {
+    assert_eq!(
+        read_chunked("1 [2, 3] {:a => \"b c\"}\n%w[d e] :f", false).await,
+        ["1", "[2, 3]", r#"{:a => "b c"}"#, r#"["d", "e"]"#, ":f"]
+    );
+    assert_eq!(
+        read_chunked("[1, \"two, 2\", [3, [4]], {:five => 5}, %i[six]]", true).await,
+        ["1", r#""two, 2""#, "[3, [4]]", "{:five => 5}", "[:six]"]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn array_elements_with_comments_and_character_literals() {
+    assert_eq!(read_chunked("[1, # c ]\n 2]", true).await, ["1", "2"]);
+    assert_eq!(
+        read_chunked("[?], 1, ?,, ?\\], :empty?]", true).await,
+        [r#""]""#, "1", r#"",""#, r#""]""#, ":empty?"]
+    );
+    assert_eq!(
+        read_chunked("1 # a comment, then [\n2", false).await,
+        ["1", "2"]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn inspected_objects_are_single_values() {
+    assert_eq!(
+        read_chunked("#<User id: 1> 2 #<A b: #<B c: [1]>, d: {:e => 1}>", false).await,
+        [
+            "#<struct User id=1>",
+            "2",
+            "#<struct A b=#<struct B c=[1]>, d={:e => 1}>"
+        ]
+    );
+    assert_eq!(
+        read_chunked("[#<User id: 1, name: \"a, b\">, 2]", true).await,
+        [r#"#<struct User id=1, name="a, b">"#, "2"]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn heredoc_bodies_belong_to_their_value() {
+    assert_eq!(
+        read_chunked("<<~A 1\n  x, y ] z\n  A\n2", false).await,
+        [
+            "error: failed to parse input: unexpected trailing input at offset 5",
+            "2"
+        ]
+    );
+    assert_eq!(
+        read_chunked("<<~A\n  x ]\n  A\n<<-B # c\n y\n  B", false).await,
+        [r#""x ]\n""#, r#"" y\n""#]
+    );
+    assert_eq!(
+        read_chunked(
+            "[\n  <<~A,\n    x, ]\n  A\n  <<~B + <<~C,\n    y\n  B\n    z\n  C\n  2]",
+            true
+        )
+        .await,
+        [r#""x, ]\n""#, r#""y\nz\n""#, "2"]
+    );
+    assert_eq!(
+        read_chunked("[1, <<~A]\n  x\n  A\n", true).await,
+        ["1", r#""x\n""#]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn trailing_calls_stay_with_their_value() {
+    let options = nompom::ParseOptions {
+        ignore_trailing_calls: true,
+        ..Default::default()
+    };
+    let mut values = nompom::parse_from_async_read(Chunked {
+        input: b"\"a\".freeze [1].dup.freeze {}",
+        chunk: 2,
+    })
+    .with_options(options);
+    let mut read = Vec::new();
+    while let Some(value) = values.next_value().await {
+        read.push(value.unwrap().to_string());
+    }
+    assert_eq!(read, [r#""a""#, "[1]", "{}"]);
+}
+
+/// Input a fuzzer might find: unterminated and truncated literals, escapes
+/// and numbers out of range, nesting past the depth limit, and bytes that
+/// aren't UTF-8.
+const MALFORMED: &[&[u8]] = &[
+    b"",
+    b"{",
+    b"[",
+    b"{:a",
+    b"{:a =>",
+    b"{:a => 1,,}",
+    b"{a: }",
+    b"[1 2]",
+    b"\"",
+    b"\"\\",
+    b"\"\\u{110000}\"",
+    b"\"\\ud800\"",
+    b"\"\\x\"",
+    b"'",
+    b":",
+    b":\"",
+    b"?",
+    b"?\\",
+    b"%w[",
+    b"%i(a",
+    b"<<~EOS",
+    b"<<~EOS\nbody",
+    b"#<",
+    b"#<User id: 1",
+    b"#<User id 1>",
+    b"0x",
+    b"0b2",
+    b"1e",
+    b"1.2.3",
+    b"--1",
+    b"Float::",
+    b"::",
+    b"}",
+    b"]",
+    b"=>",
+    b"\xff",
+    b"\"\xc3\"",
+    b"[\xe2\x82]",
+    b"{:a => \x00\x01}",
+];
+
+/// Nesting deeper than the parser allows, which has to fail before it runs
+/// out of stack.
+const DEEP: &[(&str, &str)] = &[
+    ("[", "]"),
+    ("{a: ", "}"),
+    ("{:a => ", "}"),
+    ("[{a: ", "}]"),
+    ("#<A b: ", ">"),
+    ("[#<A b: {c: ", "}>]"),
+    ("(", ")"),
+    ("%{a: ", "}"),
+];
+
+#[test]
+fn malformed_input_is_an_error() {
+    let options = nompom::ParseOptions::default();
+    for input in MALFORMED {
+        let result = nompom::parse_slice(input, &options);
+        assert!(result.is_err(), "{:?} gave {:?}", input, result);
+        assert!(Value::from_reader(*input).is_err());
+    }
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let options = nompom::ParseOptions {
+        parenthesized_arrays: true,
+        elixir_maps: true,
+        ..Default::default()
+    };
+    for (open, close) in DEEP {
+        let input = format!("{}1{}", open.repeat(10_000), close.repeat(10_000));
+        let err = nompom::parse_with_options(&input, &options).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("maximum nesting depth exceeded"),
+            "{} gave {}",
+            open,
+            err
+        );
+        assert!(nompom::parse_with_options(&input[..10_000], &options).is_err());
+    }
+}
+
+#[test]
+fn truncated_input_does_not_panic() {
+    let input = "{:a => [1, -2.5e3, 0x1F, nil], \"b\\u{263A}\" => {c: %w[d e], 'f' => ?g},\n  \
+        :h => <<~EOS, :i => #<User id: 1, tags: [:x]>, j: Float::INFINITY}\n    body\n  EOS\n";
+    let options = nompom::ParseOptions::default();
+    assert!(nompom::parse_slice(input.as_bytes(), &options).is_ok());
+    for end in 0..input.len() {
+        let _ = nompom::parse_slice(&input.as_bytes()[..end], &options);
+        let _ = nompom::parse_lenient(&input[..end]);
+    }
+}
+
+#[test]
+fn document_separated_values() {
+    let input = "---\n{:a => 1}\n---\n[1,\n 2]\n--- \n\"three\"\n";
+    let options = nompom::ParseOptions {
+        document_separators: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        nompom::parse_concatenated_with_options(input, &options).unwrap(),
+        [
+            nompom::parse("{:a => 1}").unwrap(),
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            Value::Bytes("three".to_string()),
+        ]
+    );
+    assert!(nompom::parse_concatenated(input).is_err());
+    assert!(nompom::parse_concatenated_with_options("[1,\n---\n2]", &options).is_err());
+}
+
+#[test]
+fn semicolon_separated_values() {
+    let options = nompom::ParseOptions {
+        semicolon_separators: true,
+        ..Default::default()
+    };
+    let parse = |input| nompom::parse_concatenated_with_options(input, &options).unwrap();
+    assert_eq!(
+        parse("1; 2; nil"),
+        [Value::Integer(1), Value::Integer(2), Value::Null]
+    );
+    assert_eq!(
+        parse(r#"1;{a: "x;y", b: 3};"#),
+        [
+            Value::Integer(1),
+            nompom::parse(r#"{a: "x;y", b: 3}"#).unwrap()
+        ]
+    );
+    assert_eq!(parse("1 ;; 2"), [Value::Integer(1), Value::Integer(2)]);
+    assert_eq!(
+        nompom::parse_concatenated("1; 2").unwrap_err().to_string(),
+        "expected whitespace between values at offset 1"
+    );
+}
@@ -0,0 +1,198 @@
+use nompom::{parse, parse_call_args, ObjectKey, Value};
+
+fn round_trip(input: &str) -> String {
+    let value = parse(input).unwrap();
+    let written = value.to_string();
+    assert_eq!(parse(&written).unwrap(), value, "{}", written);
+    written
+}
+
+#[test]
+fn symbol_keys_are_written_as_symbols() {
+    assert_eq!(round_trip("{:a => 1}"), "{:a => 1}");
+    assert_eq!(round_trip(r#"{:"a b" => 1}"#), r#"{:"a b" => 1}"#);
+    assert_eq!(round_trip("{:a? => 1, :+ => 2}"), "{:+ => 2, :a? => 1}");
+}
+
+#[test]
+fn string_keys_starting_with_a_colon_stay_strings() {
+    let value = parse(r#"{":a" => 1, :a => 2}"#).unwrap();
+    let keys: Vec<_> = value.entries().map(|(key, _)| ObjectKey::of(key)).collect();
+    assert_eq!(keys, [ObjectKey::String(":a"), ObjectKey::Symbol("a")]);
+    assert_eq!(value.to_string(), r#"{":a" => 1, :a => 2}"#);
+    assert_eq!(round_trip(r#"{":" => 1}"#), r#"{":" => 1}"#);
+}
+
+#[test]
+fn object_keys_are_stored_apart() {
+    for key in [
+        ObjectKey::String("a"),
+        ObjectKey::String(":a"),
+        ObjectKey::String(":"),
+        ObjectKey::String("\0nil"),
+        ObjectKey::Symbol("a"),
+        ObjectKey::Symbol(""),
+        ObjectKey::Nil,
+        ObjectKey::Boolean(true),
+        ObjectKey::Boolean(false),
+    ]
+    .iter()
+    {
+        assert_eq!(ObjectKey::of(&key.stored()), *key);
+    }
+    assert_eq!(ObjectKey::of(Value::NIL_KEY).text(), "");
+    assert_eq!(round_trip(r#"{:"" => 1}"#), r#"{:"" => 1}"#);
+}
+
+/// Each input beside what Ruby 3.4's `inspect` prints for the same object.
+#[test]
+fn inspect_matches_ruby() {
+    let cases = [
+        (
+            r#"{:a => 1, :b => [nil, true, 1.5, "x"]}"#,
+            r#"{a: 1, b: [nil, true, 1.5, "x"]}"#,
+        ),
+        (r#"{:"a b" => 1}"#, r#"{"a b": 1}"#),
+        (r#"{nil => :"a b", :a? => 1}"#, r#"{nil => :"a b", a?: 1}"#),
+        (
+            r#"{"s" => "tab\tbell\a\u0001"}"#,
+            r#"{"s" => "tab\tbell\a\u0001"}"#,
+        ),
+        (
+            "[1.0e20, 1.0e-5, 100.0, 1e16, 0.0001]",
+            "[1.0e+20, 1.0e-05, 100.0, 1.0e+16, 0.0001]",
+        ),
+        ("[Float::NAN, -Float::INFINITY]", "[NaN, -Infinity]"),
+        ("#<struct Point x=1, y=2>", "#<struct Point x=1, y=2>"),
+    ];
+    for (input, ruby) in cases.iter() {
+        assert_eq!(parse(input).unwrap().to_inspect(), *ruby, "{}", input);
+    }
+}
+
+#[test]
+fn inspect_writes_label_keys_as_strings() {
+    assert_eq!(parse("{a: 1}").unwrap().to_inspect(), r#"{"a" => 1}"#);
+    assert_eq!(
+        parse("#<User id: 1>").unwrap().to_inspect(),
+        "#<struct User id=1>"
+    );
+}
+
+#[cfg(feature = "quickcheck")]
+#[test]
+fn display_round_trips() {
+    fn property(value: Value) -> bool {
+        parse(&value.to_string()).as_ref() == Ok(&value)
+    }
+    quickcheck::quickcheck(property as fn(Value) -> bool);
+}
+
+#[test]
+fn comments_round_trip() {
+    let input = r#"# Deploy settings
+{
+  # who owns it
+  :owner => "ops", # on call
+  :regions => [
+    # primary first
+    "eu-west-1",
+    "us-east-1", # failover
+  ],
+  :replicas => 3,
+  :server => {
+    "port" => 8080, # http
+  },
+} # end
+"#;
+    let options = nompom::ParseOptions::default();
+    let (value, comments) = nompom::parse_with_comments(input, &options).unwrap();
+    assert_eq!(value, parse(input).unwrap());
+    let node = comments.get("/:regions/1").unwrap();
+    assert_eq!(node.trailing, ["# failover"]);
+    assert!(node.leading.is_empty());
+    let written = value.to_ruby_with_comments(&comments);
+    assert_eq!(written, input);
+    assert_eq!(
+        nompom::parse_with_comments(&written, &options).unwrap(),
+        (value, comments)
+    );
+}
+
+#[test]
+fn write_ruby_writes_what_display_does() {
+    let value = parse(
+        r#"{:a => [1, -2.5, "x\ny", :"b c", nil, true], "d" => {e: #<User id: 1>}, nil => Foo::Bar}"#,
+    )
+    .unwrap();
+    let mut written = String::new();
+    value.write_ruby(&mut written).unwrap();
+    assert_eq!(written, value.to_string());
+    assert_eq!(written, value.to_ruby());
+}
+
+#[test]
+fn trailing_hash_arguments() {
+    let args = |input| {
+        let (name, args) = parse_call_args(input).unwrap();
+        (name, Value::Array(args).to_string())
+    };
+    assert_eq!(
+        args("foo(1, 2, a: 3, b: 4)"),
+        (
+            "foo".to_string(),
+            r#"[1, 2, {"a" => 3, "b" => 4}]"#.to_string()
+        )
+    );
+    assert_eq!(
+        args("Foo.bar?(:x => 1)"),
+        ("Foo.bar?".to_string(), "[{:x => 1}]".to_string())
+    );
+    assert_eq!(args("foo()"), ("foo".to_string(), "[]".to_string()));
+    assert_eq!(
+        parse_call_args("foo(1, a: 2, 3)").unwrap_err().to_string(),
+        "expected a hash entry after hash entries at offset 13"
+    );
+    // Array literals gather a trailing hash the same way.
+    assert_eq!(
+        parse("[1, a: 2, :b => 3]").unwrap().to_string(),
+        r#"[1, {:b => 3, "a" => 2}]"#
+    );
+}
+
+#[test]
+fn splats_in_call_arguments() {
+    let args = |input| {
+        let (_, args) = parse_call_args(input).unwrap();
+        Value::Array(args).to_string()
+    };
+    assert_eq!(args("foo(**{a: 1})"), r#"[{"a" => 1}]"#);
+    assert_eq!(
+        args("foo(1, *[2, 3], b: 4, **{c: 5})"),
+        r#"[1, 2, 3, {"b" => 4, "c" => 5}]"#
+    );
+    assert_eq!(args("foo(**{a: 1}, a: 2)"), r#"[{"a" => 2}]"#);
+    assert_eq!(args("foo(*[])"), "[]");
+    let error = |input| parse_call_args(input).unwrap_err().to_string();
+    assert_eq!(error("foo(*1)"), "expected an array after '*' at offset 5");
+    assert_eq!(
+        error("foo(**[1])"),
+        "expected a hash after '**' at offset 6"
+    );
+    assert_eq!(parse("[1, *[2, 3]]").unwrap().to_string(), "[1, 2, 3]");
+}
+
+#[test]
+fn sorted_keys_display_in_byte_order() {
+    let mut value = nompom::ObjectBuilder::new()
+        .key("b", 1)
+        .key("a", parse("{z: 1, :y => 2, Y: 3}").unwrap())
+        .key("B", 2)
+        .key("ab", 3)
+        .build();
+    value.sort_keys();
+    assert_eq!(
+        value.to_string(),
+        r#"{"B" => 2, "a" => {:y => 2, "Y" => 3, "z" => 1}, "ab" => 3, "b" => 1}"#
+    );
+}
@@ -0,0 +1,45 @@
+use nompom::{parse, Shape};
+
+#[test]
+fn inferred_shapes_match_their_example() {
+    let example = parse(
+        r#"{name: "x", :tag => :a, ratio: 1.5, none: nil, list: [1, 2], mixed: [1, "a"], empty: [], nested: {deep: [{id: 1, ok: true}]}, k: Foo}"#,
+    )
+    .unwrap();
+    let shape = example.infer_shape();
+    assert_eq!(
+        shape,
+        Shape::object(vec![
+            (":tag", Shape::Symbol),
+            ("empty", Shape::array(Shape::Any)),
+            ("k", Shape::Any),
+            ("list", Shape::array(Shape::Integer)),
+            ("mixed", Shape::array(Shape::Any)),
+            ("name", Shape::String),
+            (
+                "nested",
+                Shape::object(vec![(
+                    "deep",
+                    Shape::array(Shape::object(vec![
+                        ("id", Shape::Integer),
+                        ("ok", Shape::Boolean)
+                    ]))
+                )])
+            ),
+            ("none", Shape::Null),
+            ("ratio", Shape::Float),
+        ])
+    );
+    assert_eq!(example.matches_shape(&shape), Ok(()));
+
+    let list = parse("{list: [1, 2]}").unwrap().infer_shape();
+    let errors = parse(r#"{list: [1, 2.5], name: "x"}"#)
+        .unwrap()
+        .matches_shape(&list)
+        .unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "/list/1: expected integer, found float"
+    );
+}
@@ -0,0 +1,264 @@
+use nompom::{parse, parse_with_options, ParseOptions, Value};
+
+fn string(text: &str) -> Value {
+    Value::Bytes(text.to_string())
+}
+
+#[test]
+fn empty_strings() {
+    assert_eq!(parse(r#""""#).unwrap(), string(""));
+    assert_eq!(parse("''").unwrap(), string(""));
+    assert_eq!(parse(r#""" "#).unwrap(), string(""));
+    assert_eq!(
+        parse(r#"["", 1]"#).unwrap(),
+        Value::Array(vec![string(""), Value::Integer(1)])
+    );
+    assert_eq!(
+        parse(r#"{"" => ""}"#).unwrap(),
+        Value::Object(vec![(String::new(), string(""))].into_iter().collect())
+    );
+}
+
+#[test]
+fn empty_string_followed_by_input() {
+    let err = parse(r#"""extra"#).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected trailing input at offset 2");
+}
+
+#[test]
+fn string_length_limit() {
+    let options = ParseOptions {
+        max_string_len: Some(8),
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options).map_err(|err| err.to_string());
+    assert_eq!(parse(r#""12345678""#), Ok(string("12345678")));
+    assert_eq!(
+        parse(r#""123456789""#),
+        Err("string exceeds max_string_len of 8 at offset 0".to_string())
+    );
+    // Escapes count once decoded, so this is eight bytes.
+    assert_eq!(parse(r#""1234567\n""#), Ok(string("1234567\n")));
+    assert_eq!(
+        parse("{a: [1, '123456789']}"),
+        Err("string at /a/1 exceeds max_string_len of 8 at offset 8".to_string())
+    );
+    assert_eq!(
+        parse(r#"{"123456789" => 1}"#),
+        Err("string exceeds max_string_len of 8 at offset 1".to_string())
+    );
+}
+
+#[test]
+fn crlf_line_endings() {
+    assert_eq!(
+        parse("<<~EOS\r\n  a\r\n    b\r\nEOS\r\n").unwrap(),
+        string("a\n  b\n")
+    );
+    assert_eq!(parse("<<-EOS\r\nx\r\n  EOS").unwrap(), string("x\n"));
+    assert_eq!(parse("\"a\r\nb\"").unwrap(), string("a\nb"));
+    assert_eq!(parse("'a\r\nb'").unwrap(), string("a\nb"));
+    // A backslash before a CRLF joins the lines, as before an LF.
+    assert_eq!(parse("\"a\\\r\nb\"").unwrap(), string("ab"));
+    assert_eq!(
+        parse("%w[a\r\nb]").unwrap(),
+        Value::Array(vec![string("a"), string("b")])
+    );
+    assert_eq!(
+        parse("{\r\n  a: 1, # c\r\n  b: \"x\"\r\n}\r\n").unwrap(),
+        parse("{\n  a: 1, # c\n  b: \"x\"\n}\n").unwrap()
+    );
+}
+
+#[test]
+fn octal_escapes() {
+    assert_eq!(parse(r#""\101""#).unwrap(), string("A"));
+    assert_eq!(parse(r#""\0""#).unwrap(), string("\0"));
+    assert_eq!(parse(r#""\7""#).unwrap(), string("\x07"));
+    // At most three digits are read, and `8` isn't one.
+    assert_eq!(parse(r#""\1012""#).unwrap(), string("A2"));
+    assert_eq!(parse(r#""\08""#).unwrap(), string("\08"));
+    // Above `\377` wraps to the low byte, as in Ruby.
+    assert_eq!(parse(r#""\400""#).unwrap(), string("\0"));
+    // Bytes join into UTF-8, which they have to make.
+    assert_eq!(parse(r#""\303\251""#).unwrap(), string("é"));
+    assert_eq!(
+        parse(r#""\377""#).unwrap_err().to_string(),
+        "invalid escape sequence in bytes at offset 0"
+    );
+    // Single quotes don't have them.
+    assert_eq!(parse(r"'\101'").unwrap(), string(r"\101"));
+}
+
+#[test]
+fn percent_arrays_with_any_delimiter() {
+    let words = |words: &[&str]| Value::Array(words.iter().map(|word| string(word)).collect());
+    assert_eq!(parse("%w|a b|").unwrap(), words(&["a", "b"]));
+    assert_eq!(parse("%w!a b!").unwrap(), words(&["a", "b"]));
+    assert_eq!(parse("%w-a b-").unwrap(), words(&["a", "b"]));
+    assert_eq!(
+        parse("%i/c d/").unwrap(),
+        Value::Array(vec![
+            Value::Symbol("c".to_string()),
+            Value::Symbol("d".to_string())
+        ])
+    );
+    assert_eq!(parse(r"%w|a\|b c|").unwrap(), words(&["a|b", "c"]));
+    // Brackets nest, and other delimiters don't.
+    assert_eq!(parse("%w<a <b> c>").unwrap(), words(&["a", "<b>", "c"]));
+    assert_eq!(
+        parse("%w(a (b c) d)").unwrap(),
+        words(&["a", "(b", "c)", "d"])
+    );
+    assert!(parse("%wa b a").is_err());
+    assert!(parse("%w|a b").is_err());
+}
+
+#[test]
+fn unterminated_strings_are_reported_at_their_opening_quote() {
+    let message = |input| parse(input).unwrap_err().to_string();
+    assert_eq!(
+        message(r#""abc"#),
+        "unterminated string in bytes at offset 0"
+    );
+    assert_eq!(
+        message("{a: 'abc}"),
+        "unterminated string in bytes at offset 4"
+    );
+    assert_eq!(
+        message(r#"[1, "x\"]"#),
+        "unterminated string in bytes at offset 4"
+    );
+}
+
+#[test]
+fn lone_surrogate_escapes() {
+    use nompom::InvalidUnicode;
+
+    let parse = |input, on_invalid_unicode| {
+        let options = ParseOptions {
+            on_invalid_unicode,
+            ..Default::default()
+        };
+        parse_with_options(input, &options).map_err(|err| err.to_string())
+    };
+    assert_eq!(
+        parse(r#""\uD800""#, InvalidUnicode::Strict),
+        Err("invalid escape sequence in bytes at offset 0".to_string())
+    );
+    assert_eq!(
+        parse(r#""a\uD800b""#, InvalidUnicode::Replace),
+        Ok(string("a\u{FFFD}b"))
+    );
+    assert_eq!(
+        parse(r#""a\uD800b""#, InvalidUnicode::Preserve),
+        Ok(string(r"a\uD800b"))
+    );
+    assert_eq!(
+        parse(r#""\u{D800}""#, InvalidUnicode::Preserve),
+        Ok(string(r"\u{D800}"))
+    );
+    // Ruby has no surrogate pairs, so each half is invalid on its own.
+    assert_eq!(
+        parse(r#""\uD83D\uDE00""#, InvalidUnicode::Replace),
+        Ok(string("\u{FFFD}\u{FFFD}"))
+    );
+    assert!(parse(r#""\uD83D\uDE00""#, InvalidUnicode::Strict).is_err());
+}
+
+#[test]
+fn heredoc_whitespace() {
+    let body = |opener: &str, terminator: &str| {
+        let input = format!("[{}END, 1]\n  a  \n\n    b\t\n{}END\n", opener, terminator);
+        parse(&input).unwrap()
+    };
+    let with = |text: &str| Value::Array(vec![string(text), Value::Integer(1)]);
+    assert_eq!(body("<<", ""), with("  a  \n\n    b\t\n"));
+    // `<<-` only lets the terminator be indented.
+    assert_eq!(body("<<-", "  "), with("  a  \n\n    b\t\n"));
+    // `<<~` removes the common indentation and nothing else.
+    assert_eq!(body("<<~", "  "), with("a  \n\n  b\t\n"));
+}
+
+#[test]
+fn validating_escapes() {
+    let offset = |content| Value::validate_escapes(content).map_err(|error| error.offset);
+    assert_eq!(offset(r"é \u{1F600 41} \x41 \303\251"), Ok(()));
+    // Unknown escapes stand for their character, as in Ruby.
+    assert_eq!(offset(r"\q"), Ok(()));
+    assert_eq!(offset(r"\u12"), Err(0));
+    assert_eq!(offset(r"\uD800"), Err(0));
+    assert_eq!(offset(r"a\u{110000}"), Err(1));
+    assert_eq!(offset(r"\x"), Err(0));
+    assert_eq!(offset(r"\xZZ"), Err(0));
+    assert_eq!(offset(r"ok \303"), Err(3));
+    assert_eq!(offset(r"ok\"), Err(2));
+    assert_eq!(
+        Value::validate_escapes(r"\xZZ").unwrap_err().to_string(),
+        "invalid escape sequence at byte 0"
+    );
+}
+
+#[test]
+fn escaped_whitespace_and_delimiters_in_percent_arrays() {
+    let words = |words: &[&str]| Value::Array(words.iter().map(|word| string(word)).collect());
+    assert_eq!(parse(r"%w[a\ b c]").unwrap(), words(&["a b", "c"]));
+    assert_eq!(parse("%w[a\\\tb]").unwrap(), words(&["a\tb"]));
+    assert_eq!(parse("%w[a\\\nb c]").unwrap(), words(&["a\nb", "c"]));
+    assert_eq!(parse(r"%w[a\] b]").unwrap(), words(&["a]", "b"]));
+    assert_eq!(parse(r"%w(a\) b)").unwrap(), words(&["a)", "b"]));
+    // Other backslashes are kept, apart from an escaped backslash.
+    assert_eq!(parse(r"%w[a\\ b]").unwrap(), words(&[r"a\", "b"]));
+    assert_eq!(parse(r"%w[a\nb]").unwrap(), words(&[r"a\nb"]));
+    assert_eq!(
+        parse(r"%i[x\ y z]").unwrap(),
+        Value::Array(vec![
+            Value::Symbol("x y".to_string()),
+            Value::Symbol("z".to_string())
+        ])
+    );
+}
+
+#[test]
+fn tab_indented_heredocs() {
+    let parse = |input, tab_width| {
+        let options = ParseOptions {
+            tab_width,
+            ..Default::default()
+        };
+        parse_with_options(input, &options).unwrap()
+    };
+    assert_eq!(parse("<<~EOS\n\ta\n\t\tb\n\tEOS\n", 8), string("a\n\tb\n"));
+    // A tab is as wide as the spaces that reach the same column.
+    assert_eq!(parse("<<~EOS\n\ta\n        b\nEOS", 8), string("a\nb\n"));
+    assert_eq!(
+        parse("<<~EOS\n\ta\n        b\nEOS", 4),
+        string("a\n    b\n")
+    );
+    assert_eq!(parse("<<~EOS\n    a\n\tb\nEOS", 4), string("a\nb\n"));
+    // A tab that reaches past the indentation removed is kept.
+    assert_eq!(parse("<<~EOS\n    a\n\tb\nEOS", 8), string("a\n\tb\n"));
+    assert_eq!(parse("<<~EOS\n  \tx\n\ty\nEOS", 2), string("\tx\ny\n"));
+    assert_eq!(parse("<<~EOS\n  \tx\n\ty\nEOS", 0), string(" \tx\ny\n"));
+    assert_eq!(ParseOptions::default().tab_width, 8);
+}
+
+#[test]
+fn escaped_character_literals() {
+    assert_eq!(parse(r"?\n").unwrap(), string("\n"));
+    assert_eq!(parse(r"?\t").unwrap(), string("\t"));
+    assert_eq!(parse(r"?\s").unwrap(), string(" "));
+    assert_eq!(parse(r"?\x41").unwrap(), string("A"));
+    assert_eq!(parse(r"?\u{263A}").unwrap(), string("\u{263A}"));
+    assert_eq!(parse(r"?\u{1F600}").unwrap(), string("\u{1F600}"));
+    assert_eq!(parse("?z").unwrap(), string("z"));
+    assert_eq!(parse("?\u{263A}").unwrap(), string("\u{263A}"));
+    assert_eq!(
+        parse("[?a, ?b]").unwrap(),
+        Value::Array(vec![string("a"), string("b")])
+    );
+    assert_eq!(
+        parse("?ab").unwrap_err().to_string(),
+        "unexpected input at offset 0"
+    );
+}
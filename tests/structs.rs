@@ -0,0 +1,41 @@
+use nompom::{parse, Value};
+
+fn object(name: &str, fields: Vec<(&str, Value)>) -> Value {
+    Value::Struct {
+        name: name.to_string(),
+        fields: fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    }
+}
+
+#[test]
+fn nested_inspected_objects() {
+    assert_eq!(
+        parse("#<A b: #<B c: 1>>").unwrap(),
+        object(
+            "A",
+            vec![("b", object("B", vec![("c", Value::Integer(1))]))]
+        )
+    );
+    assert_eq!(
+        parse(r#"#<A b: #<B c: #<C d: "x > y <z">>, e: [1]>"#).unwrap(),
+        object(
+            "A",
+            vec![
+                (
+                    "b",
+                    object(
+                        "B",
+                        vec![(
+                            "c",
+                            object("C", vec![("d", Value::Bytes("x > y <z".to_string()))])
+                        )]
+                    )
+                ),
+                ("e", Value::Array(vec![Value::Integer(1)])),
+            ]
+        )
+    );
+}
@@ -0,0 +1,79 @@
+use nompom::{parse, parse_with_options, ParseOptions, Value};
+
+fn symbol(name: &str) -> Value {
+    Value::Symbol(name.to_string())
+}
+
+#[test]
+fn single_quoted_symbols() {
+    assert_eq!(parse(r":'foo'").unwrap(), symbol("foo"));
+    assert_eq!(parse(r":'it\'s'").unwrap(), symbol("it's"));
+    assert_eq!(parse(r":'a\\b'").unwrap(), symbol(r"a\b"));
+    // Only `\\` and `\'` are escapes in single quotes.
+    assert_eq!(parse(r":'a\nb'").unwrap(), symbol(r"a\nb"));
+    assert_eq!(parse(r#":"a\nb""#).unwrap(), symbol("a\nb"));
+}
+
+#[test]
+fn leading_underscore_symbols() {
+    assert_eq!(parse(":_x").unwrap(), symbol("_x"));
+    assert_eq!(parse(":__y").unwrap(), symbol("__y"));
+    // `__END__` and `__FILE__` are only special as barewords.
+    assert_eq!(parse(":__END__").unwrap(), symbol("__END__"));
+    assert_eq!(parse(":__FILE__").unwrap(), symbol("__FILE__"));
+    let value = parse("{:_x => [:__y]}").unwrap();
+    assert_eq!(parse(&value.to_string()).unwrap(), value);
+}
+
+#[test]
+fn dotted_symbol_keys() {
+    let value = parse(r#"{ :"a.b.c" => :"x.y" }"#).unwrap();
+    assert_eq!(
+        value.get(":a.b.c").and_then(Value::symbol).as_deref(),
+        Some("x.y")
+    );
+    assert_eq!(value.to_string(), r#"{:"a.b.c" => :"x.y"}"#);
+
+    let options = ParseOptions {
+        split_dotted_keys: true,
+        ..Default::default()
+    };
+    let parse = |input| parse_with_options(input, &options).unwrap();
+    // What Ruby writes as `{a: {b: 1}}`.
+    assert_eq!(parse(r#"{ :"a.b" => 1 }"#), parse("{:a => {:b => 1}}"));
+    assert_eq!(
+        parse(r#"{:"a.b" => 1, :"a.c" => 2, "d.e" => 3}"#).to_string(),
+        r#"{:a => {:b => 1, :c => 2}, "d.e" => 3}"#
+    );
+}
+
+#[test]
+fn operator_symbols() {
+    let value = parse("{:+ => :[]=, :<=> => :[], :[]= => :-@}").unwrap();
+    assert_eq!(value.get(":+"), Some(&symbol("[]=")));
+    assert_eq!(value.get(":<=>"), Some(&symbol("[]")));
+    assert_eq!(value.get(":[]="), Some(&symbol("-@")));
+    assert_eq!(parse(&value.to_string()).unwrap(), value);
+    assert_eq!(
+        parse("[:==, :!, :**, :<<, :=~]").unwrap(),
+        Value::Array(
+            ["==", "!", "**", "<<", "=~"]
+                .iter()
+                .map(|name| symbol(name))
+                .collect()
+        )
+    );
+}
+
+#[test]
+fn quoted_symbols_with_special_characters() {
+    assert_eq!(parse(r#":"a b""#).unwrap(), symbol("a b"));
+    assert_eq!(parse(r#":"with\"quote""#).unwrap(), symbol("with\"quote"));
+    assert_eq!(parse(r#":"""#).unwrap(), symbol(""));
+    let value = parse(r#"{:"weird key!" => :'x y'}"#).unwrap();
+    assert_eq!(value.get(":weird key!"), Some(&symbol("x y")));
+    // Names that aren't bare identifiers are quoted again on the way out.
+    assert_eq!(value.to_string(), r#"{:"weird key!" => :"x y"}"#);
+    assert_eq!(symbol("with\"quote").to_string(), r#":"with\"quote""#);
+    assert_eq!(symbol("ok?").to_string(), ":ok?");
+}